@@ -16,10 +16,14 @@ use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
-use crate::error::{CryptoError, CryptoResult};
-use crate::security::create_secure_tempfile;
+use crate::commands::file_utils::create_new_or_next;
+use crate::crypto::CopyProgress;
+use crate::error::{CryptoError, CryptoResult, IoPathExt};
+use crate::security::{create_secure_tempfile, persist_tempfile};
 use chrono::Local;
+use serde::Serialize;
 use tar::{Archive, Builder, EntryType};
+use zeroize::Zeroizing;
 
 #[cfg(windows)]
 use crate::security::set_owner_only_dacl;
@@ -30,8 +34,9 @@ use crate::security::set_owner_only_dacl;
 /// This ratio check is combined with an absolute size limit for defense in depth.
 const MAX_DECOMPRESSION_RATIO: u64 = 100;
 
-/// Absolute maximum extracted size in bytes (10 GB).
+/// Default absolute maximum extracted size in bytes (10 GB).
 /// This provides a hard cap regardless of archive size to prevent resource exhaustion.
+/// Callers of [`extract_tar_zstd_archive`] may pass their own cap instead.
 /// Even with the ratio check, a very large archive could theoretically extract to
 /// an unreasonably large size, so this cap provides an additional safety layer.
 const MAX_EXTRACTED_SIZE_BYTES: u64 = 10 * 1024 * 1024 * 1024; // 10 GB
@@ -39,6 +44,15 @@ const MAX_EXTRACTED_SIZE_BYTES: u64 = 10 * 1024 * 1024 * 1024; // 10 GB
 /// Default ZSTD compression level for archives
 const ARCHIVE_COMPRESSION_LEVEL: i32 = 3;
 
+/// Files up to this size are read ahead in parallel; larger ones are streamed (4 MB)
+const PREREAD_MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Upper bound on file contents held in read-ahead buffers at once (64 MB)
+const PREREAD_MAX_BUFFERED: u64 = 64 * 1024 * 1024;
+
+/// Maximum number of threads reading files ahead
+const PREREAD_MAX_THREADS: usize = 8;
+
 /// Progress callback type for archive operations
 pub type ArchiveProgressCallback = Box<dyn Fn(usize, usize, &str) + Send + Sync>;
 
@@ -52,24 +66,49 @@ pub type ArchiveProgressCallback = Box<dyn Fn(usize, usize, &str) + Send + Sync>
 /// * `output_path` - Where to write the .tar.zst archive
 /// * `progress_callback` - Optional callback (files_processed, total_files, current_file)
 ///
+/// An existing file at `output_path` is never replaced: the archive is
+/// written to the next free collision name instead (e.g. "files (1).tar.zst").
+///
 /// # Returns
-/// Ok(()) on success, or CryptoError on failure
+/// The path the archive was written to, or CryptoError on failure
 pub fn create_tar_zstd_archive<P, Q>(
     input_paths: &[P],
     output_path: Q,
     progress_callback: Option<ArchiveProgressCallback>,
-) -> CryptoResult<()>
+) -> CryptoResult<PathBuf>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
 {
+    build_tar_zstd_archive(input_paths, output_path.as_ref(), progress_callback, true)
+}
+
+/// A validated input file and its name inside the archive
+struct PendingEntry {
+    path: PathBuf,
+    archive_name: PathBuf,
+    size: u64,
+}
+
+/// Create the archive, optionally reading small files ahead in parallel
+///
+/// TAR output is sequential and the whole archive is one ZSTD stream, so
+/// entries are always appended in input order from the calling thread. With
+/// `preread`, runs of small files are first read into memory by up to
+/// [`PREREAD_MAX_THREADS`] threads (at most [`PREREAD_MAX_BUFFERED`] bytes at
+/// a time), which hides per-file open/read latency for large file counts.
+fn build_tar_zstd_archive<P: AsRef<Path>>(
+    input_paths: &[P],
+    output_path: &Path,
+    progress_callback: Option<ArchiveProgressCallback>,
+    preread: bool,
+) -> CryptoResult<PathBuf> {
     if input_paths.is_empty() {
         return Err(CryptoError::FormatError(
             "No files provided for archive".to_string(),
         ));
     }
 
-    let output_path = output_path.as_ref();
     let parent = output_path.parent().unwrap_or_else(|| Path::new("."));
 
     // Create secure temp file
@@ -77,7 +116,7 @@ where
     let temp_path = temp_file.path().to_path_buf();
 
     // Create ZSTD compressed writer
-    let file = File::create(&temp_path)?;
+    let file = File::create(&temp_path).with_path(&temp_path)?;
     let zstd_writer =
         zstd::Encoder::new(BufWriter::new(file), ARCHIVE_COMPRESSION_LEVEL)?.auto_finish();
 
@@ -93,57 +132,148 @@ where
 
     let total_files = input_paths.len();
 
-    for (index, input_path) in input_paths.iter().enumerate() {
-        let input_path = input_path.as_ref();
-
-        // Validate input path
-        let canonical_path = validate_archive_input(input_path)?;
-
-        // Get filename for progress
-        let file_name = canonical_path
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_default();
+    // Validate every input and compute its archive entry name up front
+    let entries = paths
+        .iter()
+        .map(|input_path| {
+            let path = validate_archive_input(input_path)?;
+            let archive_name = compute_archive_entry_name(&path, &common_prefix)?;
+            let size = fs::metadata(&path).with_path(&path)?.len();
+            Ok(PendingEntry {
+                path,
+                archive_name,
+                size,
+            })
+        })
+        .collect::<CryptoResult<Vec<_>>>()?;
 
-        // Report progress
+    let report_progress = |index: usize, entry: &PendingEntry| {
         if let Some(ref callback) = progress_callback {
+            let file_name = entry
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
             callback(index, total_files, &file_name);
         }
+    };
 
-        // Compute archive entry name (relative path from common prefix)
-        let archive_name = compute_archive_entry_name(&canonical_path, &common_prefix)?;
+    let mut index = 0;
+    while index < total_files {
+        let window_end = if preread {
+            preread_window_end(&entries, index)
+        } else {
+            index
+        };
 
-        // Add file to archive
-        let mut file = File::open(&canonical_path)?;
-        tar_builder.append_file(&archive_name, &mut file)?;
+        // Large file (or read-ahead disabled): stream it straight into the archive
+        if window_end == index {
+            let entry = &entries[index];
+            report_progress(index, entry);
+            let mut file = File::open(&entry.path).with_path(&entry.path)?;
+            tar_builder.append_file(&entry.archive_name, &mut file)?;
+            index += 1;
+            continue;
+        }
+
+        let window = &entries[index..window_end];
+        for (entry, preread) in window.iter().zip(preread_files(window)?) {
+            report_progress(index, entry);
+            let (metadata, contents) = preread;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&metadata);
+            header.set_size(contents.len() as u64);
+            tar_builder.append_data(&mut header, &entry.archive_name, contents.as_slice())?;
+            index += 1;
+        }
     }
 
     // Finish TAR archive
     let zstd_writer = tar_builder.into_inner()?;
     drop(zstd_writer); // Ensure ZSTD encoder is flushed
 
-    // Persist temp file to output path
-    fs::rename(&temp_path, output_path).map_err(|e| {
-        let _ = fs::remove_file(&temp_path);
-        CryptoError::Io(e)
-    })?;
+    // Reserve a name nobody else holds, then replace the empty placeholder
+    let (final_path, placeholder) = create_new_or_next(output_path)?;
+    drop(placeholder);
+    if let Err(err) = persist_tempfile(temp_file, &final_path) {
+        let _ = fs::remove_file(err.file.path());
+        let _ = fs::remove_file(&final_path);
+        return Err(err.error).with_path(&final_path);
+    }
 
     // Report completion
     if let Some(ref callback) = progress_callback {
         callback(total_files, total_files, "");
     }
 
-    Ok(())
+    Ok(final_path)
+}
+
+/// End of the run of small files starting at `start` that fits the read-ahead budget
+///
+/// Returns `start` itself when the file at `start` is too large to buffer.
+fn preread_window_end(entries: &[PendingEntry], start: usize) -> usize {
+    let mut buffered = 0u64;
+    let mut end = start;
+    for entry in &entries[start..] {
+        if entry.size > PREREAD_MAX_FILE_SIZE || buffered + entry.size > PREREAD_MAX_BUFFERED {
+            break;
+        }
+        buffered += entry.size;
+        end += 1;
+    }
+    end
+}
+
+/// Read every file in `window` into memory using a few threads, in input order
+fn preread_files(window: &[PendingEntry]) -> CryptoResult<Vec<(fs::Metadata, Zeroizing<Vec<u8>>)>> {
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, PREREAD_MAX_THREADS);
+    let per_thread = window.len().div_ceil(threads);
+
+    let read_one = |entry: &PendingEntry| -> CryptoResult<(fs::Metadata, Zeroizing<Vec<u8>>)> {
+        let mut file = File::open(&entry.path).with_path(&entry.path)?;
+        let metadata = file.metadata().with_path(&entry.path)?;
+        let mut contents = Zeroizing::new(Vec::with_capacity(entry.size as usize));
+        file.read_to_end(&mut contents)?;
+        Ok((metadata, contents))
+    };
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = window
+            .chunks(per_thread)
+            .map(|part| scope.spawn(move || part.iter().map(read_one).collect::<Vec<_>>()))
+            .collect();
+        let mut results = Vec::with_capacity(window.len());
+        for handle in handles {
+            let part = handle.join().map_err(|_| {
+                CryptoError::Io(std::io::Error::other("File read-ahead thread panicked"))
+            })?;
+            for result in part {
+                results.push(result?);
+            }
+        }
+        Ok(results)
+    })
 }
 
 /// Extract a compressed TAR archive to a directory
 ///
 /// Validates archive entries for security (path traversal, symlinks, decompression bombs).
 ///
+/// The decompression ratio is measured against `archive_path` itself, so for
+/// encrypted archives this must be the decrypted .tar.zst (never the encrypted
+/// file, whose size includes headers and tags). Both passes compare against
+/// the same limit: the first sums header-declared sizes, the second counts
+/// bytes actually written.
+///
 /// # Arguments
 /// * `archive_path` - Path to the .tar.zst archive
 /// * `output_dir` - Directory where files will be extracted
 /// * `allow_overwrite` - Whether to overwrite existing files
+/// * `max_extracted_size` - Absolute cap on extracted bytes (default: 10 GB)
 /// * `progress_callback` - Optional callback (files_processed, total_files, current_file)
 ///
 /// # Returns
@@ -152,6 +282,7 @@ pub fn extract_tar_zstd_archive<P, Q>(
     archive_path: P,
     output_dir: Q,
     allow_overwrite: bool,
+    max_extracted_size: Option<u64>,
     progress_callback: Option<ArchiveProgressCallback>,
 ) -> CryptoResult<Vec<PathBuf>>
 where
@@ -168,53 +299,24 @@ where
         ));
     }
 
-    // Calculate decompression bomb limits using a combined approach:
-    // 1. Ratio-based limit: archive size * MAX_DECOMPRESSION_RATIO (100x)
-    // 2. Absolute limit: MAX_EXTRACTED_SIZE_BYTES (10 GB hard cap)
-    // The effective limit is the minimum of these two values.
-    let archive_size = fs::metadata(archive_path)?.len();
-    let ratio_based_limit = archive_size.saturating_mul(MAX_DECOMPRESSION_RATIO);
-    let max_extracted_size = ratio_based_limit.min(MAX_EXTRACTED_SIZE_BYTES);
+    let absolute_cap = max_extracted_size.unwrap_or(MAX_EXTRACTED_SIZE_BYTES);
+    let max_extracted_size = max_extracted_size_for(
+        fs::metadata(archive_path).with_path(archive_path)?.len(),
+        absolute_cap,
+    );
 
     // Open archive with ZSTD decompression
-    let file = File::open(archive_path)?;
+    let file = File::open(archive_path).with_path(archive_path)?;
     let zstd_reader = zstd::Decoder::new(BufReader::new(file))?;
     let mut archive = Archive::new(zstd_reader);
 
-    // First pass: count entries and validate
-    let file = File::open(archive_path)?;
-    let zstd_reader = zstd::Decoder::new(BufReader::new(file))?;
-    let mut count_archive = Archive::new(zstd_reader);
-
-    let mut total_files = 0usize;
-    let mut total_size = 0u64;
-
-    for entry in count_archive.entries()? {
-        let entry = entry?;
-
-        // Validate entry
-        validate_archive_entry(&entry)?;
-
-        total_size = total_size.saturating_add(entry.size());
-        total_files += 1;
-
-        // Check for decompression bomb (combined ratio + absolute limit check)
-        if total_size > max_extracted_size {
-            let limit_type = if max_extracted_size == MAX_EXTRACTED_SIZE_BYTES {
-                "absolute limit of 10 GB"
-            } else {
-                "100x compression ratio limit"
-            };
-            return Err(CryptoError::ArchiveError(format!(
-                "Archive extraction would exceed safe size limit ({} bytes, {})",
-                max_extracted_size, limit_type
-            )));
-        }
-    }
+    // First pass: count entries and validate, failing on the first problem
+    let validation = scan_archive_entries(archive_path, max_extracted_size, absolute_cap, true)?;
+    let total_files = validation.total_entries;
 
     // Second pass: extract files
     let mut extracted_paths = Vec::with_capacity(total_files);
-    let canonical_output = fs::canonicalize(output_dir)?;
+    let canonical_output = fs::canonicalize(output_dir).with_path(output_dir)?;
     let mut total_bytes_written: u64 = 0;
 
     for (index, entry) in archive.entries()?.enumerate() {
@@ -255,7 +357,7 @@ where
         } else {
             // Create parent directories if needed
             if let Some(parent) = safe_output_path.parent() {
-                fs::create_dir_all(parent)?;
+                fs::create_dir_all(parent).with_path(parent)?;
             }
             let bytes = extract_entry_to_path(&mut entry, &safe_output_path)?;
             extracted_paths.push(safe_output_path);
@@ -281,18 +383,262 @@ where
     Ok(extracted_paths)
 }
 
+/// Safety report for an archive, produced without extracting anything
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchiveValidation {
+    /// Number of entries in the archive, including rejected ones
+    pub total_entries: usize,
+    /// Sum of header-declared sizes of accepted entries
+    pub total_size: u64,
+    /// Decompression bomb limit that applies to this archive
+    pub max_extracted_size: u64,
+    /// Whether `total_size` exceeds `max_extracted_size`
+    pub exceeds_limit: bool,
+    /// Entries that extraction would refuse, with the reason
+    pub rejected_entries: Vec<RejectedEntry>,
+}
+
+impl ArchiveValidation {
+    /// True when extraction would succeed the validation pass
+    pub fn is_safe(&self) -> bool {
+        !self.exceeds_limit && self.rejected_entries.is_empty()
+    }
+}
+
+/// An archive entry that failed validation
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedEntry {
+    /// Entry path as recorded in the archive
+    pub path: String,
+    /// Why the entry was rejected
+    pub reason: String,
+}
+
+/// Validate a compressed TAR archive without extracting it (dry run)
+///
+/// Runs the same checks as the first pass of [`extract_tar_zstd_archive`]
+/// (path traversal, symlinks, decompression bomb limit) but collects every
+/// finding instead of stopping at the first one.
+///
+/// # Arguments
+/// * `archive_path` - Path to the .tar.zst archive
+///
+/// # Returns
+/// The validation report, or CryptoError if the archive cannot be read
+pub fn validate_archive<P: AsRef<Path>>(archive_path: P) -> CryptoResult<ArchiveValidation> {
+    let archive_path = archive_path.as_ref();
+    let max_extracted_size = max_extracted_size_for(
+        fs::metadata(archive_path).with_path(archive_path)?.len(),
+        MAX_EXTRACTED_SIZE_BYTES,
+    );
+    scan_archive_entries(
+        archive_path,
+        max_extracted_size,
+        MAX_EXTRACTED_SIZE_BYTES,
+        false,
+    )
+}
+
+/// How extraction would treat an archive entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveEntryKind {
+    /// Regular file that extraction writes out
+    Regular,
+    /// Directory, hard link, or other entry that extraction silently skips
+    Skipped,
+    /// Entry that makes extraction fail (path traversal, symlink)
+    Rejected,
+}
+
+/// One entry in an archive listing
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntryInfo {
+    /// Entry path as recorded in the archive
+    pub path: String,
+    /// How extraction would treat the entry
+    pub kind: ArchiveEntryKind,
+    /// Lowercase extension without the dot (None if the name has none)
+    pub extension: Option<String>,
+    /// Header-declared size in bytes
+    pub size: u64,
+}
+
+/// Regular entries sharing one extension
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtensionSummary {
+    /// Lowercase extension without the dot (None for names without one)
+    pub extension: Option<String>,
+    /// Number of entries with this extension
+    pub count: usize,
+    /// Sum of the entries' sizes in bytes
+    pub total_size: u64,
+}
+
+/// Archive contents together with a per-extension summary
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveListing {
+    /// Every entry, in archive order
+    pub entries: Vec<ArchiveEntryInfo>,
+    /// Regular entries grouped by extension (see [`group_by_extension`])
+    pub by_extension: Vec<ExtensionSummary>,
+}
+
+/// List the entries of a compressed TAR archive without extracting it
+///
+/// # Arguments
+/// * `archive_path` - Path to the .tar.zst archive
+///
+/// # Returns
+/// Entries in archive order, or CryptoError if the archive cannot be read
+pub fn list_archive<P: AsRef<Path>>(archive_path: P) -> CryptoResult<Vec<ArchiveEntryInfo>> {
+    let file = File::open(archive_path.as_ref()).with_path(archive_path.as_ref())?;
+    let zstd_reader = zstd::Decoder::new(BufReader::new(file))?;
+    let mut archive = Archive::new(zstd_reader);
+
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let kind = if validate_archive_entry(&entry).is_err() {
+            ArchiveEntryKind::Rejected
+        } else if matches!(
+            entry.header().entry_type(),
+            EntryType::Regular | EntryType::Continuous
+        ) {
+            ArchiveEntryKind::Regular
+        } else {
+            ArchiveEntryKind::Skipped
+        };
+        let extension = Path::new(&path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        entries.push(ArchiveEntryInfo {
+            path,
+            kind,
+            extension,
+            size: entry.size(),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Group regular entries by extension, largest total size first
+///
+/// Skipped and rejected entries are left out, since extraction would not
+/// produce them. Ties are ordered by extension so the result is stable.
+pub fn group_by_extension(entries: &[ArchiveEntryInfo]) -> Vec<ExtensionSummary> {
+    let mut groups: Vec<ExtensionSummary> = Vec::new();
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.kind == ArchiveEntryKind::Regular)
+    {
+        match groups
+            .iter_mut()
+            .find(|group| group.extension == entry.extension)
+        {
+            Some(group) => {
+                group.count += 1;
+                group.total_size = group.total_size.saturating_add(entry.size);
+            }
+            None => groups.push(ExtensionSummary {
+                extension: entry.extension.clone(),
+                count: 1,
+                total_size: entry.size,
+            }),
+        }
+    }
+
+    groups.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    groups
+}
+
+/// Calculate the decompression bomb limit using a combined approach:
+/// 1. Ratio-based limit: archive size * MAX_DECOMPRESSION_RATIO (100x)
+/// 2. Absolute limit: `absolute_cap` (MAX_EXTRACTED_SIZE_BYTES by default)
+///
+/// The effective limit is the minimum of these two values.
+fn max_extracted_size_for(archive_size: u64, absolute_cap: u64) -> u64 {
+    let ratio_based_limit = archive_size.saturating_mul(MAX_DECOMPRESSION_RATIO);
+    ratio_based_limit.min(absolute_cap)
+}
+
+/// Walk all archive entries, validating each and totalling declared sizes.
+///
+/// With `strict`, the first rejected entry or exceeded limit is returned as an
+/// error; otherwise findings are recorded in the returned report.
+fn scan_archive_entries(
+    archive_path: &Path,
+    max_extracted_size: u64,
+    absolute_cap: u64,
+    strict: bool,
+) -> CryptoResult<ArchiveValidation> {
+    let file = File::open(archive_path).with_path(archive_path)?;
+    let zstd_reader = zstd::Decoder::new(BufReader::new(file))?;
+    let mut archive = Archive::new(zstd_reader);
+
+    let mut validation = ArchiveValidation {
+        max_extracted_size,
+        ..Default::default()
+    };
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        validation.total_entries += 1;
+
+        // Validate entry
+        if let Err(err) = validate_archive_entry(&entry) {
+            if strict {
+                return Err(err);
+            }
+            validation.rejected_entries.push(RejectedEntry {
+                path: String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+                reason: err.to_string(),
+            });
+            continue;
+        }
+
+        validation.total_size = validation.total_size.saturating_add(entry.size());
+
+        // Check for decompression bomb (combined ratio + absolute limit check)
+        if validation.total_size > max_extracted_size {
+            if strict {
+                let limit_type = if max_extracted_size == absolute_cap {
+                    "absolute size limit"
+                } else {
+                    "100x compression ratio limit"
+                };
+                return Err(CryptoError::ArchiveError(format!(
+                    "Archive extraction would exceed safe size limit ({} bytes, {})",
+                    max_extracted_size, limit_type
+                )));
+            }
+            validation.exceeds_limit = true;
+        }
+    }
+
+    Ok(validation)
+}
+
 /// Validate an input file path for archiving
 fn validate_archive_input(path: &Path) -> CryptoResult<PathBuf> {
     // Check path exists
     if !path.exists() {
-        return Err(CryptoError::Io(std::io::Error::new(
+        return Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
-            format!("File not found: {}", path.display()),
-        )));
+            "File not found",
+        ))
+        .with_path(path);
     }
 
     // Check it's a regular file (not a symlink or directory)
-    let metadata = fs::symlink_metadata(path)?;
+    let metadata = fs::symlink_metadata(path).with_path(path)?;
     if metadata.file_type().is_symlink() {
         return Err(CryptoError::InvalidPath(
             "Symlinks are not allowed for security reasons".to_string(),
@@ -305,7 +651,7 @@ fn validate_archive_input(path: &Path) -> CryptoResult<PathBuf> {
     }
 
     // Return canonical path
-    fs::canonicalize(path).map_err(CryptoError::Io)
+    fs::canonicalize(path).with_path(path)
 }
 
 /// Validate an archive entry for security
@@ -394,17 +740,18 @@ fn normalize_path(path: &Path) -> PathBuf {
 fn extract_entry_to_path<R: Read>(entry: &mut tar::Entry<R>, path: &Path) -> CryptoResult<u64> {
     // Create parent directories if needed
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+        fs::create_dir_all(parent).with_path(parent)?;
     }
 
     // Create file with secure permissions
     let mut file = create_output_file(path)?;
 
-    // Copy data
-    let bytes_written = std::io::copy(entry, &mut file)?;
-    file.flush()?;
+    // Copy data, counting what actually reached the file
+    let mut writer = CopyProgress::new(&mut file, |_| {});
+    std::io::copy(entry, &mut writer)?;
+    writer.flush()?;
 
-    Ok(bytes_written)
+    Ok(writer.bytes_written())
 }
 
 /// Create an output file with secure permissions
@@ -417,13 +764,14 @@ fn create_output_file(path: &Path) -> CryptoResult<File> {
             .create(true)
             .truncate(true)
             .mode(0o600)
-            .open(path)?;
+            .open(path)
+            .with_path(path)?;
         Ok(file)
     }
 
     #[cfg(windows)]
     {
-        let file = File::create(path)?;
+        let file = File::create(path).with_path(path)?;
         if let Err(e) = set_owner_only_dacl(path) {
             let _ = fs::remove_file(path);
             return Err(CryptoError::Io(e.into()));
@@ -433,7 +781,7 @@ fn create_output_file(path: &Path) -> CryptoResult<File> {
 
     #[cfg(not(any(unix, windows)))]
     {
-        Ok(File::create(path)?)
+        File::create(path).with_path(path)
     }
 }
 
@@ -658,7 +1006,8 @@ mod tests {
         assert!(archive_path.exists());
 
         // Extract archive
-        let extracted = extract_tar_zstd_archive(&archive_path, &extract_dir, false, None).unwrap();
+        let extracted =
+            extract_tar_zstd_archive(&archive_path, &extract_dir, false, None, None).unwrap();
         assert_eq!(extracted.len(), 2);
 
         // Verify content
@@ -671,6 +1020,189 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_archive_never_replaces_existing_output() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("input.txt");
+        fs::write(&input, b"archived").unwrap();
+        let archive_path = temp.path().join("files.tar.zst");
+        fs::write(&archive_path, b"unrelated").unwrap();
+
+        let written = create_tar_zstd_archive(&[&input], &archive_path, None).unwrap();
+        assert_eq!(written, temp.path().join("files.tar (1).zst"));
+        assert_eq!(fs::read(&archive_path).unwrap(), b"unrelated");
+
+        let extract_dir = temp.path().join("out");
+        fs::create_dir(&extract_dir).unwrap();
+        let extracted =
+            extract_tar_zstd_archive(&written, &extract_dir, false, None, None).unwrap();
+        assert_eq!(fs::read(&extracted[0]).unwrap(), b"archived");
+    }
+
+    #[test]
+    fn test_preread_archive_matches_serial() {
+        use rand::{rngs::SysRng, TryRng};
+
+        let temp = tempdir().unwrap();
+        let input_dir = temp.path().join("input");
+        fs::create_dir_all(input_dir.join("sub")).unwrap();
+
+        // Many small files plus one too large to buffer, so both paths run
+        let mut inputs = Vec::new();
+        for i in 0..40 {
+            let dir = if i % 3 == 0 {
+                input_dir.join("sub")
+            } else {
+                input_dir.clone()
+            };
+            let path = dir.join(format!("file{i}.txt"));
+            fs::write(&path, format!("contents of file {i}\n").repeat(i + 1)).unwrap();
+            inputs.push(path);
+        }
+        let large = input_dir.join("large.bin");
+        let mut large_content = vec![0u8; PREREAD_MAX_FILE_SIZE as usize + 17];
+        SysRng.try_fill_bytes(&mut large_content).unwrap();
+        fs::write(&large, &large_content).unwrap();
+        inputs.insert(20, large);
+
+        let serial_archive = temp.path().join("serial.tar.zst");
+        let parallel_archive = temp.path().join("parallel.tar.zst");
+        build_tar_zstd_archive(&inputs, &serial_archive, None, false).unwrap();
+        create_tar_zstd_archive(&inputs, &parallel_archive, None).unwrap();
+
+        let extract = |archive: &Path, name: &str| {
+            let dir = temp.path().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            let mut files: Vec<(PathBuf, Vec<u8>)> =
+                extract_tar_zstd_archive(archive, &dir, false, None, None)
+                    .unwrap()
+                    .into_iter()
+                    .map(|path| {
+                        let content = fs::read(&path).unwrap();
+                        (path.strip_prefix(&dir).unwrap().to_path_buf(), content)
+                    })
+                    .collect();
+            files.sort();
+            files
+        };
+        let serial = extract(&serial_archive, "serial");
+        let parallel = extract(&parallel_archive, "parallel");
+        assert_eq!(serial.len(), inputs.len());
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_validate_archive_well_formed() {
+        let temp = tempdir().unwrap();
+        let file1 = temp.path().join("file1.txt");
+        let file2 = temp.path().join("file2.txt");
+        fs::write(&file1, b"content1").unwrap();
+        fs::write(&file2, b"longer content2").unwrap();
+
+        let archive_path = temp.path().join("test.tar.zst");
+        create_tar_zstd_archive(&[&file1, &file2], &archive_path, None).unwrap();
+
+        let validation = validate_archive(&archive_path).unwrap();
+        assert_eq!(validation.total_entries, 2);
+        assert_eq!(validation.total_size, 23);
+        assert!(validation.rejected_entries.is_empty());
+        assert!(!validation.exceeds_limit);
+        assert!(validation.is_safe());
+    }
+
+    #[test]
+    fn test_list_archive_groups_by_extension() {
+        let temp = tempdir().unwrap();
+        let archive_path = temp.path().join("mixed.tar.zst");
+
+        let file = File::create(&archive_path).unwrap();
+        let encoder = zstd::Encoder::new(file, ARCHIVE_COMPRESSION_LEVEL)
+            .unwrap()
+            .auto_finish();
+        let mut builder = Builder::new(encoder);
+        let entries: [(&str, EntryType, &[u8]); 6] = [
+            ("photos/a.jpg", EntryType::Regular, b"jpeg data one"),
+            ("photos/b.JPG", EntryType::Regular, b"jpeg two"),
+            ("notes.txt", EntryType::Regular, b"hi"),
+            ("README", EntryType::Regular, b"read me"),
+            ("photos/", EntryType::Directory, b""),
+            ("link.txt", EntryType::Symlink, b""),
+        ];
+        for (name, entry_type, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(name).unwrap();
+            header.set_size(data.len() as u64);
+            header.set_entry_type(entry_type);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap();
+
+        let listing = list_archive(&archive_path).unwrap();
+        assert_eq!(listing.len(), 6);
+        assert_eq!(listing[1].extension.as_deref(), Some("jpg"));
+        assert_eq!(listing[3].extension, None);
+        assert_eq!(listing[4].kind, ArchiveEntryKind::Skipped);
+        assert_eq!(listing[5].kind, ArchiveEntryKind::Rejected);
+
+        let groups = group_by_extension(&listing);
+        assert_eq!(
+            groups,
+            vec![
+                ExtensionSummary {
+                    extension: Some("jpg".to_string()),
+                    count: 2,
+                    total_size: 21,
+                },
+                ExtensionSummary {
+                    extension: None,
+                    count: 1,
+                    total_size: 7,
+                },
+                ExtensionSummary {
+                    extension: Some("txt".to_string()),
+                    count: 1,
+                    total_size: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_archive_reports_path_traversal() {
+        let temp = tempdir().unwrap();
+        let archive_path = temp.path().join("evil.tar.zst");
+
+        // tar::Builder refuses ".." paths, so write the raw header name
+        let file = File::create(&archive_path).unwrap();
+        let encoder = zstd::Encoder::new(file, ARCHIVE_COMPRESSION_LEVEL)
+            .unwrap()
+            .auto_finish();
+        let mut builder = Builder::new(encoder);
+        for (name, data) in [(&b"ok.txt"[..], &b"fine"[..]), (b"../evil.txt", b"bad")] {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name);
+            header.set_size(data.len() as u64);
+            header.set_entry_type(EntryType::Regular);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap();
+
+        let validation = validate_archive(&archive_path).unwrap();
+        assert_eq!(validation.total_entries, 2);
+        assert_eq!(validation.total_size, 4);
+        assert_eq!(validation.rejected_entries.len(), 1);
+        assert_eq!(validation.rejected_entries[0].path, "../evil.txt");
+        assert!(!validation.is_safe());
+
+        // Extraction still refuses the archive outright
+        let extract_dir = temp.path().join("extract");
+        fs::create_dir_all(&extract_dir).unwrap();
+        let result = extract_tar_zstd_archive(&archive_path, &extract_dir, false, None, None);
+        assert!(matches!(result, Err(CryptoError::PathTraversal(_))));
+    }
+
     #[test]
     fn test_archive_rejects_path_traversal() {
         // This tests that validation rejects path traversal attempts
@@ -890,7 +1422,8 @@ mod tests {
 
         // Step 4: Extract the archive
         let extracted =
-            extract_tar_zstd_archive(&decrypted_archive_path, &extract_dir, false, None).unwrap();
+            extract_tar_zstd_archive(&decrypted_archive_path, &extract_dir, false, None, None)
+                .unwrap();
         assert_eq!(extracted.len(), files.len());
 
         // Step 5: Verify contents match originals
@@ -908,6 +1441,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_archive_pipeline_respects_configured_cap() {
+        use crate::crypto::{
+            decrypt_file_streaming, encrypt_file_streaming, Password, DEFAULT_CHUNK_SIZE,
+        };
+
+        let temp = tempdir().unwrap();
+        let input_path = temp.path().join("log.txt");
+        // Repetitive text: compresses well, but stays under the 100x ratio limit
+        let content: Vec<u8> = (0..1024 * 1024u32)
+            .map(|i| b"0123456789abcdef"[(i.wrapping_mul(2_654_435_761) >> 28) as usize])
+            .collect();
+        fs::write(&input_path, &content).unwrap();
+
+        let archive_path = temp.path().join("logs.tar.zst");
+        create_tar_zstd_archive(&[input_path.as_path()], &archive_path, None).unwrap();
+        let archive_size = fs::metadata(&archive_path).unwrap().len();
+        assert!(archive_size * MAX_DECOMPRESSION_RATIO > content.len() as u64);
+
+        let encrypted_path = temp.path().join("logs.tar.zst.encrypted");
+        let password = Password::new(test_password());
+        encrypt_file_streaming(
+            &archive_path,
+            &encrypted_path,
+            &password,
+            DEFAULT_CHUNK_SIZE,
+            None,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // The ratio baseline is the decrypted archive, not the encrypted file
+        let decrypted_path = temp.path().join("decrypted.tar.zst");
+        decrypt_file_streaming(
+            &encrypted_path,
+            &decrypted_path,
+            &password,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::metadata(&decrypted_path).unwrap().len(), archive_size);
+
+        let capped_dir = temp.path().join("capped");
+        fs::create_dir(&capped_dir).unwrap();
+        let result =
+            extract_tar_zstd_archive(&decrypted_path, &capped_dir, false, Some(512 * 1024), None);
+        match result {
+            Err(CryptoError::ArchiveError(msg)) => assert!(msg.contains("absolute size limit")),
+            other => panic!("expected cap to be enforced, got {:?}", other),
+        }
+        assert_eq!(fs::read_dir(&capped_dir).unwrap().count(), 0);
+
+        let roomy_dir = temp.path().join("roomy");
+        fs::create_dir(&roomy_dir).unwrap();
+        let extracted = extract_tar_zstd_archive(
+            &decrypted_path,
+            &roomy_dir,
+            false,
+            Some(4 * 1024 * 1024),
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&extracted[0]).unwrap(), content);
+    }
+
     #[test]
     fn test_archive_roundtrip_cross_directory() {
         // Files in separate temp dirs (simulates no common prefix beyond root)
@@ -927,7 +1529,7 @@ mod tests {
 
         // Extract - should NOT fail with PathTraversal error
         let extracted =
-            extract_tar_zstd_archive(&archive_path, extract_dir.path(), false, None).unwrap();
+            extract_tar_zstd_archive(&archive_path, extract_dir.path(), false, None, None).unwrap();
 
         assert_eq!(extracted.len(), 2);
     }
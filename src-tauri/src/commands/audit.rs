@@ -0,0 +1,225 @@
+// commands/audit.rs - Vault Consistency Audit
+//
+// Users who keep many encrypted files under "the same" password can drift:
+// one file ends up with an old password, another was written by a build with
+// cheap Argon2 settings. `audit_vault` checks a set of files against one set
+// of credentials and reports:
+// - which files open with them and which do not
+// - which files record KDF parameters below the security floor
+//
+// The KDF check reads only the header, so it works even for files the given
+// password cannot open. Opening uses a quick verification (key commitment,
+// trailer tag, first chunk): enough to confirm the credentials, nothing is
+// written, and the plaintext is discarded.
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::command;
+
+use crate::commands::file_utils::{validate_batch_count, validate_input_path};
+use crate::crypto::{
+    read_kdf_params, verify_file_streaming, weak_params_finding, KdfParams, Password,
+};
+use crate::error::{CryptoError, CryptoResult};
+
+/// Audit result for one file
+#[derive(Clone, Serialize)]
+pub struct FileAudit {
+    /// Input file path as provided by the user
+    pub path: String,
+    /// Whether the file opens with the given password (and key file)
+    pub opens: bool,
+    /// Why the file did not open (None if it did)
+    pub error: Option<String>,
+    /// KDF parameters from the header (None if the header could not be read)
+    pub kdf_params: Option<KdfParams>,
+    /// Why the KDF parameters are considered weak (None if they are not)
+    pub weak_kdf: Option<String>,
+}
+
+/// Summary of an `audit_vault` run
+#[derive(Clone, Serialize)]
+pub struct VaultAudit {
+    /// Per-file results, in input order
+    pub files: Vec<FileAudit>,
+    /// Count of files that open with the given credentials
+    pub opens_count: usize,
+    /// Count of files that do not (other password, other key file, or unreadable)
+    pub mismatched_count: usize,
+    /// Count of files whose KDF parameters are below the security floor
+    pub weak_kdf_count: usize,
+}
+
+/// Audit one file: header KDF parameters, then a quick credential check
+fn audit_file(path: &str, password: &Password, key_file_path: Option<&Path>) -> FileAudit {
+    let validated = validate_input_path(path);
+    let kdf_params = validated
+        .as_ref()
+        .ok()
+        .and_then(|input| read_kdf_params(input).ok());
+    let opened =
+        validated.and_then(|input| verify_file_streaming(&input, password, key_file_path, true));
+
+    FileAudit {
+        path: path.to_string(),
+        opens: opened.is_ok(),
+        error: opened.err().map(|e| e.to_string()),
+        weak_kdf: kdf_params.as_ref().and_then(weak_params_finding),
+        kdf_params,
+    }
+}
+
+/// Core implementation of the vault audit.
+///
+/// This is separated from the Tauri command to allow unit testing without
+/// requiring a Tauri runtime.
+fn audit_vault_impl(
+    paths: &[String],
+    password: &str,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<VaultAudit> {
+    if password.is_empty() && key_file_path.is_none() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+    if paths.is_empty() {
+        return Err(CryptoError::FormatError("No files selected".to_string()));
+    }
+    validate_batch_count(paths.len())?;
+
+    let password = Password::new(password.to_string());
+    let files: Vec<FileAudit> = paths
+        .iter()
+        .map(|path| audit_file(path, &password, key_file_path))
+        .collect();
+
+    let opens_count = files.iter().filter(|file| file.opens).count();
+    let weak_kdf_count = files.iter().filter(|file| file.weak_kdf.is_some()).count();
+    log::info!(
+        "Vault audit: {} of {} files open, {} with weak KDF parameters",
+        opens_count,
+        files.len(),
+        weak_kdf_count
+    );
+
+    Ok(VaultAudit {
+        mismatched_count: files.len() - opens_count,
+        opens_count,
+        weak_kdf_count,
+        files,
+    })
+}
+
+/// Check which encrypted files share the given password and which use weak KDF settings
+///
+/// Files that open with the credentials are protected consistently; files
+/// that do not were encrypted with another password or key file (or are not
+/// readable FileCrypter files, see each file's `error`). Weak KDF parameters
+/// are flagged from the header whether or not the file opens.
+///
+/// # Arguments
+/// * `paths` - Encrypted files to audit
+/// * `password` - Password expected to open every file
+/// * `key_file_path` - Optional key file expected alongside the password
+///
+/// # Returns
+/// VaultAudit with per-file results and summary counts
+///
+/// # Frontend Usage
+/// ```typescript
+/// const audit = await invoke('audit_vault', { paths, password, keyFilePath: null });
+/// ```
+#[command]
+pub async fn audit_vault(
+    paths: Vec<String>,
+    password: String,
+    key_file_path: Option<String>,
+) -> CryptoResult<VaultAudit> {
+    log::info!("Auditing {} files", paths.len());
+    audit_vault_impl(&paths, &password, key_file_path.as_deref().map(Path::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{encrypt_file_streaming_with, EncryptOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn encrypt(dir: &Path, name: &str, password: &str, options: EncryptOptions) -> String {
+        let input = dir.join(format!("{}.txt", name));
+        let output = dir.join(format!("{}.txt.encrypted", name));
+        fs::write(&input, name.as_bytes()).unwrap();
+        encrypt_file_streaming_with(
+            &input,
+            &output,
+            &Password::new(password.to_string()),
+            options,
+        )
+        .unwrap();
+        output.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_audit_vault_flags_other_passwords_and_weak_kdf() {
+        let dir = tempdir().unwrap();
+        let weak_params = KdfParams {
+            memory_cost_kib: 8 * 1024,
+            time_cost: 1,
+            parallelism: 1,
+            ..KdfParams::default()
+        };
+        let plaintext = dir.path().join("notes.txt");
+        fs::write(&plaintext, b"not encrypted").unwrap();
+        let paths = vec![
+            encrypt(
+                dir.path(),
+                "shared",
+                "vault password",
+                EncryptOptions::new(),
+            ),
+            encrypt(
+                dir.path(),
+                "shared_weak",
+                "vault password",
+                EncryptOptions::new().kdf_params(weak_params),
+            ),
+            encrypt(dir.path(), "other", "old password", EncryptOptions::new()),
+            encrypt(
+                dir.path(),
+                "other_weak",
+                "old password",
+                EncryptOptions::new().kdf_params(weak_params),
+            ),
+            plaintext.to_string_lossy().to_string(),
+        ];
+
+        let audit = audit_vault_impl(&paths, "vault password", None).unwrap();
+
+        let opens: Vec<bool> = audit.files.iter().map(|file| file.opens).collect();
+        assert_eq!(opens, [true, true, false, false, false]);
+        let weak: Vec<bool> = audit
+            .files
+            .iter()
+            .map(|file| file.weak_kdf.is_some())
+            .collect();
+        assert_eq!(weak, [false, true, false, true, false]);
+        assert_eq!(audit.opens_count, 2);
+        assert_eq!(audit.mismatched_count, 3);
+        assert_eq!(audit.weak_kdf_count, 2);
+
+        // The header is readable without the password; a plaintext file has none
+        assert_eq!(audit.files[3].kdf_params, Some(weak_params));
+        assert!(audit.files[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Invalid password"));
+        assert!(audit.files[4].kdf_params.is_none());
+        assert!(audit.files[0].error.is_none());
+
+        assert!(audit_vault_impl(&paths, "", None).is_err());
+        assert!(audit_vault_impl(&[], "vault password", None).is_err());
+    }
+}
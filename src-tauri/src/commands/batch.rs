@@ -12,31 +12,50 @@
 // - Unique salt generated per file (each file has independent key derivation)
 // - Password wrapper reused across batch (avoids repeated allocations)
 // - Maximum 1000 files per batch (configurable via MAX_BATCH_FILES)
+// - Files are processed sequentially unless `max_concurrent_files` > 1
+// - Parallel workers share a budget of open file handles (default 64, set
+//   FILECRYPTER_MAX_OPEN_FILES to change it) so large batches cannot hit
+//   "too many open files"
 //
 // Progress tracking:
 // - Emits BatchProgress events after each file completes
 // - Reports: current file name, file index, total files, stage, percentage
 // - Frontend can display per-file progress and overall batch progress
+// - `progress_interval` coalesces per-file events (every N files / M ms, or
+//   silent); the final "complete" event is always emitted
 //
 // Error handling:
 // - Failed files don't stop the batch (continues to next file)
+// - `transactional` batches are all-or-nothing instead: the first failure
+//   skips the remaining files and removes every output already written
 // - Each file result includes success status and error message
 // - BatchResult aggregates all individual file results
 
-use serde::Serialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 use tauri::{command, AppHandle, Emitter};
 
 use crate::commands::archive::{
-    create_tar_zstd_archive, extract_tar_zstd_archive, generate_archive_name,
+    create_tar_zstd_archive, extract_tar_zstd_archive, generate_archive_name, group_by_extension,
+    list_archive, validate_archive, ArchiveListing, ArchiveValidation,
 };
 use crate::commands::command_utils::validate_batch_inputs;
-use crate::commands::file_utils::{resolve_output_path, validate_input_path};
+use crate::commands::file_utils::{
+    decrypted_filename, filter_excluded_paths, random_hex, resolve_output_path,
+    validate_batch_count, validate_input_path, validate_no_symlinks,
+};
+#[cfg(debug_assertions)]
+use crate::crypto::streaming::read_stream_header;
 use crate::crypto::{
-    decrypt_file_streaming, encrypt_file_streaming, CompressionConfig, Password, DEFAULT_CHUNK_SIZE,
+    decrypt_file_streaming, encrypt_file_streaming, encrypt_file_streaming_hashed,
+    verify_file_streaming, CompressionConfig, EncryptOptions, Password, DEFAULT_CHUNK_SIZE,
 };
-use crate::error::{CryptoError, CryptoResult};
-use crate::security::create_secure_tempfile;
+use crate::error::{CryptoError, CryptoResult, IoPathExt};
+use crate::security::{create_secure_tempfile, shred_file};
 
 /// Progress event for batch operations.
 ///
@@ -50,7 +69,7 @@ pub struct BatchProgress {
     pub file_index: usize,
     /// Total number of files in the batch
     pub total_files: usize,
-    /// Current stage: "encrypting", "decrypting", or "complete"
+    /// Current stage: "encrypting", "decrypting", "verifying", or "complete"
     pub stage: String,
     /// Overall batch progress percentage (0-100)
     pub percent: u32,
@@ -69,6 +88,25 @@ pub struct FileResult {
     pub success: bool,
     /// Error message describing why the operation failed (None if successful)
     pub error: Option<String>,
+    /// BLAKE3 hash (hex) of the encrypted output (batch encryption only)
+    pub ciphertext_hash: Option<String>,
+}
+
+/// Output of processing one batch file
+struct ProcessedFile {
+    /// Resolved output path
+    output_path: String,
+    /// BLAKE3 hash (hex) of the written ciphertext, when encrypting
+    ciphertext_hash: Option<String>,
+}
+
+impl From<String> for ProcessedFile {
+    fn from(output_path: String) -> Self {
+        Self {
+            output_path,
+            ciphertext_hash: None,
+        }
+    }
 }
 
 /// Aggregated result of a batch encrypt/decrypt operation.
@@ -82,6 +120,8 @@ pub struct BatchResult {
     pub success_count: usize,
     /// Count of files that failed to process
     pub failed_count: usize,
+    /// Count of inputs skipped because they named a file already in the batch
+    pub duplicates_skipped: usize,
 }
 
 /// Event name for batch progress
@@ -121,12 +161,66 @@ pub struct ArchiveResult {
     pub error: Option<String>,
 }
 
+/// Limits on how often per-file batch progress events are emitted.
+///
+/// Large batches of small files otherwise send one IPC event per file. The
+/// final "complete" event is emitted regardless of these settings.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ProgressInterval {
+    /// Emit at most once every this many files (0 means every file)
+    pub files: usize,
+    /// Emit at most once every this many milliseconds (0 means no time limit)
+    pub ms: u64,
+    /// Suppress per-file events entirely
+    pub silent: bool,
+}
+
+/// Per-batch state for coalescing progress events.
+///
+/// When both limits are set, an event is emitted only once both have elapsed
+/// since the previous one. The first file is always reported unless silent.
+#[derive(Debug, Default)]
+struct ProgressThrottle {
+    interval: ProgressInterval,
+    last_emitted: Option<(usize, Instant)>,
+}
+
+impl ProgressThrottle {
+    fn new(interval: ProgressInterval) -> Self {
+        Self {
+            interval,
+            last_emitted: None,
+        }
+    }
+
+    /// Whether the event for `file_index` should be emitted, recording it if so.
+    fn should_emit(&mut self, file_index: usize) -> bool {
+        if self.interval.silent {
+            return false;
+        }
+        let now = Instant::now();
+        if let Some((last_index, last_time)) = self.last_emitted {
+            if file_index.saturating_sub(last_index) < self.interval.files.max(1) {
+                return false;
+            }
+            if now.duration_since(last_time) < Duration::from_millis(self.interval.ms) {
+                return false;
+            }
+        }
+        self.last_emitted = Some((file_index, now));
+        true
+    }
+}
+
 /// Emit a batch progress event for the current file.
 ///
 /// Extracts the filename from the input path and calculates the overall
-/// percentage based on the file index.
+/// percentage based on the file index. Events the throttle coalesces away
+/// are dropped.
 fn emit_batch_progress<F>(
     emit_progress: &mut F,
+    throttle: &mut ProgressThrottle,
     input_path: &str,
     file_index: usize,
     total_files: usize,
@@ -134,6 +228,10 @@ fn emit_batch_progress<F>(
 ) where
     F: FnMut(BatchProgress),
 {
+    if !throttle.should_emit(file_index) {
+        return;
+    }
+
     let file_name = Path::new(input_path)
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -162,62 +260,371 @@ where
     });
 }
 
+/// Default number of file handles parallel batch workers may hold at once
+pub const DEFAULT_MAX_OPEN_FILES: usize = 64;
+
+/// Environment variable overriding [`DEFAULT_MAX_OPEN_FILES`]
+pub const MAX_OPEN_FILES_ENV: &str = "FILECRYPTER_MAX_OPEN_FILES";
+
+/// Handles processing one file may hold at once: input, output temp file,
+/// output lock file, and key file
+const HANDLES_PER_FILE: usize = 4;
+
+/// File handle budget for parallel batches, from `FILECRYPTER_MAX_OPEN_FILES`
+fn max_open_files() -> usize {
+    parse_max_open_files(std::env::var_os(MAX_OPEN_FILES_ENV).as_deref())
+}
+
+/// Interpret a configured handle budget; invalid or zero values use the default
+fn parse_max_open_files(raw: Option<&std::ffi::OsStr>) -> usize {
+    raw.and_then(|value| value.to_str())
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|&limit| limit > 0)
+        .unwrap_or(DEFAULT_MAX_OPEN_FILES)
+}
+
+/// Counting semaphore over open file handles shared by batch workers.
+///
+/// Each file in flight holds `HANDLES_PER_FILE` handles; a worker waits for
+/// a permit before opening anything. The budget always admits one file, so a
+/// tiny limit degrades to sequential processing instead of deadlocking.
+struct OpenFileLimit {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl OpenFileLimit {
+    fn new(max_handles: usize) -> Self {
+        Self {
+            available: Mutex::new(max_handles.max(HANDLES_PER_FILE)),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Block until there is room for one more file's handles
+    fn acquire(&self) -> OpenFilePermit<'_> {
+        let mut available = self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *available < HANDLES_PER_FILE {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *available -= HANDLES_PER_FILE;
+        OpenFilePermit(self)
+    }
+}
+
+/// Handles reserved for one file; returned to the budget on drop
+struct OpenFilePermit<'a>(&'a OpenFileLimit);
+
+impl Drop for OpenFilePermit<'_> {
+    fn drop(&mut self) {
+        *self
+            .0
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) += HANDLES_PER_FILE;
+        self.0.released.notify_one();
+    }
+}
+
+/// Clamp a requested file concurrency to `[1, available CPUs]`.
+///
+/// `None` (and 0) means 1, i.e. files are processed sequentially.
+fn effective_concurrency(max_concurrent_files: Option<usize>) -> usize {
+    let cpus = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    max_concurrent_files.unwrap_or(1).clamp(1, cpus)
+}
+
+/// Whether two inputs would map to the same output filename.
+///
+/// Output collision handling (`resolve_output_path`) is check-then-write, so
+/// such batches must be processed sequentially.
+fn has_duplicate_file_names(input_paths: &[String]) -> bool {
+    let mut seen = HashSet::new();
+    input_paths
+        .iter()
+        .any(|path| !seen.insert(Path::new(path).file_name().map(|n| n.to_os_string())))
+}
+
+/// Drop inputs that resolve to a file already listed earlier in the batch.
+///
+/// Paths are compared after canonicalization, so `a.txt` and `./a.txt` count
+/// as the same file. Paths that cannot be canonicalized (e.g. missing files)
+/// are compared as given and left for per-file processing to report. Returns
+/// the remaining paths in first-occurrence order and the number dropped.
+fn dedupe_input_paths(input_paths: &[String]) -> (Vec<String>, usize) {
+    let mut seen = HashSet::new();
+    let unique: Vec<String> = input_paths
+        .iter()
+        .filter(|path| {
+            let key = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+            seen.insert(key)
+        })
+        .cloned()
+        .collect();
+    let skipped = input_paths.len() - unique.len();
+    if skipped > 0 {
+        log::info!("Skipping {} duplicate batch input(s)", skipped);
+    }
+    (unique, skipped)
+}
+
+/// Run `process` for every input and collect per-file results in input order.
+///
+/// With `max_concurrent <= 1` files are processed one at a time. Otherwise up to
+/// `max_concurrent` worker threads pull files from a shared queue, and progress
+/// is emitted from the calling thread as each file completes. Workers also
+/// wait for room in the open file handle budget (see [`max_open_files`]).
+fn run_batch<F, P, O>(
+    input_paths: &[String],
+    max_concurrent: usize,
+    progress_interval: ProgressInterval,
+    stage: &str,
+    process: P,
+    emit_progress: &mut F,
+) -> Vec<FileResult>
+where
+    F: FnMut(BatchProgress),
+    P: Fn(&str) -> CryptoResult<O> + Sync,
+    O: Into<ProcessedFile> + Send,
+{
+    run_batch_limited(
+        input_paths,
+        max_concurrent,
+        &OpenFileLimit::new(max_open_files()),
+        progress_interval,
+        stage,
+        process,
+        emit_progress,
+    )
+}
+
+/// [`run_batch`] with an explicit open file handle budget
+fn run_batch_limited<F, P, O>(
+    input_paths: &[String],
+    max_concurrent: usize,
+    open_files: &OpenFileLimit,
+    progress_interval: ProgressInterval,
+    stage: &str,
+    process: P,
+    emit_progress: &mut F,
+) -> Vec<FileResult>
+where
+    F: FnMut(BatchProgress),
+    P: Fn(&str) -> CryptoResult<O> + Sync,
+    O: Into<ProcessedFile> + Send,
+{
+    let total_files = input_paths.len();
+    let mut throttle = ProgressThrottle::new(progress_interval);
+    let to_file_result = |input_path: &String, result: CryptoResult<O>| match result {
+        Ok(processed) => {
+            let processed = processed.into();
+            FileResult {
+                input_path: input_path.clone(),
+                output_path: Some(processed.output_path),
+                success: true,
+                error: None,
+                ciphertext_hash: processed.ciphertext_hash,
+            }
+        }
+        Err(e) => {
+            log::error!("Failed {} {}: {}", stage, input_path, e);
+            FileResult {
+                input_path: input_path.clone(),
+                output_path: None,
+                success: false,
+                error: Some(e.to_string()),
+                ciphertext_hash: None,
+            }
+        }
+    };
+
+    let workers = max_concurrent.min(total_files);
+    if workers <= 1 || has_duplicate_file_names(input_paths) {
+        let mut results = Vec::with_capacity(total_files);
+        for (index, input_path) in input_paths.iter().enumerate() {
+            emit_batch_progress(
+                emit_progress,
+                &mut throttle,
+                input_path,
+                index,
+                total_files,
+                stage,
+            );
+            results.push(to_file_result(input_path, process(input_path)));
+        }
+        return results;
+    }
+
+    let next_index = AtomicUsize::new(0);
+    let mut slots: Vec<Option<FileResult>> = vec![None; total_files];
+    std::thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let next_index = &next_index;
+            let process = &process;
+            scope.spawn(move || loop {
+                let index = next_index.fetch_add(1, Ordering::SeqCst);
+                let Some(input_path) = input_paths.get(index) else {
+                    break;
+                };
+                let result = {
+                    let _permit = open_files.acquire();
+                    process(input_path)
+                };
+                if tx.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (completed, (index, result)) in rx.iter().enumerate() {
+            let input_path = &input_paths[index];
+            emit_batch_progress(
+                emit_progress,
+                &mut throttle,
+                input_path,
+                completed,
+                total_files,
+                stage,
+            );
+            slots[index] = Some(to_file_result(input_path, result));
+        }
+    });
+
+    slots.into_iter().flatten().collect()
+}
+
+/// All-or-nothing state for a transactional batch.
+///
+/// Once a file fails, files not yet started are skipped; [`Transaction::roll_back`]
+/// then removes the outputs of the files that succeeded. Outputs are always
+/// new files (transactional batches never overwrite), so removing them leaves
+/// the output directory as it was. Decrypted outputs are plaintext, so they
+/// are zeroed before removal like a failed decrypt's partial output.
+/// Disabled transactions pass results through.
+struct Transaction {
+    enabled: bool,
+    failed: AtomicBool,
+}
+
+impl Transaction {
+    /// Start a transaction, rejecting `allow_overwrite` since an overwritten
+    /// file could not be restored on rollback
+    fn new(enabled: bool, allow_overwrite: bool) -> CryptoResult<Self> {
+        if enabled && allow_overwrite {
+            return Err(CryptoError::FormatError(
+                "Transactional batches cannot overwrite existing files".to_string(),
+            ));
+        }
+        Ok(Self {
+            enabled,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Process one file unless an earlier file already failed
+    fn run<O>(&self, process: impl FnOnce() -> CryptoResult<O>) -> CryptoResult<O> {
+        if !self.enabled {
+            return process();
+        }
+        if self.failed.load(Ordering::SeqCst) {
+            return Err(CryptoError::Cancelled);
+        }
+        let result = process();
+        if result.is_err() {
+            self.failed.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// After a failure, remove every output written and mark those files failed
+    ///
+    /// With `plaintext`, outputs are shredded (zeroed, synced, then removed).
+    fn roll_back(&self, results: &mut [FileResult], plaintext: bool) {
+        if !self.enabled || results.iter().all(|result| result.success) {
+            return;
+        }
+        for result in results.iter_mut().filter(|result| result.success) {
+            if let Some(output_path) = result.output_path.take() {
+                let removed = if plaintext {
+                    shred_file(Path::new(&output_path))
+                } else {
+                    std::fs::remove_file(&output_path)
+                };
+                if let Err(err) = removed {
+                    log::warn!("Failed to roll back {}: {}", output_path, err);
+                }
+            }
+            result.success = false;
+            result.error = Some("Rolled back: another file in the batch failed".to_string());
+            result.ciphertext_hash = None;
+        }
+        log::info!("Transactional batch failed; outputs rolled back");
+    }
+}
+
 /// Core implementation of batch encryption.
 ///
 /// This is separated from the Tauri command to allow unit testing without
 /// requiring a Tauri runtime.
+#[allow(clippy::too_many_arguments)]
 fn batch_encrypt_impl<F>(
     input_paths: &[String],
     output_dir: &str,
     password: &str,
     allow_overwrite: bool,
     key_file_path: Option<&Path>,
+    max_concurrent: usize,
+    transactional: bool,
+    progress_interval: ProgressInterval,
     emit_progress: &mut F,
 ) -> CryptoResult<BatchResult>
 where
     F: FnMut(BatchProgress),
 {
     // Validate common batch inputs
-    validate_batch_inputs(password, input_paths, output_dir)?;
+    validate_batch_inputs(password, key_file_path, input_paths, output_dir)?;
+    let transaction = Transaction::new(transactional, allow_overwrite)?;
 
+    let (input_paths, duplicates_skipped) = dedupe_input_paths(input_paths);
     let total_files = input_paths.len();
-    let mut results: Vec<FileResult> = Vec::with_capacity(total_files);
     let password = Password::new(password.to_string());
 
-    for (index, input_path) in input_paths.iter().enumerate() {
-        emit_batch_progress(emit_progress, input_path, index, total_files, "encrypting");
-
-        let result = encrypt_single_file(
-            &password,
-            input_path,
-            output_dir,
-            allow_overwrite,
-            key_file_path,
-        );
-
-        match result {
-            Ok(output_path) => {
-                results.push(FileResult {
-                    input_path: input_path.clone(),
-                    output_path: Some(output_path),
-                    success: true,
-                    error: None,
-                });
-            }
-            Err(e) => {
-                log::error!("Failed to encrypt {}: {}", input_path, e);
-                results.push(FileResult {
-                    input_path: input_path.clone(),
-                    output_path: None,
-                    success: false,
-                    error: Some(e.to_string()),
-                });
-            }
-        }
-    }
+    let mut results = run_batch(
+        &input_paths,
+        max_concurrent,
+        progress_interval,
+        "encrypting",
+        |input_path| {
+            transaction.run(|| {
+                encrypt_single_file(
+                    &password,
+                    input_path,
+                    output_dir,
+                    allow_overwrite,
+                    key_file_path,
+                )
+            })
+        },
+        emit_progress,
+    );
+    transaction.roll_back(&mut results, false);
 
     emit_batch_complete(emit_progress, total_files);
 
+    #[cfg(debug_assertions)]
+    debug_assert_unique_salts(&results);
+
     let success_count = results.iter().filter(|r| r.success).count();
     let failed_count = results.len() - success_count;
 
@@ -231,62 +638,77 @@ where
         files: results,
         success_count,
         failed_count,
+        duplicates_skipped,
     })
 }
 
+/// Debug check that no two files in a batch share a salt (and therefore a key).
+///
+/// `encrypt_file_streaming` derives a fresh salt per call; this guards against
+/// a future change that caches the derived key across the batch.
+#[cfg(debug_assertions)]
+fn debug_assert_unique_salts(results: &[FileResult]) {
+    let mut seen = HashSet::new();
+    for path in results.iter().filter_map(|r| r.output_path.as_deref()) {
+        let header = std::fs::File::open(path)
+            .map_err(CryptoError::from)
+            .and_then(|mut file| read_stream_header(&mut file));
+        if let Ok(header) = header {
+            debug_assert!(
+                seen.insert(header.salt().to_vec()),
+                "salt reused within batch: {}",
+                path
+            );
+        }
+    }
+}
+
 /// Core implementation of batch decryption.
 ///
 /// This is separated from the Tauri command to allow unit testing without
 /// requiring a Tauri runtime.
+#[allow(clippy::too_many_arguments)]
 fn batch_decrypt_impl<F>(
     input_paths: &[String],
     output_dir: &str,
     password: &str,
     allow_overwrite: bool,
     key_file_path: Option<&Path>,
+    max_concurrent: usize,
+    transactional: bool,
+    progress_interval: ProgressInterval,
     emit_progress: &mut F,
 ) -> CryptoResult<BatchResult>
 where
     F: FnMut(BatchProgress),
 {
     // Validate common batch inputs
-    validate_batch_inputs(password, input_paths, output_dir)?;
+    validate_batch_inputs(password, key_file_path, input_paths, output_dir)?;
+    let transaction = Transaction::new(transactional, allow_overwrite)?;
 
+    let (input_paths, duplicates_skipped) = dedupe_input_paths(input_paths);
     let total_files = input_paths.len();
-    let mut results: Vec<FileResult> = Vec::with_capacity(total_files);
     let password = Password::new(password.to_string());
 
-    for (index, input_path) in input_paths.iter().enumerate() {
-        emit_batch_progress(emit_progress, input_path, index, total_files, "decrypting");
-
-        let result = decrypt_single_file(
-            &password,
-            input_path,
-            output_dir,
-            allow_overwrite,
-            key_file_path,
-        );
-
-        match result {
-            Ok(output_path) => {
-                results.push(FileResult {
-                    input_path: input_path.clone(),
-                    output_path: Some(output_path),
-                    success: true,
-                    error: None,
-                });
-            }
-            Err(e) => {
-                log::error!("Failed to decrypt {}: {}", input_path, e);
-                results.push(FileResult {
-                    input_path: input_path.clone(),
-                    output_path: None,
-                    success: false,
-                    error: Some(e.to_string()),
-                });
-            }
-        }
-    }
+    let mut results = run_batch(
+        &input_paths,
+        max_concurrent,
+        progress_interval,
+        "decrypting",
+        |input_path| {
+            transaction.run(|| {
+                decrypt_single_file(
+                    &password,
+                    input_path,
+                    output_dir,
+                    allow_overwrite,
+                    key_file_path,
+                )
+            })
+        },
+        emit_progress,
+    );
+    transaction.roll_back(&mut results, true);
 
     emit_batch_complete(emit_progress, total_files);
 
@@ -303,6 +725,7 @@ where
         files: results,
         success_count,
         failed_count,
+        duplicates_skipped,
     })
 }
 
@@ -318,10 +741,19 @@ where
 /// * `output_dir` - Directory where encrypted files will be saved
 /// * `password` - Password for encryption (used for all files)
 /// * `allow_overwrite` - Allow overwriting existing files (default: false)
+/// * `exclude_patterns` - Optional glob patterns (e.g. `*.tmp`, `.DS_Store`) for files to skip
+/// * `max_concurrent_files` - Maximum files processed at once, clamped to `[1, CPUs]`
+///   (default: 1, meaning sequential)
+/// * `transactional` - All-or-nothing: if any file fails, skip the rest and
+///   remove every output already written (default: false; cannot be combined
+///   with `allow_overwrite`)
+/// * `progress_interval` - Optional limits on per-file progress events (every N
+///   files / M milliseconds, or silent); the "complete" event is always emitted
 ///
 /// # Returns
 /// BatchResult with success/failure status for each file
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn batch_encrypt(
     app: AppHandle,
     input_paths: Vec<String>,
@@ -329,7 +761,13 @@ pub async fn batch_encrypt(
     password: String,
     allow_overwrite: Option<bool>,
     key_file_path: Option<String>,
+    exclude_patterns: Option<Vec<String>>,
+    max_concurrent_files: Option<usize>,
+    transactional: Option<bool>,
+    progress_interval: Option<ProgressInterval>,
 ) -> CryptoResult<BatchResult> {
+    let input_paths = filter_excluded_paths(&input_paths, &exclude_patterns.unwrap_or_default())?;
+
     log::info!(
         "Batch encrypting {} files to {}",
         input_paths.len(),
@@ -349,6 +787,9 @@ pub async fn batch_encrypt(
         &password,
         allow_overwrite,
         kf_path,
+        effective_concurrency(max_concurrent_files),
+        transactional.unwrap_or(false),
+        progress_interval.unwrap_or_default(),
         &mut emit_progress,
     )
 }
@@ -366,7 +807,7 @@ pub async fn batch_encrypt(
 /// * `allow_overwrite` - Whether to overwrite existing files
 ///
 /// # Returns
-/// The path to the encrypted file as a String
+/// The path to the encrypted file and the BLAKE3 hash of its contents
 ///
 /// # Note
 /// No progress callback is provided because batch operations track progress
@@ -377,7 +818,7 @@ fn encrypt_single_file(
     output_dir: &str,
     allow_overwrite: bool,
     key_file_path: Option<&Path>,
-) -> CryptoResult<String> {
+) -> CryptoResult<ProcessedFile> {
     // Validate input path (check for symlinks)
     let validated_path = validate_input_path(input_path)
         .map_err(|e| CryptoError::FormatError(format!("File '{}': {}", input_path, e)))?;
@@ -391,19 +832,22 @@ fn encrypt_single_file(
     let resolved_output_path = resolve_output_path(&output_path, allow_overwrite)?;
 
     // Use streaming encryption with compression for batch operations
-    // Compression is always enabled for batch mode
-    encrypt_file_streaming(
-        validated_path,
-        &resolved_output_path,
-        password,
-        DEFAULT_CHUNK_SIZE,
-        None, // No progress callback - batch has its own progress tracking
-        allow_overwrite,
-        Some(CompressionConfig::default()), // ZSTD level 3 compression
-        key_file_path,
-    )?;
+    // Compression is always enabled for batch mode (ZSTD level 3).
+    // No progress callback - batch has its own progress tracking
+    let mut options = EncryptOptions::new()
+        .chunk_size(DEFAULT_CHUNK_SIZE)
+        .allow_overwrite(allow_overwrite)
+        .compression(CompressionConfig::default());
+    if let Some(path) = key_file_path {
+        options = options.key_file(path);
+    }
+    let ciphertext_hash =
+        encrypt_file_streaming_hashed(validated_path, &resolved_output_path, password, options)?;
 
-    Ok(resolved_output_path.to_string_lossy().to_string())
+    Ok(ProcessedFile {
+        output_path: resolved_output_path.to_string_lossy().to_string(),
+        ciphertext_hash: Some(ciphertext_hash.to_hex().to_string()),
+    })
 }
 
 /// Decrypt multiple files with the same password
@@ -414,10 +858,18 @@ fn encrypt_single_file(
 /// * `output_dir` - Directory where decrypted files will be saved
 /// * `password` - Password for decryption
 /// * `allow_overwrite` - Allow overwriting existing files (default: false)
+/// * `max_concurrent_files` - Maximum files processed at once, clamped to `[1, CPUs]`
+///   (default: 1, meaning sequential)
+/// * `transactional` - All-or-nothing: if any file fails, skip the rest and
+///   remove every output already written (default: false; cannot be combined
+///   with `allow_overwrite`)
+/// * `progress_interval` - Optional limits on per-file progress events (every N
+///   files / M milliseconds, or silent); the "complete" event is always emitted
 ///
 /// # Returns
 /// BatchResult with success/failure status for each file
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn batch_decrypt(
     app: AppHandle,
     input_paths: Vec<String>,
@@ -425,6 +877,9 @@ pub async fn batch_decrypt(
     password: String,
     allow_overwrite: Option<bool>,
     key_file_path: Option<String>,
+    max_concurrent_files: Option<usize>,
+    transactional: Option<bool>,
+    progress_interval: Option<ProgressInterval>,
 ) -> CryptoResult<BatchResult> {
     log::info!(
         "Batch decrypting {} files to {}",
@@ -445,6 +900,9 @@ pub async fn batch_decrypt(
         &password,
         allow_overwrite,
         kf_path,
+        effective_concurrency(max_concurrent_files),
+        transactional.unwrap_or(false),
+        progress_interval.unwrap_or_default(),
         &mut emit_progress,
     )
 }
@@ -478,16 +936,7 @@ fn decrypt_single_file(
         .map_err(|e| CryptoError::FormatError(format!("File '{}': {}", input_path, e)))?;
 
     // Create output path (remove .encrypted extension if present)
-    let input_filename = validated_path
-        .file_name()
-        .ok_or_else(|| CryptoError::FormatError("Invalid input path".to_string()))?
-        .to_string_lossy();
-
-    let output_filename = if let Some(stripped) = input_filename.strip_suffix(".encrypted") {
-        stripped.to_string()
-    } else {
-        format!("{}.decrypted", input_filename)
-    };
+    let output_filename = decrypted_filename(&validated_path)?;
 
     let output_path = Path::new(output_dir).join(&output_filename);
     let resolved_output_path = resolve_output_path(&output_path, allow_overwrite)?;
@@ -505,72 +954,411 @@ fn decrypt_single_file(
     Ok(resolved_output_path.to_string_lossy().to_string())
 }
 
-// =============================================================================
-// Archive Mode Commands
-// =============================================================================
+/// Core implementation of batch verification.
+///
+/// Nothing is written; each `FileResult` has no output path.
+fn batch_verify_impl<F>(
+    input_paths: &[String],
+    password: &str,
+    key_file_path: Option<&Path>,
+    quick: bool,
+    max_concurrent: usize,
+    progress_interval: ProgressInterval,
+    emit_progress: &mut F,
+) -> CryptoResult<BatchResult>
+where
+    F: FnMut(BatchProgress),
+{
+    if password.is_empty() && key_file_path.is_none() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+    if input_paths.is_empty() {
+        return Err(CryptoError::FormatError("No files selected".to_string()));
+    }
+    validate_batch_count(input_paths.len())?;
 
-/// Encrypt multiple files as a single encrypted archive.
+    let (input_paths, duplicates_skipped) = dedupe_input_paths(input_paths);
+    let total_files = input_paths.len();
+    let password = Password::new(password.to_string());
+
+    let mut results = run_batch(
+        &input_paths,
+        max_concurrent,
+        progress_interval,
+        "verifying",
+        |input_path| {
+            let validated_path = validate_input_path(input_path)
+                .map_err(|e| CryptoError::FormatError(format!("File '{}': {}", input_path, e)))?;
+            verify_file_streaming(&validated_path, &password, key_file_path, quick)?;
+            Ok(String::new())
+        },
+        emit_progress,
+    );
+    for result in &mut results {
+        result.output_path = None;
+    }
+
+    emit_batch_complete(emit_progress, total_files);
+
+    let success_count = results.iter().filter(|r| r.success).count();
+    let failed_count = results.len() - success_count;
+
+    log::info!(
+        "Batch verification complete: {} passed, {} failed",
+        success_count,
+        failed_count
+    );
+
+    Ok(BatchResult {
+        files: results,
+        success_count,
+        failed_count,
+        duplicates_skipped,
+    })
+}
+
+/// Verify multiple encrypted files without writing any output
 ///
-/// This creates a compressed TAR archive from the input files, then encrypts
-/// the entire archive as a single unit. This is useful when you want to bundle
-/// multiple files together and protect them with a single password.
+/// Each file is authenticated with the given credentials and the plaintext is
+/// discarded. In quick mode only the first chunk (plus the key commitment and
+/// integrity trailer tag, when present) is checked, which confirms the
+/// password but not the integrity of the rest of the file.
 ///
 /// # Arguments
 /// * `app` - Tauri app handle for emitting progress events
-/// * `input_paths` - List of file paths to include in the archive
-/// * `output_dir` - Directory where the encrypted archive will be saved
-/// * `password` - Password for encryption
-/// * `archive_name` - Optional custom name for the archive (without extension)
-/// * `allow_overwrite` - Allow overwriting existing files (default: false)
+/// * `input_paths` - List of encrypted file paths to verify
+/// * `password` - Password for decryption
+/// * `key_file_path` - Optional key file for files that require one
+/// * `quick` - Only check the first chunk of each file (default: false)
+/// * `max_concurrent_files` - Maximum files processed at once, clamped to `[1, CPUs]`
+///   (default: 1, meaning sequential)
+/// * `progress_interval` - Optional limits on per-file progress events (every N
+///   files / M milliseconds, or silent); the "complete" event is always emitted
 ///
 /// # Returns
-/// ArchiveResult with the path to the encrypted archive
+/// BatchResult with pass/fail status for each file
 #[command]
-pub async fn batch_encrypt_archive(
+pub async fn verify_batch(
     app: AppHandle,
     input_paths: Vec<String>,
-    output_dir: String,
     password: String,
-    archive_name: Option<String>,
-    allow_overwrite: Option<bool>,
     key_file_path: Option<String>,
-) -> CryptoResult<ArchiveResult> {
-    log::info!(
-        "Batch archive encrypting {} files to {}",
-        input_paths.len(),
-        output_dir
-    );
+    quick: Option<bool>,
+    max_concurrent_files: Option<usize>,
+    progress_interval: Option<ProgressInterval>,
+) -> CryptoResult<BatchResult> {
+    log::info!("Batch verifying {} files", input_paths.len());
 
-    // Validate common batch inputs
-    validate_batch_inputs(&password, &input_paths, &output_dir)?;
+    let mut emit_progress = |progress: BatchProgress| {
+        let _ = app.emit(BATCH_PROGRESS_EVENT, progress);
+    };
 
-    let allow_overwrite = allow_overwrite.unwrap_or(false);
-    let total_files = input_paths.len();
+    let kf_path = key_file_path.as_deref().map(Path::new);
 
-    // Emit initial progress
-    let emit_archive_progress =
-        |phase: &str, current_file: Option<&str>, processed: usize, total: usize, percent: u32| {
-            let _ = app.emit(
-                ARCHIVE_PROGRESS_EVENT,
-                ArchiveProgress {
-                    phase: phase.to_string(),
-                    current_file: current_file.map(|s| s.to_string()),
-                    files_processed: processed,
-                    total_files: total,
-                    percent,
-                },
-            );
-        };
+    batch_verify_impl(
+        &input_paths,
+        &password,
+        kf_path,
+        quick.unwrap_or(false),
+        effective_concurrency(max_concurrent_files),
+        progress_interval.unwrap_or_default(),
+        &mut emit_progress,
+    )
+}
 
-    // Generate archive filename
-    let archive_filename = generate_archive_name(archive_name.as_deref());
-    // Use a secure temp file with random name for the intermediate (unencrypted) archive.
-    // This prevents plaintext from persisting at a guessable path if the process crashes.
-    // TempPath auto-deletes on drop, ensuring cleanup even on panic/crash.
-    let output_dir_path = Path::new(&output_dir);
-    let temp_archive = create_secure_tempfile(output_dir_path)?;
-    let temp_archive_path = temp_archive.into_temp_path();
-    let archive_path = temp_archive_path.to_path_buf();
+// =============================================================================
+// Directory Tree Mode
+// =============================================================================
+
+/// Regular files under `root`, in a stable (sorted) order
+///
+/// Only the top level is read unless `recurse` is set. Symlinks are skipped
+/// rather than followed, so every file returned lies inside `root`.
+fn collect_tree_files(root: &Path, recurse: bool) -> CryptoResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).with_path(&dir)? {
+            let entry = entry.with_path(&dir)?;
+            let file_type = entry.file_type().with_path(entry.path())?;
+            if file_type.is_file() {
+                files.push(entry.path());
+            } else if file_type.is_dir() && recurse {
+                pending.push(entry.path());
+            } else if file_type.is_symlink() {
+                log::warn!("Skipping symlink: {}", entry.path().display());
+            }
+        }
+        validate_batch_count(files.len())?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Directory under `output_root` that mirrors the parent of `file` under `input_root`
+///
+/// Fails with `PathTraversal` unless the relative path is made of plain names.
+fn mirrored_output_dir(
+    input_root: &Path,
+    output_root: &Path,
+    file: &Path,
+) -> CryptoResult<PathBuf> {
+    let relative = file.strip_prefix(input_root).map_err(|_| {
+        CryptoError::PathTraversal("File is outside the input directory".to_string())
+    })?;
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(CryptoError::PathTraversal(format!(
+            "Unsafe relative path: {}",
+            relative.display()
+        )));
+    }
+    Ok(match relative.parent() {
+        Some(parent) => output_root.join(parent),
+        None => output_root.to_path_buf(),
+    })
+}
+
+/// Core implementation of directory tree encryption.
+///
+/// This is separated from the Tauri command to allow unit testing without
+/// requiring a Tauri runtime.
+#[allow(clippy::too_many_arguments)]
+fn encrypt_tree_impl<F>(
+    input_dir: &str,
+    output_dir: &str,
+    password: &str,
+    key_file_path: Option<&Path>,
+    recurse: bool,
+    max_concurrent: usize,
+    progress_interval: ProgressInterval,
+    emit_progress: &mut F,
+) -> CryptoResult<BatchResult>
+where
+    F: FnMut(BatchProgress),
+{
+    let secret = Password::new(password.to_string());
+    encrypt_tree_with(
+        input_dir,
+        output_dir,
+        password,
+        key_file_path,
+        recurse,
+        max_concurrent,
+        progress_interval,
+        |input_path, file_output_dir| {
+            encrypt_single_file(&secret, input_path, file_output_dir, false, key_file_path)
+        },
+        emit_progress,
+    )
+}
+
+/// [`encrypt_tree_impl`] with an explicit per-file step, called with the
+/// input path and the mirrored output directory (already created)
+#[allow(clippy::too_many_arguments)]
+fn encrypt_tree_with<F, E>(
+    input_dir: &str,
+    output_dir: &str,
+    password: &str,
+    key_file_path: Option<&Path>,
+    recurse: bool,
+    max_concurrent: usize,
+    progress_interval: ProgressInterval,
+    encrypt_file: E,
+    emit_progress: &mut F,
+) -> CryptoResult<BatchResult>
+where
+    F: FnMut(BatchProgress),
+    E: Fn(&str, &str) -> CryptoResult<ProcessedFile> + Sync,
+{
+    let input_root = Path::new(input_dir);
+    if !input_root.is_dir() {
+        return Err(CryptoError::InvalidPath(
+            "Input path must be a directory".to_string(),
+        ));
+    }
+    validate_no_symlinks(input_root)?;
+    let input_root = input_root.canonicalize().with_path(input_root)?;
+
+    let input_paths: Vec<String> = collect_tree_files(&input_root, recurse)?
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    validate_batch_inputs(password, key_file_path, &input_paths, output_dir)?;
+
+    // Outputs from an earlier run would be walked and encrypted again
+    let output_root = Path::new(output_dir).canonicalize().with_path(output_dir)?;
+    if recurse && output_root.starts_with(&input_root) {
+        return Err(CryptoError::InvalidPath(
+            "Output directory cannot be inside the input directory".to_string(),
+        ));
+    }
+
+    let total_files = input_paths.len();
+
+    let results = run_batch(
+        &input_paths,
+        max_concurrent,
+        progress_interval,
+        "encrypting",
+        |input_path| {
+            let file_output_dir =
+                mirrored_output_dir(&input_root, &output_root, Path::new(input_path))?;
+            std::fs::create_dir_all(&file_output_dir).with_path(&file_output_dir)?;
+            encrypt_file(input_path, &file_output_dir.to_string_lossy())
+        },
+        emit_progress,
+    );
+
+    emit_batch_complete(emit_progress, total_files);
+
+    let success_count = results.iter().filter(|r| r.success).count();
+    let failed_count = results.len() - success_count;
+
+    log::info!(
+        "Tree encryption complete: {} succeeded, {} failed",
+        success_count,
+        failed_count
+    );
+
+    Ok(BatchResult {
+        files: results,
+        success_count,
+        failed_count,
+        duplicates_skipped: 0,
+    })
+}
+
+/// Encrypt every file in a directory tree into a mirrored output tree
+///
+/// Unlike archive mode, each file is encrypted on its own, and its output
+/// lands at the same relative path under `output_dir` (e.g. `in/a/b.txt` ->
+/// `out/a/b.txt.encrypted`). Subdirectories are created as needed and
+/// symlinks are skipped. Existing outputs are never overwritten; a
+/// collision gets a numbered name instead.
+///
+/// # Arguments
+/// * `app` - Tauri app handle for emitting progress events
+/// * `input_dir` - Directory whose files will be encrypted
+/// * `output_dir` - Existing directory where the mirrored tree is created
+///   (must not be inside `input_dir` when recursing)
+/// * `password` - Password for encryption (used for all files)
+/// * `key_file_path` - Optional key file for two-factor encryption
+/// * `recurse` - Descend into subdirectories (default: true)
+/// * `max_concurrent_files` - Maximum files processed at once, clamped to `[1, CPUs]`
+///   (default: 1, meaning sequential)
+/// * `progress_interval` - Optional limits on per-file progress events (every N
+///   files / M milliseconds, or silent); the "complete" event is always emitted
+///
+/// # Returns
+/// BatchResult with success/failure status for each file
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn encrypt_tree(
+    app: AppHandle,
+    input_dir: String,
+    output_dir: String,
+    password: String,
+    key_file_path: Option<String>,
+    recurse: Option<bool>,
+    max_concurrent_files: Option<usize>,
+    progress_interval: Option<ProgressInterval>,
+) -> CryptoResult<BatchResult> {
+    log::info!("Encrypting directory tree {} to {}", input_dir, output_dir);
+
+    let mut emit_progress = |progress: BatchProgress| {
+        let _ = app.emit(BATCH_PROGRESS_EVENT, progress);
+    };
+
+    encrypt_tree_impl(
+        &input_dir,
+        &output_dir,
+        &password,
+        key_file_path.as_deref().map(Path::new),
+        recurse.unwrap_or(true),
+        effective_concurrency(max_concurrent_files),
+        progress_interval.unwrap_or_default(),
+        &mut emit_progress,
+    )
+}
+
+// =============================================================================
+// Archive Mode Commands
+// =============================================================================
+
+/// Encrypt multiple files as a single encrypted archive.
+///
+/// This creates a compressed TAR archive from the input files, then encrypts
+/// the entire archive as a single unit. This is useful when you want to bundle
+/// multiple files together and protect them with a single password.
+///
+/// # Arguments
+/// * `app` - Tauri app handle for emitting progress events
+/// * `input_paths` - List of file paths to include in the archive
+/// * `output_dir` - Directory where the encrypted archive will be saved
+/// * `password` - Password for encryption
+/// * `archive_name` - Optional custom name for the archive (without extension)
+/// * `allow_overwrite` - Allow overwriting existing files (default: false)
+/// * `exclude_patterns` - Optional glob patterns (e.g. `*.tmp`, `.DS_Store`) for files to leave out
+///
+/// # Returns
+/// ArchiveResult with the path to the encrypted archive
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn batch_encrypt_archive(
+    app: AppHandle,
+    input_paths: Vec<String>,
+    output_dir: String,
+    password: String,
+    archive_name: Option<String>,
+    allow_overwrite: Option<bool>,
+    key_file_path: Option<String>,
+    exclude_patterns: Option<Vec<String>>,
+) -> CryptoResult<ArchiveResult> {
+    let input_paths = filter_excluded_paths(&input_paths, &exclude_patterns.unwrap_or_default())?;
+
+    log::info!(
+        "Batch archive encrypting {} files to {}",
+        input_paths.len(),
+        output_dir
+    );
+
+    // Validate common batch inputs
+    validate_batch_inputs(
+        &password,
+        key_file_path.as_deref().map(Path::new),
+        &input_paths,
+        &output_dir,
+    )?;
+
+    let allow_overwrite = allow_overwrite.unwrap_or(false);
+    let total_files = input_paths.len();
+
+    // Emit initial progress
+    let emit_archive_progress =
+        |phase: &str, current_file: Option<&str>, processed: usize, total: usize, percent: u32| {
+            let _ = app.emit(
+                ARCHIVE_PROGRESS_EVENT,
+                ArchiveProgress {
+                    phase: phase.to_string(),
+                    current_file: current_file.map(|s| s.to_string()),
+                    files_processed: processed,
+                    total_files: total,
+                    percent,
+                },
+            );
+        };
+
+    // Generate archive filename
+    let archive_filename = generate_archive_name(archive_name.as_deref());
+    // Use a random hidden name for the intermediate (unencrypted) archive.
+    // This prevents plaintext from persisting at a guessable path if the process crashes.
+    let output_dir_path = Path::new(&output_dir);
+    let archive_target = output_dir_path.join(format!(".{}.tar.zst", random_hex(8)?));
     let encrypted_path = output_dir_path.join(format!("{}.encrypted", archive_filename));
     let resolved_encrypted_path = resolve_output_path(&encrypted_path, allow_overwrite)?;
 
@@ -604,18 +1392,23 @@ pub async fn batch_encrypt_archive(
 
     // Create the archive
     let input_path_refs: Vec<&Path> = input_paths.iter().map(Path::new).collect();
-    if let Err(e) = create_tar_zstd_archive(
+    let temp_archive_path = match create_tar_zstd_archive(
         &input_path_refs,
-        &archive_path,
+        &archive_target,
         Some(archive_progress_callback),
     ) {
-        return Ok(ArchiveResult {
-            output_path: String::new(),
-            file_count: 0,
-            success: false,
-            error: Some(e.to_string()),
-        });
-    }
+        // TempPath auto-deletes on drop, ensuring cleanup even on panic
+        Ok(path) => tempfile::TempPath::try_from_path(path)?,
+        Err(e) => {
+            return Ok(ArchiveResult {
+                output_path: String::new(),
+                file_count: 0,
+                success: false,
+                error: Some(e.to_string()),
+            });
+        }
+    };
+    let archive_path = temp_archive_path.to_path_buf();
 
     // Phase 2: Encrypt the archive
     emit_archive_progress(
@@ -708,6 +1501,8 @@ pub async fn batch_encrypt_archive(
 /// * `output_dir` - Directory where extracted files will be saved
 /// * `password` - Password for decryption
 /// * `allow_overwrite` - Allow overwriting existing files (default: false)
+/// * `key_file_path` - Key file, if the archive was encrypted with one
+/// * `max_extracted_size` - Absolute cap on extracted bytes (default: 10 GB)
 ///
 /// # Returns
 /// ArchiveResult with the output directory and number of extracted files
@@ -719,10 +1514,11 @@ pub async fn batch_decrypt_archive(
     password: String,
     allow_overwrite: Option<bool>,
     key_file_path: Option<String>,
+    max_extracted_size: Option<u64>,
 ) -> CryptoResult<ArchiveResult> {
     log::info!("Batch archive decrypting {} to {}", input_path, output_dir);
 
-    if password.is_empty() {
+    if password.is_empty() && key_file_path.is_none() {
         return Err(CryptoError::FormatError(
             "Password cannot be empty".to_string(),
         ));
@@ -843,6 +1639,7 @@ pub async fn batch_decrypt_archive(
         &temp_archive_path,
         &output_dir,
         allow_overwrite,
+        max_extracted_size,
         Some(extract_progress_callback),
     );
 
@@ -877,10 +1674,107 @@ pub async fn batch_decrypt_archive(
     }
 }
 
+/// Validate an encrypted archive without extracting it (dry run)
+///
+/// Decrypts the archive to a secure temp file next to the input, runs
+/// [`validate_archive`] on it, and deletes the temp file. The report lists the
+/// entry count, total extracted size, rejected entries, and whether the
+/// decompression bomb limit would be exceeded.
+///
+/// # Arguments
+/// * `input_path` - Path to the encrypted archive
+/// * `password` - Password for decryption
+/// * `key_file_path` - Optional key file for two-factor decryption
+///
+/// # Returns
+/// ArchiveValidation report, or CryptoError if decryption fails
+#[command]
+pub async fn validate_encrypted_archive(
+    input_path: String,
+    password: String,
+    key_file_path: Option<String>,
+) -> CryptoResult<ArchiveValidation> {
+    log::info!("Validating encrypted archive: {}", input_path);
+
+    let temp_archive = decrypt_archive_to_temp(&input_path, password, key_file_path.as_deref())?;
+    let validation = validate_archive(&temp_archive)?;
+    if !validation.is_safe() {
+        log::warn!(
+            "Archive would be rejected: {} rejected entries, exceeds limit: {}",
+            validation.rejected_entries.len(),
+            validation.exceeds_limit
+        );
+    }
+    Ok(validation)
+}
+
+/// List the contents of an encrypted archive without extracting it
+///
+/// Decrypts the archive to a secure temp file next to the input, reads its
+/// entries, and deletes the temp file. Each entry carries its extension and
+/// whether extraction would write it, skip it, or reject the archive; regular
+/// entries are also summarized per extension.
+///
+/// # Arguments
+/// * `input_path` - Path to the encrypted archive
+/// * `password` - Password for decryption
+/// * `key_file_path` - Optional key file for two-factor decryption
+///
+/// # Returns
+/// ArchiveListing with every entry and the per-extension summary
+#[command]
+pub async fn list_archive_contents(
+    input_path: String,
+    password: String,
+    key_file_path: Option<String>,
+) -> CryptoResult<ArchiveListing> {
+    log::info!("Listing encrypted archive: {}", input_path);
+
+    let temp_archive = decrypt_archive_to_temp(&input_path, password, key_file_path.as_deref())?;
+    let entries = list_archive(&temp_archive)?;
+    let by_extension = group_by_extension(&entries);
+    Ok(ArchiveListing {
+        entries,
+        by_extension,
+    })
+}
+
+/// Decrypt an encrypted archive to a secure temp file next to it
+///
+/// The returned `TempPath` deletes the file on drop, so the decrypted archive
+/// never outlives the caller.
+fn decrypt_archive_to_temp(
+    input_path: &str,
+    password: String,
+    key_file_path: Option<&str>,
+) -> CryptoResult<tempfile::TempPath> {
+    if password.is_empty() && key_file_path.is_none() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+
+    let input = validate_input_path(input_path)?;
+    let temp_dir = input.parent().unwrap_or_else(|| Path::new("."));
+    let temp_archive = create_secure_tempfile(temp_dir)?.into_temp_path();
+
+    decrypt_file_streaming(
+        &input,
+        &temp_archive,
+        &Password::new(password),
+        None,
+        true, // Always overwrite temp file
+        key_file_path.map(Path::new),
+    )?;
+
+    Ok(temp_archive)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::commands::file_utils::MAX_BATCH_FILES;
+    use crate::crypto::streaming::read_stream_header;
     use std::fs;
     use std::path::Path;
     use std::sync::atomic::{AtomicU64, Ordering};
@@ -929,6 +1823,9 @@ mod tests {
             &password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         )
         .unwrap();
@@ -943,62 +1840,269 @@ mod tests {
     }
 
     #[test]
-    fn test_batch_encrypt_auto_renames_on_collision() {
-        let input_dir = tempdir().unwrap();
-        let output_dir = tempdir().unwrap();
-        let input_path = write_input_file(input_dir.path(), "sample.txt", b"alpha");
-        let output_dir_str = fs::canonicalize(output_dir.path())
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
-        let password = Password::new(test_password());
-
-        let first_output =
-            encrypt_single_file(&password, &input_path, &output_dir_str, false, None).unwrap();
-        let second_output =
-            encrypt_single_file(&password, &input_path, &output_dir_str, false, None).unwrap();
-
-        assert_ne!(first_output, second_output);
-        assert!(Path::new(&first_output).exists());
-        assert!(Path::new(&second_output).exists());
-        assert!(second_output.ends_with("sample.txt (1).encrypted"));
-    }
-
-    #[test]
-    fn test_batch_encrypt_partial_failure() {
+    fn test_transactional_batch_rolls_back_on_failure() {
         let input_dir = tempdir().unwrap();
         let output_dir = tempdir().unwrap();
-        let valid_path = write_input_file(input_dir.path(), "file1.txt", b"alpha");
-        // Canonicalize the directory path for the missing file to avoid symlink issues
-        let missing_path = fs::canonicalize(input_dir.path())
-            .unwrap()
-            .join("missing.txt")
-            .to_string_lossy()
-            .to_string();
-        let input_paths = vec![valid_path, missing_path];
-        // Canonicalize output directory to resolve symlinks
+        let missing = input_dir.path().join("file3.txt");
+        let input_paths = vec![
+            write_input_file(input_dir.path(), "file1.txt", b"alpha"),
+            write_input_file(input_dir.path(), "file2.txt", b"beta"),
+            missing.to_string_lossy().to_string(),
+            write_input_file(input_dir.path(), "file4.txt", b"delta"),
+        ];
         let output_dir_str = fs::canonicalize(output_dir.path())
             .unwrap()
             .to_string_lossy()
             .to_string();
         let mut no_progress = |_progress: BatchProgress| {};
 
-        let password = test_password();
         let result = batch_encrypt_impl(
             &input_paths,
             &output_dir_str,
-            &password,
+            &test_password(),
             false,
             None,
+            1,
+            true,
+            ProgressInterval::default(),
             &mut no_progress,
         )
         .unwrap();
 
-        assert_eq!(result.success_count, 1);
-        assert_eq!(result.failed_count, 1);
-        assert!(result.files.iter().any(|file| file.success));
-        assert!(result.files.iter().any(|file| !file.success));
-    }
+        assert_eq!(result.success_count, 0);
+        assert_eq!(result.failed_count, 4);
+        assert!(result
+            .files
+            .iter()
+            .all(|file| !file.success && file.output_path.is_none()));
+        assert!(result.files[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Rolled back"));
+        assert!(!Path::new(&output_dir_str)
+            .join("file1.txt.encrypted")
+            .exists());
+        assert!(!Path::new(&output_dir_str)
+            .join("file2.txt.encrypted")
+            .exists());
+        assert_eq!(fs::read_dir(output_dir.path()).unwrap().count(), 0);
+
+        // All-or-nothing cannot undo an overwrite
+        let overwrite = batch_encrypt_impl(
+            &input_paths,
+            &output_dir_str,
+            &test_password(),
+            true,
+            None,
+            1,
+            true,
+            ProgressInterval::default(),
+            &mut no_progress,
+        );
+        assert!(matches!(overwrite, Err(CryptoError::FormatError(_))));
+    }
+
+    #[test]
+    fn test_transactional_batch_decrypt_rolls_back_plaintext() {
+        let input_dir = tempdir().unwrap();
+        let encrypt_dir = tempdir().unwrap();
+        let decrypt_dir = tempdir().unwrap();
+        let canonical = |dir: &Path| fs::canonicalize(dir).unwrap().to_string_lossy().to_string();
+        let mut no_progress = |_progress: BatchProgress| {};
+        let password = test_password();
+        let input_paths = vec![
+            write_input_file(input_dir.path(), "file1.txt", b"alpha"),
+            write_input_file(input_dir.path(), "file2.txt", b"beta"),
+        ];
+
+        let encrypted = batch_encrypt_impl(
+            &input_paths,
+            &canonical(encrypt_dir.path()),
+            &password,
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        let mut encrypted_paths: Vec<String> = encrypted
+            .files
+            .iter()
+            .map(|file| file.output_path.clone().unwrap())
+            .collect();
+        encrypted_paths.push(
+            encrypt_dir
+                .path()
+                .join("missing.txt.encrypted")
+                .to_string_lossy()
+                .to_string(),
+        );
+
+        let result = batch_decrypt_impl(
+            &encrypted_paths,
+            &canonical(decrypt_dir.path()),
+            &password,
+            false,
+            None,
+            1,
+            true,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+
+        assert_eq!(result.success_count, 0);
+        assert!(result.files[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Rolled back"));
+        assert_eq!(fs::read_dir(decrypt_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_batch_encrypt_skips_duplicate_inputs() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let input_path = write_input_file(input_dir.path(), "file1.txt", b"alpha");
+        // Same file under a second spelling that canonicalizes to the first
+        let alias = Path::new(&input_path)
+            .parent()
+            .unwrap()
+            .join(".")
+            .join("file1.txt")
+            .to_string_lossy()
+            .to_string();
+        let input_paths = vec![input_path.clone(), alias];
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut no_progress = |_progress: BatchProgress| {};
+
+        let result = batch_encrypt_impl(
+            &input_paths,
+            &output_dir_str,
+            &test_password(),
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+
+        assert_eq!(result.files.len(), 1);
+        assert_eq!(result.files[0].input_path, input_path);
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.duplicates_skipped, 1);
+        assert_eq!(fs::read_dir(output_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_batch_encrypt_salts_unique_per_file() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        // Identical content and password: only the salt can make the keys differ
+        let input_paths: Vec<String> = (0..4)
+            .map(|i| write_input_file(input_dir.path(), &format!("same{i}.txt"), b"same"))
+            .collect();
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut no_progress = |_progress: BatchProgress| {};
+
+        let result = batch_encrypt_impl(
+            &input_paths,
+            &output_dir_str,
+            &test_password(),
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(result.success_count, input_paths.len());
+
+        let mut salts = HashSet::new();
+        for file_result in &result.files {
+            let path = file_result.output_path.as_ref().unwrap();
+            let header = read_stream_header(&mut fs::File::open(path).unwrap()).unwrap();
+            assert!(salts.insert(header.salt().to_vec()), "salt reused");
+        }
+    }
+
+    #[test]
+    fn test_batch_encrypt_auto_renames_on_collision() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let input_path = write_input_file(input_dir.path(), "sample.txt", b"alpha");
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let password = Password::new(test_password());
+
+        let first_output =
+            encrypt_single_file(&password, &input_path, &output_dir_str, false, None)
+                .unwrap()
+                .output_path;
+        let second_output =
+            encrypt_single_file(&password, &input_path, &output_dir_str, false, None)
+                .unwrap()
+                .output_path;
+
+        assert_ne!(first_output, second_output);
+        assert!(Path::new(&first_output).exists());
+        assert!(Path::new(&second_output).exists());
+        assert!(second_output.ends_with("sample.txt (1).encrypted"));
+    }
+
+    #[test]
+    fn test_batch_encrypt_partial_failure() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let valid_path = write_input_file(input_dir.path(), "file1.txt", b"alpha");
+        // Canonicalize the directory path for the missing file to avoid symlink issues
+        let missing_path = fs::canonicalize(input_dir.path())
+            .unwrap()
+            .join("missing.txt")
+            .to_string_lossy()
+            .to_string();
+        let input_paths = vec![valid_path, missing_path];
+        // Canonicalize output directory to resolve symlinks
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut no_progress = |_progress: BatchProgress| {};
+
+        let password = test_password();
+        let result = batch_encrypt_impl(
+            &input_paths,
+            &output_dir_str,
+            &password,
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.failed_count, 1);
+        assert!(result.files.iter().any(|file| file.success));
+        assert!(result.files.iter().any(|file| !file.success));
+    }
 
     #[test]
     fn test_batch_encrypt_empty_list() {
@@ -1013,6 +2117,9 @@ mod tests {
             &password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         );
 
@@ -1035,6 +2142,9 @@ mod tests {
             &password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         );
 
@@ -1065,7 +2175,8 @@ mod tests {
             false,
             None,
         )
-        .unwrap();
+        .unwrap()
+        .output_path;
         let input_paths = vec![encrypted_path];
         let mut no_progress = |_progress: BatchProgress| {};
         // Canonicalize decrypt directory to resolve symlinks
@@ -1080,6 +2191,9 @@ mod tests {
             &wrong_password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         )
         .unwrap();
@@ -1112,7 +2226,8 @@ mod tests {
             false,
             None,
         )
-        .unwrap();
+        .unwrap()
+        .output_path;
 
         let password = Password::new(password_value);
         let first_output = decrypt_single_file(
@@ -1151,6 +2266,9 @@ mod tests {
             &password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         );
 
@@ -1194,6 +2312,9 @@ mod tests {
             &roundtrip_password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         )
         .unwrap();
@@ -1215,6 +2336,9 @@ mod tests {
             &roundtrip_password,
             false,
             None,
+            1,
+            false,
+            ProgressInterval::default(),
             &mut no_progress,
         )
         .unwrap();
@@ -1233,4 +2357,604 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_batch_roundtrip_streams_multi_chunk_file() {
+        let input_dir = tempdir().unwrap();
+        let encrypt_dir = tempdir().unwrap();
+        let decrypt_dir = tempdir().unwrap();
+
+        // Several chunks plus a partial one; batch never holds a whole file
+        let content: Vec<u8> = (0..DEFAULT_CHUNK_SIZE * 3 + DEFAULT_CHUNK_SIZE / 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let input_paths = vec![write_input_file(input_dir.path(), "large.bin", &content)];
+        let encrypt_dir_str = fs::canonicalize(encrypt_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let decrypt_dir_str = fs::canonicalize(decrypt_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+
+        let mut events = Vec::new();
+        let mut record_progress = |progress: BatchProgress| events.push(progress.stage);
+        let password = test_password();
+        let encrypt_result = batch_encrypt_impl(
+            &input_paths,
+            &encrypt_dir_str,
+            &password,
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut record_progress,
+        )
+        .unwrap();
+        assert_eq!(encrypt_result.success_count, 1);
+        assert!(events.iter().any(|stage| stage == "complete"));
+
+        let encrypted_path = encrypt_result.files[0].output_path.clone().unwrap();
+
+        let mut no_progress = |_progress: BatchProgress| {};
+        let decrypt_result = batch_decrypt_impl(
+            &[encrypted_path],
+            &decrypt_dir_str,
+            &password,
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(decrypt_result.success_count, 1);
+        let decrypted_path = decrypt_result.files[0].output_path.as_ref().unwrap();
+        assert!(fs::read(decrypted_path).unwrap() == content);
+    }
+
+    #[test]
+    fn test_batch_key_file_only_roundtrip() {
+        let input_dir = tempdir().unwrap();
+        let encrypt_dir = tempdir().unwrap();
+        let decrypt_dir = tempdir().unwrap();
+        let key_file = input_dir.path().join("key.bin");
+        crate::crypto::generate_key_file(&key_file).unwrap();
+        let input_paths = vec![write_input_file(input_dir.path(), "file.txt", b"alpha")];
+        let canonical = |dir: &Path| fs::canonicalize(dir).unwrap().to_string_lossy().to_string();
+        let mut no_progress = |_progress: BatchProgress| {};
+
+        let encrypted = batch_encrypt_impl(
+            &input_paths,
+            &canonical(encrypt_dir.path()),
+            "",
+            false,
+            Some(&key_file),
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(encrypted.success_count, 1);
+        let encrypted_paths = vec![encrypted.files[0].output_path.clone().unwrap()];
+
+        let verified = batch_verify_impl(
+            &encrypted_paths,
+            "",
+            Some(&key_file),
+            false,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(verified.success_count, 1);
+
+        let decrypted = batch_decrypt_impl(
+            &encrypted_paths,
+            &canonical(decrypt_dir.path()),
+            "",
+            false,
+            Some(&key_file),
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(decrypted.success_count, 1);
+        let output_path = decrypted.files[0].output_path.as_ref().unwrap();
+        assert_eq!(fs::read(output_path).unwrap(), b"alpha");
+
+        // Without a key file an empty password is still rejected
+        assert!(batch_verify_impl(
+            &encrypted_paths,
+            "",
+            None,
+            false,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_batch_verify_reports_tampered_file() {
+        let input_dir = tempdir().unwrap();
+        let encrypt_dir = tempdir().unwrap();
+
+        // Incompressible content spanning two chunks, so the tamper lands past chunk 0
+        let mut state = 0x2545_f491u32;
+        let content: Vec<u8> = (0..DEFAULT_CHUNK_SIZE + DEFAULT_CHUNK_SIZE / 2)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect();
+        let input_paths = vec![
+            write_input_file(input_dir.path(), "good.bin", &content),
+            write_input_file(input_dir.path(), "bad.bin", &content),
+        ];
+
+        let encrypt_dir_str = fs::canonicalize(encrypt_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let password = test_password();
+        let mut no_progress = |_progress: BatchProgress| {};
+
+        let encrypted_paths: Vec<String> = batch_encrypt_impl(
+            &input_paths,
+            &encrypt_dir_str,
+            &password,
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap()
+        .files
+        .into_iter()
+        .map(|r| r.output_path.unwrap())
+        .collect();
+
+        let mut tampered = fs::read(&encrypted_paths[1]).unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        fs::write(&encrypted_paths[1], &tampered).unwrap();
+        let before: Vec<_> = fs::read_dir(encrypt_dir.path()).unwrap().collect();
+
+        let result = batch_verify_impl(
+            &encrypted_paths,
+            &password,
+            None,
+            false,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.failed_count, 1);
+        assert!(result.files[0].success);
+        assert!(!result.files[1].success);
+        assert!(result.files.iter().all(|r| r.output_path.is_none()));
+
+        // Quick mode only authenticates the first chunk
+        let quick = batch_verify_impl(
+            &encrypted_paths,
+            &password,
+            None,
+            true,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(quick.success_count, 2);
+
+        let wrong = batch_verify_impl(
+            &encrypted_paths[..1],
+            "wrong password",
+            None,
+            true,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(wrong.failed_count, 1);
+
+        // Verification writes nothing
+        let after: Vec<_> = fs::read_dir(encrypt_dir.path()).unwrap().collect();
+        assert_eq!(before.len(), after.len());
+    }
+
+    #[test]
+    fn test_effective_concurrency_clamps() {
+        let cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        assert_eq!(effective_concurrency(None), 1);
+        assert_eq!(effective_concurrency(Some(0)), 1);
+        assert_eq!(effective_concurrency(Some(1)), 1);
+        assert_eq!(effective_concurrency(Some(usize::MAX)), cpus);
+    }
+
+    #[test]
+    fn test_run_batch_respects_max_concurrent() {
+        use std::time::Duration;
+
+        let input_paths: Vec<String> = (0..8).map(|i| format!("file{}.txt", i)).collect();
+
+        for max_concurrent in [1, 2] {
+            let in_flight = AtomicUsize::new(0);
+            let peak = AtomicUsize::new(0);
+            let mut events = 0;
+
+            let results = run_batch(
+                &input_paths,
+                max_concurrent,
+                ProgressInterval::default(),
+                "encrypting",
+                |input_path| {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(format!("{}.encrypted", input_path))
+                },
+                &mut |_| events += 1,
+            );
+
+            assert!(peak.load(Ordering::SeqCst) <= max_concurrent);
+            assert_eq!(events, input_paths.len());
+            // Results stay in input order regardless of completion order
+            for (input, result) in input_paths.iter().zip(&results) {
+                assert_eq!(&result.input_path, input);
+                assert!(result.success);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_max_open_files() {
+        use std::ffi::OsStr;
+
+        assert_eq!(parse_max_open_files(None), DEFAULT_MAX_OPEN_FILES);
+        assert_eq!(parse_max_open_files(Some(OsStr::new(" 16 "))), 16);
+        assert_eq!(
+            parse_max_open_files(Some(OsStr::new("0"))),
+            DEFAULT_MAX_OPEN_FILES
+        );
+        assert_eq!(
+            parse_max_open_files(Some(OsStr::new("many"))),
+            DEFAULT_MAX_OPEN_FILES
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_batch_completes_under_low_open_file_limit() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let input_paths: Vec<String> = (0..6)
+            .map(|i| write_input_file(input_dir.path(), &format!("f{}.txt", i), b"limited"))
+            .collect();
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let password = Password::new(test_password());
+
+        // Room for a single file's handles despite four workers
+        let open_files = OpenFileLimit::new(HANDLES_PER_FILE);
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let results = run_batch_limited(
+            &input_paths,
+            4,
+            &open_files,
+            ProgressInterval::default(),
+            "encrypting",
+            |input_path| {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                let result =
+                    encrypt_single_file(&password, input_path, &output_dir_str, false, None);
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                result
+            },
+            &mut |_| {},
+        );
+
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|result| result.success));
+        assert_eq!(fs::read_dir(output_dir.path()).unwrap().count(), 6);
+        // Every permit was returned
+        assert_eq!(*open_files.available.lock().unwrap(), HANDLES_PER_FILE);
+    }
+
+    #[test]
+    fn test_progress_interval_bounds_emitted_events() {
+        let input_paths: Vec<String> = (0..500).map(|i| format!("file{}.txt", i)).collect();
+        let ok = |input_path: &str| Ok(input_path.to_string());
+
+        let mut every_file = 0;
+        run_batch(
+            &input_paths,
+            1,
+            ProgressInterval::default(),
+            "encrypting",
+            ok,
+            &mut |_| every_file += 1,
+        );
+        assert_eq!(every_file, input_paths.len());
+
+        let mut indices = Vec::new();
+        let interval = ProgressInterval {
+            files: 100,
+            ..Default::default()
+        };
+        run_batch(&input_paths, 1, interval, "encrypting", ok, &mut |p| {
+            indices.push(p.file_index)
+        });
+        assert_eq!(indices, vec![0, 100, 200, 300, 400]);
+
+        // A long time limit coalesces everything after the first event
+        let mut timed = 0;
+        let interval = ProgressInterval {
+            ms: 60_000,
+            ..Default::default()
+        };
+        run_batch(&input_paths, 2, interval, "encrypting", ok, &mut |_| {
+            timed += 1
+        });
+        assert_eq!(timed, 1);
+    }
+
+    #[test]
+    fn test_silent_batch_still_emits_complete() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let input_paths: Vec<String> = (0..3)
+            .map(|i| write_input_file(input_dir.path(), &format!("file{}.txt", i), b"data"))
+            .collect();
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let mut stages = Vec::new();
+
+        let result = batch_encrypt_impl(
+            &input_paths,
+            &output_dir_str,
+            &test_password(),
+            false,
+            None,
+            1,
+            false,
+            ProgressInterval {
+                silent: true,
+                ..Default::default()
+            },
+            &mut |p: BatchProgress| stages.push(p.stage),
+        )
+        .unwrap();
+
+        assert_eq!(result.success_count, 3);
+        assert_eq!(stages, vec!["complete".to_string()]);
+    }
+
+    #[test]
+    fn test_batch_encrypt_parallel_roundtrip() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let input_paths: Vec<String> = (0..4)
+            .map(|i| {
+                write_input_file(
+                    input_dir.path(),
+                    &format!("file{}.txt", i),
+                    format!("content {}", i).as_bytes(),
+                )
+            })
+            .collect();
+        let output_dir_str = fs::canonicalize(output_dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let password = test_password();
+        let mut no_progress = |_| {};
+
+        let result = batch_encrypt_impl(
+            &input_paths,
+            &output_dir_str,
+            &password,
+            false,
+            None,
+            2,
+            false,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+
+        assert_eq!(result.success_count, 4);
+        for (i, file_result) in result.files.iter().enumerate() {
+            let output_path = file_result.output_path.as_ref().unwrap();
+            assert!(output_path.ends_with(&format!("file{}.txt.encrypted", i)));
+        }
+    }
+
+    #[test]
+    fn test_encrypt_tree_mirrors_structure_roundtrip() {
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        let files: [(&str, &[u8]); 3] = [
+            ("top.txt", b"top level"),
+            ("a/b.txt", b"one level down"),
+            ("a/c/d.txt", b"two levels down"),
+        ];
+        fs::create_dir_all(input_dir.path().join("a/c")).unwrap();
+        for (name, content) in files {
+            fs::write(input_dir.path().join(name), content).unwrap();
+        }
+        let input_dir_str = input_dir.path().to_string_lossy().to_string();
+        let output_dir_str = output_dir.path().to_string_lossy().to_string();
+        let password = test_password();
+        let mut no_progress = |_| {};
+
+        let result = encrypt_tree_impl(
+            &input_dir_str,
+            &output_dir_str,
+            &password,
+            None,
+            true,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(result.success_count, 3);
+        assert_eq!(result.failed_count, 0);
+
+        let decrypted_dir = tempdir().unwrap();
+        for (name, content) in files {
+            let encrypted = output_dir.path().join(format!("{}.encrypted", name));
+            assert!(encrypted.exists(), "missing {}", encrypted.display());
+            let decrypted = decrypted_dir.path().join("out");
+            decrypt_file_streaming(
+                &encrypted,
+                &decrypted,
+                &Password::new(password.clone()),
+                None,
+                true,
+                None,
+            )
+            .unwrap();
+            assert_eq!(fs::read(&decrypted).unwrap(), content);
+        }
+
+        // Without recursion only the top level is encrypted
+        let flat_dir = tempdir().unwrap();
+        let flat_dir_str = flat_dir.path().to_string_lossy().to_string();
+        let result = encrypt_tree_impl(
+            &input_dir_str,
+            &flat_dir_str,
+            &password,
+            None,
+            false,
+            1,
+            ProgressInterval::default(),
+            &mut no_progress,
+        )
+        .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert!(!flat_dir.path().join("a").exists());
+
+        // Recursing into its own output would re-encrypt earlier results
+        let nested = input_dir.path().join("a").to_string_lossy().to_string();
+        assert!(matches!(
+            encrypt_tree_impl(
+                &input_dir_str,
+                &nested,
+                &password,
+                None,
+                true,
+                1,
+                ProgressInterval::default(),
+                &mut no_progress
+            ),
+            Err(CryptoError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_tree_respects_max_concurrent() {
+        use std::time::Duration;
+
+        let input_dir = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        fs::create_dir_all(input_dir.path().join("sub")).unwrap();
+        for i in 0..6 {
+            let dir = if i % 2 == 0 { "" } else { "sub" };
+            let path = input_dir.path().join(dir).join(format!("f{}.txt", i));
+            fs::write(path, b"tree file").unwrap();
+        }
+        let input_dir_str = input_dir.path().to_string_lossy().to_string();
+        let password = test_password();
+        let secret = Password::new(password.clone());
+
+        for max_concurrent in [1, 2] {
+            let output_dir = output_dir.path().join(max_concurrent.to_string());
+            fs::create_dir(&output_dir).unwrap();
+            let in_flight = AtomicUsize::new(0);
+            let peak = AtomicUsize::new(0);
+            let mut indices = Vec::new();
+
+            let result = encrypt_tree_with(
+                &input_dir_str,
+                &output_dir.to_string_lossy(),
+                &password,
+                None,
+                true,
+                max_concurrent,
+                ProgressInterval {
+                    files: 3,
+                    ..Default::default()
+                },
+                |input_path, file_output_dir| {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    let result =
+                        encrypt_single_file(&secret, input_path, file_output_dir, false, None);
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    result
+                },
+                &mut |p: BatchProgress| {
+                    if p.stage == "encrypting" {
+                        indices.push(p.file_index)
+                    }
+                },
+            )
+            .unwrap();
+
+            assert_eq!(result.success_count, 6);
+            // Each file runs Argon2id, so two workers always overlap
+            assert_eq!(peak.load(Ordering::SeqCst), max_concurrent);
+            // The progress interval is passed through too
+            assert_eq!(indices, vec![0, 3]);
+        }
+    }
+
+    #[test]
+    fn test_mirrored_output_dir_rejects_traversal() {
+        let input_root = Path::new("/in");
+        let output_root = Path::new("/out");
+        assert_eq!(
+            mirrored_output_dir(input_root, output_root, Path::new("/in/a/b.txt")).unwrap(),
+            Path::new("/out/a")
+        );
+        assert_eq!(
+            mirrored_output_dir(input_root, output_root, Path::new("/in/b.txt")).unwrap(),
+            output_root
+        );
+        assert!(matches!(
+            mirrored_output_dir(input_root, output_root, Path::new("/in/../etc/passwd")),
+            Err(CryptoError::PathTraversal(_))
+        ));
+        assert!(matches!(
+            mirrored_output_dir(input_root, output_root, Path::new("/elsewhere/b.txt")),
+            Err(CryptoError::PathTraversal(_))
+        ));
+    }
 }
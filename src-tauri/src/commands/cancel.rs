@@ -0,0 +1,88 @@
+// commands/cancel.rs - Cancelling In-Flight Operations
+//
+// Single-file encrypt/decrypt commands register a cancellation token for the
+// duration of the call. The `cancel_operation` command trips every registered
+// token; each operation then stops at its next poll point (see crypto/cancel.rs)
+// and returns `CryptoError::Cancelled`.
+
+use std::sync::Mutex;
+
+use tauri::command;
+
+use crate::crypto::CancellationToken;
+
+/// Tokens of operations that are currently running
+static ACTIVE_OPERATIONS: Mutex<Vec<CancellationToken>> = Mutex::new(Vec::new());
+
+/// Registration of one running operation; unregisters on drop
+pub struct OperationGuard {
+    token: CancellationToken,
+}
+
+impl OperationGuard {
+    /// Token to pass to the cancellable crypto functions
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_OPERATIONS.lock() {
+            active.retain(|token| !token.ptr_eq(&self.token));
+        }
+    }
+}
+
+/// Register a new cancellable operation
+pub fn begin_operation() -> OperationGuard {
+    let token = CancellationToken::new();
+    if let Ok(mut active) = ACTIVE_OPERATIONS.lock() {
+        active.push(token.clone());
+    }
+    OperationGuard { token }
+}
+
+/// Cancel all running encrypt/decrypt operations
+///
+/// # Returns
+/// Number of operations that were signalled
+#[command]
+pub async fn cancel_operation() -> usize {
+    let cancelled = cancel_all_operations();
+    log::info!("Cancelling {} running operation(s)", cancelled);
+    cancelled
+}
+
+/// Signal every running operation, returning how many were signalled
+pub fn cancel_all_operations() -> usize {
+    cancel_registered(&ACTIVE_OPERATIONS)
+}
+
+fn cancel_registered(operations: &Mutex<Vec<CancellationToken>>) -> usize {
+    let active = match operations.lock() {
+        Ok(active) => active,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    for token in active.iter() {
+        token.cancel();
+    }
+    active.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_registered_signals_every_operation() {
+        // A local registry, so running tests elsewhere are not cancelled
+        let operations = Mutex::new(Vec::new());
+        let tokens = [CancellationToken::new(), CancellationToken::new()];
+        operations.lock().unwrap().extend(tokens.iter().cloned());
+
+        assert_eq!(cancel_registered(&operations), 2);
+        assert!(tokens.iter().all(CancellationToken::is_cancelled));
+        assert_eq!(cancel_registered(&Mutex::new(Vec::new())), 0);
+    }
+}
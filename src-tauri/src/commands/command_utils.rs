@@ -4,41 +4,66 @@
 // command handlers to reduce code duplication.
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
 
-use crate::commands::file_utils::{resolve_output_path, validate_batch_count, validate_input_path};
+use crate::commands::file_utils::{
+    resolve_output_path, validate_batch_count, validate_input_path, validate_output_allowed,
+};
 use crate::commands::CryptoResponse;
 use crate::crypto::Password;
 use crate::error::{CryptoError, CryptoResult};
-use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
+use crate::events::{ProgressEvent, ProgressModel, CRYPTO_PROGRESS_EVENT};
+
+/// Sink for crypto progress events
+///
+/// Commands emit to the frontend through [`app_progress_emitter`]; the
+/// command cores take this instead of an `AppHandle` so tests can drive the
+/// exact production path with a no-op emitter.
+pub type EmitProgress = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// Emit progress events to the frontend as `CRYPTO_PROGRESS_EVENT`
+pub fn app_progress_emitter(app: AppHandle) -> EmitProgress {
+    Arc::new(move |event| {
+        let _ = app.emit(CRYPTO_PROGRESS_EVENT, event);
+    })
+}
+
+/// Discard progress events (tests driving the command cores directly)
+#[cfg(test)]
+pub(crate) fn no_progress() -> EmitProgress {
+    Arc::new(|_| {})
+}
 
 /// Validated paths and password for crypto operations
 pub struct ValidatedCryptoInputs {
     pub input: PathBuf,
     pub output: PathBuf,
     pub password: Password,
+    /// Progress weights sized to the input file
+    pub progress: ProgressModel,
 }
 
 /// Validate input/output paths and wrap password for crypto operations
 ///
 /// This function performs common validation steps:
-/// 1. Emit reading and deriving_key progress events
+/// 1. Emit the reading progress event
 /// 2. Validate and canonicalize input path (check symlinks, existence)
 /// 3. Resolve output path (handle collisions if !allow_overwrite)
-/// 4. Wrap password in secure Password type
+/// 4. Size the progress model to the input and emit the deriving_key event
+/// 5. Wrap password in secure Password type
 pub fn validate_crypto_inputs(
-    app: &AppHandle,
+    emit: &EmitProgress,
     input_path: &str,
     output_path: &str,
     password: String,
     allow_overwrite: bool,
+    key_file_path: Option<&str>,
 ) -> CryptoResult<ValidatedCryptoInputs> {
-    // Emit progress events
-    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::reading());
-    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::deriving_key());
+    emit(ProgressEvent::reading());
 
-    // Reject empty passwords
-    if password.is_empty() {
+    // Reject empty passwords, unless a key file is the only key source
+    if password.is_empty() && key_file_path.is_none() {
         return Err(CryptoError::FormatError(
             "Password cannot be empty".to_string(),
         ));
@@ -47,36 +72,42 @@ pub fn validate_crypto_inputs(
     // Validate input path (check for symlinks, canonicalize)
     let validated_input = validate_input_path(input_path)?;
     let validated_output = resolve_output_path(output_path, allow_overwrite)?;
+    let progress = progress_model_for(&validated_input);
+    emit(ProgressEvent::deriving_key());
     let password = Password::new(password);
 
     Ok(ValidatedCryptoInputs {
         input: validated_input,
         output: validated_output,
         password,
+        progress,
     })
 }
 
+/// Build a progress model from the size of the file being processed
+///
+/// Falls back to a zero-length estimate if the file cannot be stat'ed; the
+/// crypto operation itself will surface the real error.
+pub fn progress_model_for(input: &Path) -> ProgressModel {
+    let size = std::fs::metadata(input).map(|m| m.len()).unwrap_or(0);
+    ProgressModel::for_file_size(size)
+}
+
 /// Create a progress callback for streaming operations
 ///
-/// Returns a boxed callback that calculates percentage and emits progress events.
-/// The percentage is capped at 99 to leave room for the completion event.
+/// Returns a boxed callback that maps bytes processed onto the crypto stage's
+/// span of the progress model and emits progress events. The percentage is
+/// capped at 99 to leave room for the completion event.
 pub fn create_progress_callback(
-    app: AppHandle,
+    emit: EmitProgress,
+    model: ProgressModel,
     stage: &'static str,
     message: &'static str,
 ) -> Box<dyn Fn(u64, u64) + Send + Sync> {
     Box::new(move |bytes_processed: u64, total_bytes: u64| {
-        let percent = if total_bytes > 0 {
-            ((bytes_processed as f64 / total_bytes as f64) * 100.0) as u32
-        } else {
-            0
-        }
-        .min(99);
-
-        let _ = app.emit(
-            CRYPTO_PROGRESS_EVENT,
-            ProgressEvent::new(stage, percent, message),
-        );
+        let percent = model.crypto_percent(bytes_processed, total_bytes);
+
+        emit(ProgressEvent::new(stage, percent, message));
     })
 }
 
@@ -86,22 +117,27 @@ pub fn format_success_response(output_path: &Path, operation: &str) -> CryptoRes
     CryptoResponse {
         message: format!("File {} successfully: {}", operation, output_path_str),
         output_path: output_path_str,
+        already_encrypted: false,
+        ciphertext_hash: None,
+        overhead: None,
     }
 }
 
 /// Validate common inputs for batch operations
 ///
 /// Checks:
-/// - Password is not empty
+/// - Password is not empty, unless a key file is the only key source
 /// - Input paths list is not empty
 /// - File count is within limits (MAX_BATCH_FILES)
-/// - Output directory exists
+/// - Output directory exists and is inside the output allowlist and outside
+///   blocked directories
 pub fn validate_batch_inputs(
     password: &str,
+    key_file_path: Option<&Path>,
     input_paths: &[String],
     output_dir: &str,
 ) -> CryptoResult<()> {
-    if password.is_empty() {
+    if password.is_empty() && key_file_path.is_none() {
         return Err(CryptoError::FormatError(
             "Password cannot be empty".to_string(),
         ));
@@ -120,6 +156,7 @@ pub fn validate_batch_inputs(
             "Output directory does not exist".to_string(),
         ));
     }
+    validate_output_allowed(Path::new(output_dir))?;
 
     Ok(())
 }
@@ -132,16 +169,22 @@ mod tests {
     fn test_validate_batch_inputs_empty_password() {
         let temp_dir = std::env::temp_dir();
         let temp_dir_str = temp_dir.to_string_lossy();
-        let result = validate_batch_inputs("", &["file.txt".to_string()], &temp_dir_str);
+        let result = validate_batch_inputs("", None, &["file.txt".to_string()], &temp_dir_str);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Password"));
+
+        // A key file alone is a valid key source
+        let key_file = Path::new("key.bin");
+        let result =
+            validate_batch_inputs("", Some(key_file), &["file.txt".to_string()], &temp_dir_str);
+        assert!(result.is_ok());
     }
 
     #[test]
     fn test_validate_batch_inputs_empty_files() {
         let temp_dir = std::env::temp_dir();
         let temp_dir_str = temp_dir.to_string_lossy();
-        let result = validate_batch_inputs("password", &[], &temp_dir_str);
+        let result = validate_batch_inputs("password", None, &[], &temp_dir_str);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("No files"));
     }
@@ -150,6 +193,7 @@ mod tests {
     fn test_validate_batch_inputs_invalid_output_dir() {
         let result = validate_batch_inputs(
             "password",
+            None,
             &["file.txt".to_string()],
             "/nonexistent/directory",
         );
@@ -25,15 +25,19 @@
 // - AES-GCM authenticated decryption inherently prevents tampering
 // - Header tampering detected (used as AAD in each chunk)
 
-use tauri::{command, AppHandle, Emitter};
+use tauri::{command, AppHandle, Manager};
 
+use crate::commands::cancel::begin_operation;
 use crate::commands::command_utils::{
-    create_progress_callback, format_success_response, validate_crypto_inputs,
+    app_progress_emitter, create_progress_callback, format_success_response,
+    validate_crypto_inputs, EmitProgress,
 };
+use crate::commands::file_utils::{apply_preserved_mode, decrypt_target_path};
+use crate::commands::recent::{OperationKind, RecentOperations};
 use crate::commands::CryptoResponse;
-use crate::crypto::decrypt_file_streaming;
+use crate::crypto::{decrypt_file_streaming_cancellable, xattrs};
 use crate::error::CryptoResult;
-use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
+use crate::events::ProgressEvent;
 
 /// Decrypt an encrypted file with password
 ///
@@ -42,9 +46,15 @@ use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
 ///
 /// # Arguments
 /// * `input_path` - Path to the encrypted file (.encrypted)
-/// * `output_path` - Path where the decrypted file will be saved
+/// * `output_path` - Path where the decrypted file will be saved, or an existing
+///   directory (the filename is then derived by stripping `.encrypted`)
 /// * `password` - User's password (must match the one used for encryption)
 /// * `allow_overwrite` - Allow overwriting existing files (default: false)
+/// * `preserve_metadata` - Restore the owner permission bits (and, with the
+///   `xattrs` feature, extended attributes) recorded when the file was
+///   encrypted with `preserveMetadata` (Unix only, default: false)
+/// * `durable` - Sync the decrypted file to disk before returning so a crash
+///   cannot leave it empty; slower (default: false)
 ///
 /// # Returns
 /// A success response containing the message and resolved output path
@@ -72,6 +82,7 @@ use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
 /// });
 /// ```
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn decrypt_file(
     app: AppHandle,
     input_path: String,
@@ -79,33 +90,95 @@ pub async fn decrypt_file(
     password: String,
     allow_overwrite: Option<bool>,
     key_file_path: Option<String>,
+    preserve_metadata: Option<bool>,
+    durable: Option<bool>,
+) -> CryptoResult<CryptoResponse> {
+    let recorded_input = input_path.clone();
+    let result = decrypt_file_impl(
+        app_progress_emitter(app.clone()),
+        input_path,
+        output_path,
+        password,
+        allow_overwrite,
+        key_file_path,
+        preserve_metadata,
+        durable,
+    );
+    app.state::<RecentOperations>().record(
+        OperationKind::Decrypt,
+        &recorded_input,
+        result
+            .as_ref()
+            .ok()
+            .map(|response| response.output_path.as_str()),
+        result.is_ok(),
+    );
+    result
+}
+
+/// Core of `decrypt_file`, separated from the Tauri command so tests run the
+/// exact production path without a Tauri runtime
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn decrypt_file_impl(
+    emit: EmitProgress,
+    input_path: String,
+    output_path: String,
+    password: String,
+    allow_overwrite: Option<bool>,
+    key_file_path: Option<String>,
+    preserve_metadata: Option<bool>,
+    durable: Option<bool>,
 ) -> CryptoResult<CryptoResponse> {
     // Log the operation (password is NOT logged)
     log::info!("Decrypting file: {}", input_path);
 
     // Validate inputs and emit initial progress events
     let allow_overwrite = allow_overwrite.unwrap_or(false);
-    let validated =
-        validate_crypto_inputs(&app, &input_path, &output_path, password, allow_overwrite)?;
+    let output_path = decrypt_target_path(
+        std::path::Path::new(&input_path),
+        std::path::Path::new(&output_path),
+    )?;
+    let validated = validate_crypto_inputs(
+        &emit,
+        &input_path,
+        &output_path.to_string_lossy(),
+        password,
+        allow_overwrite,
+        key_file_path.as_deref(),
+    )?;
 
     // Create progress callback for streaming
-    let progress_callback =
-        create_progress_callback(app.clone(), "decrypting", "Decrypting file...");
+    let progress_callback = create_progress_callback(
+        emit.clone(),
+        validated.progress,
+        "decrypting",
+        "Decrypting file...",
+    );
 
     // Convert key file path if provided
     let kf_path = key_file_path.as_deref().map(std::path::Path::new);
 
-    // Use streaming for all files
-    decrypt_file_streaming(
-        validated.input,
+    // Use streaming for all files; `cancel_operation` can stop it early
+    let operation = begin_operation();
+    let metadata = decrypt_file_streaming_cancellable(
+        &validated.input,
         &validated.output,
         &validated.password,
         Some(progress_callback),
         allow_overwrite,
         kf_path,
+        durable.unwrap_or(false),
+        operation.token(),
     )?;
 
-    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::decrypt_complete());
+    if preserve_metadata.unwrap_or(false) {
+        if let Some(mode) = metadata.file_mode {
+            apply_preserved_mode(&validated.output, mode)?;
+        }
+        xattrs::restore(&validated.output, &metadata.xattrs);
+    }
+
+    emit(ProgressEvent::decrypt_complete());
 
     Ok(format_success_response(&validated.output, "decrypted"))
 }
@@ -113,7 +186,11 @@ pub async fn decrypt_file(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::crypto::{encrypt_file_streaming, Password, DEFAULT_CHUNK_SIZE};
+    use crate::commands::command_utils::no_progress;
+    use crate::commands::file_utils::resolve_output_path;
+    use crate::crypto::{
+        decrypt_file_streaming, encrypt_file_streaming, Password, DEFAULT_CHUNK_SIZE,
+    };
     use std::fs;
     use tempfile::NamedTempFile;
 
@@ -194,6 +271,242 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_decrypt_into_directory_target() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("notes.txt");
+        fs::write(&input_path, b"directory target").unwrap();
+
+        let encrypted_path = temp_dir.path().join("notes.txt.encrypted");
+        let password = Password::new("test_password".to_string());
+        encrypt_file_streaming(
+            &input_path,
+            &encrypted_path,
+            &password,
+            DEFAULT_CHUNK_SIZE,
+            None,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // Existing "notes.txt" in the output directory forces collision handling
+        let out_dir = tempfile::tempdir().unwrap();
+        fs::write(out_dir.path().join("notes.txt"), b"existing").unwrap();
+
+        let target = decrypt_target_path(&encrypted_path, out_dir.path()).unwrap();
+        let resolved = resolve_output_path(&target, false).unwrap();
+        assert_eq!(resolved, out_dir.path().join("notes (1).txt"));
+
+        decrypt_file_streaming(&encrypted_path, &resolved, &password, None, false, None).unwrap();
+        assert_eq!(fs::read(&resolved).unwrap(), b"directory target");
+        assert_eq!(
+            fs::read(out_dir.path().join("notes.txt")).unwrap(),
+            b"existing"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_decrypt_restores_preserved_executable_bit() {
+        use crate::crypto::{encrypt_file_streaming_with, read_file_mode, EncryptOptions};
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("run.sh");
+        fs::write(&input_path, b"#!/bin/sh\necho hi\n").unwrap();
+        fs::set_permissions(&input_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let password = Password::new("test_password".to_string());
+        let encrypted_path = temp_dir.path().join("run.sh.encrypted");
+        encrypt_file_streaming_with(
+            &input_path,
+            &encrypted_path,
+            &password,
+            EncryptOptions::new().preserve_metadata(true),
+        )
+        .unwrap();
+        assert_eq!(read_file_mode(&encrypted_path).unwrap(), Some(0o700));
+
+        let decrypt = |name: &str, preserve: bool| {
+            let path = temp_dir.path().join(name);
+            decrypt_file_impl(
+                no_progress(),
+                encrypted_path.to_string_lossy().into_owned(),
+                path.to_string_lossy().into_owned(),
+                "test_password".to_string(),
+                None,
+                None,
+                Some(preserve),
+                None,
+            )
+            .unwrap();
+            path
+        };
+        let mode = |path: &std::path::Path| fs::metadata(path).unwrap().permissions().mode();
+
+        // Without preservation the output keeps the owner-only default
+        let plain_path = decrypt("plain.sh", false);
+        assert_eq!(mode(&plain_path) & 0o777, 0o600);
+
+        let restored_path = decrypt("restored.sh", true);
+        assert_eq!(mode(&restored_path) & 0o777, 0o700);
+        assert_eq!(fs::read(&restored_path).unwrap(), b"#!/bin/sh\necho hi\n");
+    }
+
+    #[cfg(all(unix, feature = "xattrs"))]
+    #[test]
+    fn test_decrypt_restores_preserved_xattrs() {
+        use crate::crypto::{encrypt_file_streaming_with, EncryptOptions};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("tagged.txt");
+        fs::write(&input_path, b"tagged content").unwrap();
+        // Some filesystems (e.g. tmpfs without user xattrs) refuse them
+        if xattr::set(&input_path, "user.filecrypter.test", b"blue").is_err() {
+            return;
+        }
+
+        let password = Password::new("test_password".to_string());
+        let encrypted_path = temp_dir.path().join("tagged.txt.encrypted");
+        encrypt_file_streaming_with(
+            &input_path,
+            &encrypted_path,
+            &password,
+            EncryptOptions::new().preserve_metadata(true),
+        )
+        .unwrap();
+        // Encryption never copies attributes onto the encrypted file itself
+        assert!(xattr::get(&encrypted_path, "user.filecrypter.test")
+            .unwrap()
+            .is_none());
+
+        let output_path = temp_dir.path().join("restored.txt");
+        decrypt_file_impl(
+            no_progress(),
+            encrypted_path.to_string_lossy().into_owned(),
+            output_path.to_string_lossy().into_owned(),
+            "test_password".to_string(),
+            None,
+            None,
+            Some(true),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&output_path).unwrap(), b"tagged content");
+        assert_eq!(
+            xattr::get(&output_path, "user.filecrypter.test").unwrap(),
+            Some(b"blue".to_vec())
+        );
+    }
+
+    #[cfg(all(unix, feature = "xattrs"))]
+    #[test]
+    fn test_decrypt_ignores_xattrs_swapped_in_during_decryption() {
+        use crate::crypto::{encrypt_file_streaming_with, EncryptOptions};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let password = Password::new("test_password".to_string());
+        let encrypt_tagged = |name: &str, tag: &[u8]| {
+            let input_path = temp_dir.path().join(name);
+            fs::write(&input_path, vec![0x42u8; 16 * 1024]).unwrap();
+            xattr::set(&input_path, "user.filecrypter.test", tag).ok()?;
+            let encrypted_path = temp_dir.path().join(format!("{}.encrypted", name));
+            encrypt_file_streaming_with(
+                &input_path,
+                &encrypted_path,
+                &password,
+                EncryptOptions::new()
+                    .chunk_size(1024)
+                    .preserve_metadata(true),
+            )
+            .unwrap();
+            Some(encrypted_path)
+        };
+        // Some filesystems (e.g. tmpfs without user xattrs) refuse them
+        let Some(encrypted_path) = encrypt_tagged("original.txt", b"blue") else {
+            return;
+        };
+        let tampered_path = encrypt_tagged("tampered.txt", b"red").unwrap();
+
+        // Replace the input once decryption is under way; the open handle
+        // keeps reading the original, but a re-read would see the new header
+        let swapped = Arc::new(AtomicBool::new(false));
+        let emit: EmitProgress = {
+            let swapped = swapped.clone();
+            let encrypted_path = encrypted_path.clone();
+            Arc::new(move |event: ProgressEvent| {
+                if event.stage == "decrypting" && !swapped.swap(true, Ordering::SeqCst) {
+                    fs::rename(&tampered_path, &encrypted_path).unwrap();
+                }
+            })
+        };
+
+        let output_path = temp_dir.path().join("restored.txt");
+        decrypt_file_impl(
+            emit,
+            encrypted_path.to_string_lossy().into_owned(),
+            output_path.to_string_lossy().into_owned(),
+            "test_password".to_string(),
+            None,
+            None,
+            Some(true),
+            None,
+        )
+        .unwrap();
+
+        assert!(swapped.load(Ordering::SeqCst));
+        assert_eq!(
+            xattr::get(&output_path, "user.filecrypter.test").unwrap(),
+            Some(b"blue".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_decrypt_impl_leaves_no_partial_output_on_failure() {
+        use crate::crypto::{encrypt_file_streaming_with, EncryptOptions};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("data.bin");
+        fs::write(&input_path, vec![0x5Au8; 64 * 1024]).unwrap();
+
+        // Several chunks, so the early ones decrypt before the failure
+        let password = Password::new("test_password".to_string());
+        let encrypted_path = temp_dir.path().join("data.bin.encrypted");
+        encrypt_file_streaming_with(
+            &input_path,
+            &encrypted_path,
+            &password,
+            EncryptOptions::new().chunk_size(16 * 1024),
+        )
+        .unwrap();
+        let mut encrypted = fs::read(&encrypted_path).unwrap();
+        *encrypted.last_mut().unwrap() ^= 0x01;
+        fs::write(&encrypted_path, &encrypted).unwrap();
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let output_path = out_dir.path().join("data.bin");
+        let result = decrypt_file_impl(
+            no_progress(),
+            encrypted_path.to_string_lossy().into_owned(),
+            output_path.to_string_lossy().into_owned(),
+            "test_password".to_string(),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        assert!(result.is_err());
+        assert!(!output_path.exists());
+        // The temp file holding the decrypted chunks is removed as well
+        assert_eq!(fs::read_dir(out_dir.path()).unwrap().count(), 0);
+    }
+
     #[test]
     fn test_decrypt_corrupted_file() {
         // Create a corrupted "encrypted" file
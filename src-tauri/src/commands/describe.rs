@@ -0,0 +1,56 @@
+// commands/describe.rs - Encrypted File Details
+//
+// This module implements Tauri commands that inspect an encrypted file without
+// decrypting it:
+// - `describe_file` reports what the header records (format, KDF parameters,
+//   chunking, compression, key file requirement, preserved metadata) for a
+//   "Details" panel
+// - `check_truncation` confirms every declared chunk is physically present,
+//   a fast pre-check for backups
+//
+// No password is needed: only the header and chunk lengths are parsed.
+
+use tauri::command;
+
+use crate::commands::file_utils::validate_input_path;
+use crate::crypto::{check_file_truncation, describe_encrypted_file, TruncationReport};
+use crate::error::CryptoResult;
+
+/// Describe an encrypted file without decrypting it
+///
+/// # Arguments
+/// * `input_path` - Encrypted file to describe
+///
+/// # Returns
+/// A multi-line report with one `Field: value` pair per line
+///
+/// # Frontend Usage
+/// ```typescript
+/// const details = await invoke('describe_file', { inputPath: '/path/to/file.encrypted' });
+/// ```
+#[command]
+pub async fn describe_file(input_path: String) -> CryptoResult<String> {
+    let input = validate_input_path(&input_path)?;
+    describe_encrypted_file(&input)
+}
+
+/// Check whether an encrypted file was truncated or had data appended
+///
+/// Walks the chunk length fields without decrypting, so it needs no password
+/// but does not authenticate the contents (use `verify_batch` for that).
+///
+/// # Arguments
+/// * `input_path` - Encrypted file to check
+///
+/// # Returns
+/// Declared and present chunk counts plus any missing or extra bytes
+///
+/// # Frontend Usage
+/// ```typescript
+/// const report = await invoke('check_truncation', { inputPath: '/path/to/file.encrypted' });
+/// ```
+#[command]
+pub async fn check_truncation(input_path: String) -> CryptoResult<TruncationReport> {
+    let input = validate_input_path(&input_path)?;
+    check_file_truncation(&input)
+}
@@ -8,7 +8,7 @@
 // 1. Validate input path and resolve output path
 // 2. Create secure temporary file
 // 3. Generate random salt and derive encryption key using Argon2id
-// 4. Encrypt file in 1MB chunks using AES-256-GCM
+// 4. Encrypt file in 1MB chunks using AES-256-GCM (or ChaCha20-Poly1305)
 // 5. Write encrypted chunks to temporary file
 // 6. Atomically rename temporary file to final output
 //
@@ -24,15 +24,24 @@
 // - Returns success message with resolved output path
 // - Async to avoid blocking the UI thread
 
-use tauri::{command, AppHandle, Emitter};
+use std::path::Path;
 
+use tauri::{command, AppHandle, Manager};
+
+use crate::commands::cancel::begin_operation;
 use crate::commands::command_utils::{
-    create_progress_callback, format_success_response, validate_crypto_inputs,
+    app_progress_emitter, create_progress_callback, format_success_response,
+    validate_crypto_inputs, EmitProgress,
+};
+use crate::commands::file_utils::check_free_space;
+use crate::commands::recent::{OperationKind, RecentOperations};
+use crate::commands::{CryptoResponse, SizeOverhead};
+use crate::crypto::{
+    encrypt_file_streaming_hashed, is_filecrypter_file, max_encrypted_size, CipherAlgorithm,
+    CompressionConfig, EncryptOptions, KdfParams, DEFAULT_CHUNK_SIZE,
 };
-use crate::commands::CryptoResponse;
-use crate::crypto::{encrypt_file_streaming, CompressionConfig, DEFAULT_CHUNK_SIZE};
-use crate::error::CryptoResult;
-use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
+use crate::error::{CryptoResult, IoPathExt};
+use crate::events::ProgressEvent;
 
 /// Encrypt a file with password protection
 ///
@@ -48,9 +57,35 @@ use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
 /// * `allow_overwrite` - Allow overwriting existing files (default: false)
 /// * `compression_enabled` - Enable ZSTD compression before encryption (default: false)
 /// * `compression_level` - ZSTD compression level 1-22 (default: 3)
+/// * `key_file_path` - Optional key file for two-factor encryption. With an empty
+///   password the key file becomes the only key source (key-file-only mode)
+/// * `kdf_params` - Argon2id parameters to use instead of the defaults; they
+///   are validated against the same bounds as parameters read from a header
+/// * `salt_length` - Salt length in bytes, 16-64 (default: 16). Overrides the
+///   salt length in `kdf_params`
+/// * `trim_password` - Ignore trailing whitespace in the password, now
+///   and when decrypting (default: false)
+/// * `normalize_password` - Convert the password to Unicode NFC, now and when
+///   decrypting, so composed and decomposed accents match (default: false)
+/// * `key_file_commitment` - Record a key file commitment so decryption can
+///   report a wrong key file separately from a wrong password. It can be
+///   checked without the password, so only use it with generated (random)
+///   key files (default: false)
+/// * `skip_space_check` - Skip the free disk space preflight (default: false)
+/// * `preserve_metadata` - Record the input's owner permission bits (e.g. the
+///   execute bit) and, with the `xattrs` feature, its extended attributes so
+///   decryption can restore them (Unix only, default: false)
+/// * `durable` - Sync the encrypted file to disk before returning so a crash
+///   cannot leave it empty; slower (default: false)
+/// * `cipher` - `"aes-256-gcm"` or `"chacha20-poly1305"`; the latter is
+///   faster on machines without AES instructions and selects the Version 9
+///   format (default: AES-256-GCM)
 ///
 /// # Returns
-/// A success response containing the message and resolved output path
+/// A success response containing the message and resolved output path.
+/// `already_encrypted` is set when the input is itself a FileCrypter file;
+/// encryption still goes ahead, so the frontend can warn after the fact.
+/// `overhead` compares the input size with the encrypted file on disk.
 ///
 /// # Errors
 /// Returns `CryptoError` if:
@@ -58,6 +93,8 @@ use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
 /// - Password is empty
 /// - Encryption fails
 /// - Output file cannot be written
+/// - The output filesystem reports less free space than the worst-case
+///   encrypted size (`OutOfSpace`, unless `skip_space_check` is set)
 ///
 /// # Security Notes
 /// - Password is wrapped in `Password` type and zeroized after key derivation
@@ -87,50 +124,175 @@ pub async fn encrypt_file(
     compression_enabled: Option<bool>,
     compression_level: Option<i32>,
     key_file_path: Option<String>,
+    kdf_params: Option<KdfParams>,
+    salt_length: Option<u32>,
+    trim_password: Option<bool>,
+    normalize_password: Option<bool>,
+    key_file_commitment: Option<bool>,
+    skip_space_check: Option<bool>,
+    preserve_metadata: Option<bool>,
+    durable: Option<bool>,
+    cipher: Option<CipherAlgorithm>,
+) -> CryptoResult<CryptoResponse> {
+    let recorded_input = input_path.clone();
+    let result = encrypt_file_impl(
+        app_progress_emitter(app.clone()),
+        input_path,
+        output_path,
+        password,
+        allow_overwrite,
+        compression_enabled,
+        compression_level,
+        key_file_path,
+        kdf_params,
+        salt_length,
+        trim_password,
+        normalize_password,
+        key_file_commitment,
+        skip_space_check,
+        preserve_metadata,
+        durable,
+        cipher,
+    );
+    app.state::<RecentOperations>().record(
+        OperationKind::Encrypt,
+        &recorded_input,
+        result
+            .as_ref()
+            .ok()
+            .map(|response| response.output_path.as_str()),
+        result.is_ok(),
+    );
+    result
+}
+
+/// Core of `encrypt_file`, separated from the Tauri command so tests run the
+/// exact production path (atomic temp-file write included) without a Tauri
+/// runtime; pass a no-op emitter to discard progress events
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encrypt_file_impl(
+    emit: EmitProgress,
+    input_path: String,
+    output_path: String,
+    password: String,
+    allow_overwrite: Option<bool>,
+    compression_enabled: Option<bool>,
+    compression_level: Option<i32>,
+    key_file_path: Option<String>,
+    kdf_params: Option<KdfParams>,
+    salt_length: Option<u32>,
+    trim_password: Option<bool>,
+    normalize_password: Option<bool>,
+    key_file_commitment: Option<bool>,
+    skip_space_check: Option<bool>,
+    preserve_metadata: Option<bool>,
+    durable: Option<bool>,
+    cipher: Option<CipherAlgorithm>,
 ) -> CryptoResult<CryptoResponse> {
     // Log the operation (password is NOT logged)
     log::info!("Encrypting file: {}", input_path);
 
     // Validate inputs and emit initial progress events
     let allow_overwrite = allow_overwrite.unwrap_or(false);
-    let validated =
-        validate_crypto_inputs(&app, &input_path, &output_path, password, allow_overwrite)?;
+    let validated = validate_crypto_inputs(
+        &emit,
+        &input_path,
+        &output_path,
+        password,
+        allow_overwrite,
+        key_file_path.as_deref(),
+    )?;
 
-    // Build compression config if enabled
-    let compression = if compression_enabled.unwrap_or(false) {
-        Some(CompressionConfig::new(compression_level.unwrap_or(3)))
-    } else {
-        None
+    // Create progress callback for streaming
+    let progress_callback = create_progress_callback(
+        emit.clone(),
+        validated.progress,
+        "encrypting",
+        "Encrypting file...",
+    );
+
+    // Custom KDF parameters are recorded in the header; decryption reads them back
+    let base = kdf_params.unwrap_or_default();
+    let kdf_params = KdfParams {
+        salt_length: salt_length.unwrap_or(base.salt_length),
+        ..base
     };
+    kdf_params.validate()?;
 
-    // Create progress callback for streaming
-    let progress_callback =
-        create_progress_callback(app.clone(), "encrypting", "Encrypting file...");
+    // Use streaming for all files; `cancel_operation` can stop it early
+    let operation = begin_operation();
+    let mut options = EncryptOptions::new()
+        .chunk_size(DEFAULT_CHUNK_SIZE)
+        .progress(progress_callback)
+        .allow_overwrite(allow_overwrite)
+        .kdf_params(kdf_params)
+        .trim_password(trim_password.unwrap_or(false))
+        .normalize_password(normalize_password.unwrap_or(false))
+        .key_file_commitment(key_file_commitment.unwrap_or(false))
+        .preserve_metadata(preserve_metadata.unwrap_or(false))
+        .durable(durable.unwrap_or(false))
+        .cipher(cipher.unwrap_or_default())
+        .cancel(operation.token().clone());
 
-    // Convert key file path if provided
-    let kf_path = key_file_path.as_deref().map(std::path::Path::new);
+    // Build compression config if enabled
+    let compression = compression_enabled
+        .unwrap_or(false)
+        .then(|| CompressionConfig::new(compression_level.unwrap_or(3)));
+    if let Some(config) = compression.clone() {
+        options = options.compression(config);
+    }
+    if let Some(kf_path) = key_file_path.as_deref() {
+        options = options.key_file(kf_path);
+    }
+
+    let already_encrypted = input_already_encrypted(&validated.input);
+
+    // Fail before a long encryption rather than at the end of a full disk
+    let input_len = std::fs::metadata(&validated.input)
+        .with_path(&validated.input)?
+        .len();
+    if !skip_space_check.unwrap_or(false) {
+        let needed = max_encrypted_size(input_len, DEFAULT_CHUNK_SIZE, compression.as_ref())?;
+        check_free_space(&validated.output, needed)?;
+    }
 
-    // Use streaming for all files
-    encrypt_file_streaming(
+    let ciphertext_hash = encrypt_file_streaming_hashed(
         validated.input,
         &validated.output,
         &validated.password,
-        DEFAULT_CHUNK_SIZE,
-        Some(progress_callback),
-        allow_overwrite,
-        compression,
-        kf_path,
+        options,
     )?;
 
-    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::encrypt_complete());
+    emit(ProgressEvent::encrypt_complete());
 
-    Ok(format_success_response(&validated.output, "encrypted"))
+    let output_len = std::fs::metadata(&validated.output)
+        .with_path(&validated.output)?
+        .len();
+    let mut response = format_success_response(&validated.output, "encrypted");
+    response.already_encrypted = already_encrypted;
+    response.ciphertext_hash = Some(ciphertext_hash.to_hex().to_string());
+    response.overhead = Some(SizeOverhead::new(input_len, output_len));
+    Ok(response)
+}
+
+/// Check for the common "encrypted twice" mistake, logging a warning if found
+fn input_already_encrypted(input_path: &Path) -> bool {
+    let already_encrypted = is_filecrypter_file(input_path);
+    if already_encrypted {
+        log::warn!(
+            "Input is already a FileCrypter file; encrypting it again: {}",
+            input_path.display()
+        );
+    }
+    already_encrypted
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::crypto::{decrypt_file_streaming, Password};
+    use crate::commands::command_utils::no_progress;
+    use crate::commands::decrypt::decrypt_file_impl;
+    use crate::crypto::{decrypt_file_streaming, encrypt_file_streaming, Password};
     use std::fs;
     use tempfile::NamedTempFile;
 
@@ -178,6 +340,135 @@ mod tests {
         assert_eq!(decrypted_content, b"Test content for streaming");
     }
 
+    #[test]
+    fn test_already_encrypted_input_is_flagged() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("notes.txt");
+        fs::write(&input_path, b"plain notes").unwrap();
+        let encrypted_path = temp_dir.path().join("notes.txt.encrypted");
+        let password = Password::new("test_password".to_string());
+        encrypt_file_streaming(
+            &input_path,
+            &encrypted_path,
+            &password,
+            DEFAULT_CHUNK_SIZE,
+            None,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert!(!input_already_encrypted(&input_path));
+        assert!(input_already_encrypted(&encrypted_path));
+        assert!(!input_already_encrypted(&temp_dir.path().join("missing")));
+        // A single plausible version byte is not enough
+        let short_path = temp_dir.path().join("short.bin");
+        fs::write(&short_path, [4u8]).unwrap();
+        assert!(!input_already_encrypted(&short_path));
+    }
+
+    #[test]
+    fn test_encrypt_impl_roundtrip_through_command_cores() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("report.txt");
+        fs::write(&input_path, b"quarterly numbers").unwrap();
+        let path_string = |path: &Path| path.to_string_lossy().into_owned();
+
+        let encrypted_path = temp_dir.path().join("report.txt.encrypted");
+        let response = encrypt_file_impl(
+            no_progress(),
+            path_string(&input_path),
+            path_string(&encrypted_path),
+            "test_password".to_string(),
+            None,
+            Some(true),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(response.output_path, path_string(&encrypted_path));
+        assert!(!response.already_encrypted);
+        let expected_hash = blake3::hash(&fs::read(&encrypted_path).unwrap());
+        assert_eq!(
+            response.ciphertext_hash,
+            Some(expected_hash.to_hex().to_string())
+        );
+        let overhead = response.overhead.unwrap();
+        assert_eq!(overhead.input_size, b"quarterly numbers".len() as u64);
+        assert_eq!(
+            overhead.output_size,
+            fs::metadata(&encrypted_path).unwrap().len()
+        );
+        assert_eq!(
+            overhead.overhead_bytes,
+            overhead.output_size as i64 - overhead.input_size as i64
+        );
+
+        let decrypted_path = temp_dir.path().join("report.out.txt");
+        decrypt_file_impl(
+            no_progress(),
+            path_string(&encrypted_path),
+            path_string(&decrypted_path),
+            "test_password".to_string(),
+            None,
+            None,
+            None,
+            Some(true),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&decrypted_path).unwrap(), b"quarterly numbers");
+    }
+
+    #[test]
+    fn test_encrypt_reports_negative_overhead_when_compressed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("log.txt");
+        fs::write(&input_path, b"same line again\n".repeat(4096)).unwrap();
+        let encrypted_path = temp_dir.path().join("log.txt.encrypted");
+        let path_string = |path: &Path| path.to_string_lossy().into_owned();
+
+        let response = encrypt_file_impl(
+            no_progress(),
+            path_string(&input_path),
+            path_string(&encrypted_path),
+            "test_password".to_string(),
+            None,
+            Some(true),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let overhead = response.overhead.unwrap();
+        assert_eq!(
+            overhead.output_size,
+            fs::metadata(&encrypted_path).unwrap().len()
+        );
+        assert!(overhead.overhead_bytes < 0);
+        assert!(overhead.overhead_percent.unwrap() < 0.0);
+
+        assert_eq!(SizeOverhead::new(0, 100).overhead_percent, None);
+        assert_eq!(SizeOverhead::new(200, 250).overhead_percent, Some(25.0));
+    }
+
     #[test]
     fn test_encrypt_file_streaming_small_file() {
         // Test that streaming works correctly for very small files
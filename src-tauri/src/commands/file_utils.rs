@@ -5,7 +5,12 @@
 // - Atomic file writes (write to temp, then rename)
 // - Path validation (symlink detection, canonicalization)
 // - Output path resolution with collision handling
+// - Atomic create-new-or-next file reservation
+// - Optional output-directory allowlist (FILECRYPTER_OUTPUT_ALLOWLIST)
+// - Protected-directory blocklist (FILECRYPTER_OUTPUT_BLOCKLIST)
+// - Decrypted filename derivation (directory output targets)
 // - Batch operation validation
+// - Glob-based exclusion of batch/archive inputs
 //
 // Note: File size validation was removed as streaming handles all file sizes.
 // The atomic_write() function is kept for testing and potential future use,
@@ -14,7 +19,11 @@
 use std::fs;
 use std::path::{Component, Path, PathBuf};
 
-use crate::error::{CryptoError, CryptoResult};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rand::{rngs::SysRng, TryRng};
+
+use crate::commands::archive::compute_common_prefix;
+use crate::error::{CryptoError, CryptoResult, IoPathExt};
 
 /// Maximum number of files in a batch operation
 pub const MAX_BATCH_FILES: usize = 1000;
@@ -22,32 +31,250 @@ pub const MAX_BATCH_FILES: usize = 1000;
 /// Maximum number of collision attempts when auto-renaming output files
 const MAX_COLLISION_ATTEMPTS: u32 = 1000;
 
+/// Attempts at a timestamped name once every " (n)" suffix is taken
+const MAX_FALLBACK_ATTEMPTS: u32 = 16;
+
+/// Environment variable holding the output-directory allowlist
+///
+/// Uses the platform path-list separator (`:` on Unix, `;` on Windows).
+/// When unset, outputs may be written anywhere. When set, every output must
+/// resolve inside one of the listed directories; an empty value denies all.
+pub const OUTPUT_ALLOWLIST_ENV: &str = "FILECRYPTER_OUTPUT_ALLOWLIST";
+
+/// Read the configured output-directory allowlist, if any
+fn output_allowlist() -> Option<Vec<PathBuf>> {
+    let raw = std::env::var_os(OUTPUT_ALLOWLIST_ENV)?;
+    Some(
+        std::env::split_paths(&raw)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .collect(),
+    )
+}
+
+/// Environment variable holding the output-directory blocklist
+///
+/// Uses the platform path-list separator. When unset, the platform's system
+/// directories ([`default_output_blocklist`]) are blocked; when set, the
+/// listed directories replace the defaults and an empty value blocks nothing.
+pub const OUTPUT_BLOCKLIST_ENV: &str = "FILECRYPTER_OUTPUT_BLOCKLIST";
+
+/// System directories that outputs may not be written into by default
+#[cfg(unix)]
+fn default_output_blocklist() -> Vec<PathBuf> {
+    [
+        "/bin", "/boot", "/dev", "/etc", "/lib", "/proc", "/sbin", "/sys", "/usr",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect()
+}
+
+/// System directories that outputs may not be written into by default
+#[cfg(windows)]
+fn default_output_blocklist() -> Vec<PathBuf> {
+    ["SystemRoot", "ProgramFiles", "ProgramFiles(x86)"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// System directories that outputs may not be written into by default
+#[cfg(not(any(unix, windows)))]
+fn default_output_blocklist() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Read the configured output-directory blocklist, or the platform defaults
+fn output_blocklist() -> Vec<PathBuf> {
+    match std::env::var_os(OUTPUT_BLOCKLIST_ENV) {
+        Some(raw) => std::env::split_paths(&raw)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .collect(),
+        None => default_output_blocklist(),
+    }
+}
+
+/// Reject output paths outside the configured allowlist or inside a blocked
+/// directory.
+///
+/// The allowlist check is a no-op unless [`OUTPUT_ALLOWLIST_ENV`] is set.
+pub fn validate_output_allowed(path: &Path) -> CryptoResult<()> {
+    if let Some(allowlist) = output_allowlist() {
+        check_output_allowlist(path, &allowlist)?;
+    }
+    check_output_blocklist(path, &output_blocklist())
+}
+
+/// Check that `path` does not resolve inside any of the `blocklist` directories.
+///
+/// Canonicalized like the allowlist, so `..` segments and symlinks cannot be
+/// used to reach a blocked directory. Blocklist entries that do not exist are
+/// ignored.
+fn check_output_blocklist(path: &Path, blocklist: &[PathBuf]) -> CryptoResult<()> {
+    if blocklist.is_empty() {
+        return Ok(());
+    }
+
+    let target = canonicalize_output_target(path)?;
+
+    let blocked = blocklist
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| target.starts_with(dir));
+
+    if blocked {
+        Err(CryptoError::InvalidPath(
+            "Output path is inside a protected directory".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that `path` resolves inside one of the `allowlist` directories.
+///
+/// Both sides are canonicalized so symlinks and `..` segments cannot be used
+/// to escape. Allowlist entries that do not exist are ignored.
+fn check_output_allowlist(path: &Path, allowlist: &[PathBuf]) -> CryptoResult<()> {
+    let target = canonicalize_output_target(path)?;
+
+    let allowed = allowlist
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| target.starts_with(dir));
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(CryptoError::InvalidPath(
+            "Output path is outside the allowed directories".to_string(),
+        ))
+    }
+}
+
+/// Canonicalize an output path that may not exist yet.
+///
+/// The deepest existing ancestor is canonicalized and the remaining
+/// components are appended; those must be plain names (no `..`), since
+/// they cannot be resolved against the filesystem.
+fn canonicalize_output_target(path: &Path) -> CryptoResult<PathBuf> {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let name = existing
+            .file_name()
+            .ok_or_else(|| CryptoError::InvalidPath("Invalid output path".to_string()))?;
+        missing.push(name);
+        existing = match existing.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+    }
+
+    let mut resolved = existing.canonicalize().with_path(existing)?;
+    for name in missing.into_iter().rev() {
+        let name = Path::new(name);
+        if !matches!(name.components().next(), Some(Component::Normal(_))) {
+            return Err(CryptoError::InvalidPath("Invalid output path".to_string()));
+        }
+        resolved.push(name);
+    }
+
+    Ok(resolved)
+}
+
 /// Resolve an output path based on overwrite preference.
 ///
 /// If `allow_overwrite` is false and the target exists, this returns
-/// a new path with a " (n)" suffix (e.g., "file (1).txt").
+/// a new path with a " (n)" suffix (e.g., "file (1).txt"). The scan starts
+/// at the first index not already used in the directory, so a crowded
+/// directory costs one listing rather than one lookup per existing copy.
+/// Once all [`MAX_COLLISION_ATTEMPTS`] indices are taken, a timestamp and
+/// random suffix is used instead (e.g., "file (20250101-120000-1a2b3c4d).txt").
+///
+/// Fails with `InvalidPath` if the path is outside the output allowlist or
+/// inside a blocked directory.
 pub fn resolve_output_path<P: AsRef<Path>>(
     path: P,
     allow_overwrite: bool,
 ) -> CryptoResult<PathBuf> {
     let path = path.as_ref();
+    validate_output_allowed(path)?;
 
     if allow_overwrite || !path.exists() {
         return Ok(path.to_path_buf());
     }
 
-    for index in 1..=MAX_COLLISION_ATTEMPTS {
+    // The listing is a hint; each candidate is still checked on disk (e.g.
+    // case-insensitive filesystems can report a different spelling)
+    for index in first_unused_collision_index(path)..=MAX_COLLISION_ATTEMPTS {
         let candidate = build_collision_path(path, index)?;
         if !candidate.exists() {
             return Ok(candidate);
         }
     }
 
+    for _ in 0..MAX_FALLBACK_ATTEMPTS {
+        let candidate = build_fallback_collision_path(path)?;
+        if !candidate.exists() {
+            return Ok(candidate);
+        }
+    }
+
     Err(CryptoError::InvalidPath(
         "Unable to find available output filename".to_string(),
     ))
 }
 
+/// Smallest " (n)" index for `path` not present in its directory listing.
+///
+/// Returns 1 if the directory cannot be listed, and a value past
+/// [`MAX_COLLISION_ATTEMPTS`] if every index is taken.
+fn first_unused_collision_index(path: &Path) -> u32 {
+    let (Some(file_name), Some(parent)) = (path.file_name(), path.parent()) else {
+        return 1;
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return 1;
+    };
+
+    let stem = path.file_stem().unwrap_or(file_name).to_string_lossy();
+    let prefix = format!("{} (", stem);
+    let suffix = match path.extension() {
+        Some(ext) => format!(").{}", ext.to_string_lossy()),
+        None => ")".to_string(),
+    };
+
+    let mut used = vec![false; MAX_COLLISION_ATTEMPTS as usize + 1];
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let index = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(prefix.as_str()))
+            .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+            .and_then(|digits| digits.parse::<u32>().ok());
+        if let Some(index @ 1..=MAX_COLLISION_ATTEMPTS) = index {
+            used[index as usize] = true;
+        }
+    }
+
+    (1..=MAX_COLLISION_ATTEMPTS)
+        .find(|&index| !used[index as usize])
+        .unwrap_or(MAX_COLLISION_ATTEMPTS + 1)
+}
+
 /// Build a collision-avoidance path by inserting " (n)" before the extension.
 ///
 /// Examples:
@@ -55,6 +282,62 @@ pub fn resolve_output_path<P: AsRef<Path>>(
 /// - "archive.tar.gz" with index 2 -> "archive.tar (2).gz"
 /// - "noext" with index 1 -> "noext (1)"
 fn build_collision_path(path: &Path, index: u32) -> CryptoResult<PathBuf> {
+    build_suffixed_path(path, &index.to_string())
+}
+
+/// Build a fallback path with a " (timestamp-random)" suffix.
+fn build_fallback_collision_path(path: &Path) -> CryptoResult<PathBuf> {
+    let suffix = format!(
+        "{}-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        random_hex(4)?
+    );
+    build_suffixed_path(path, &suffix)
+}
+
+/// `len` random bytes from the OS CSPRNG as lowercase hex
+pub fn random_hex(len: usize) -> CryptoResult<String> {
+    let mut random = vec![0u8; len];
+    SysRng
+        .try_fill_bytes(&mut random)
+        .map_err(|e| CryptoError::Io(std::io::Error::other(e.to_string())))?;
+    Ok(random.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Create `path`, or the next free collision name, and return it with its handle.
+///
+/// Unlike [`resolve_output_path`], which only checks for existence, each
+/// candidate is opened with `create_new`, so the returned path is owned by
+/// the caller even if another process races for the same name. The caller
+/// may then replace the (empty) file, e.g. by persisting a temp file over it.
+///
+/// Fails with `InvalidPath` if the path is outside the output allowlist or
+/// inside a blocked directory.
+pub fn create_new_or_next(path: &Path) -> CryptoResult<(PathBuf, fs::File)> {
+    let mut candidate = path.to_path_buf();
+    validate_output_allowed(&candidate)?;
+
+    for _ in 0..=MAX_COLLISION_ATTEMPTS {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((candidate, file)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err).with_path(&candidate),
+        }
+        // Someone else holds this name; pick the next free one and race again
+        candidate = resolve_output_path(path, false)?;
+    }
+
+    Err(CryptoError::InvalidPath(
+        "Unable to find available output filename".to_string(),
+    ))
+}
+
+/// Insert " (suffix)" before the extension of `path`.
+fn build_suffixed_path(path: &Path, suffix: &str) -> CryptoResult<PathBuf> {
     let parent = path.parent().unwrap_or_else(|| Path::new("."));
     let file_name = path
         .file_name()
@@ -67,14 +350,64 @@ fn build_collision_path(path: &Path, index: u32) -> CryptoResult<PathBuf> {
         .to_string();
 
     let candidate_name = if let Some(ext) = path.extension() {
-        format!("{} ({}).{}", stem, index, ext.to_string_lossy())
+        format!("{} ({}).{}", stem, suffix, ext.to_string_lossy())
     } else {
-        format!("{} ({})", stem, index)
+        format!("{} ({})", stem, suffix)
     };
 
     Ok(parent.join(candidate_name))
 }
 
+/// Derive the decrypted filename for an encrypted input.
+///
+/// Strips a trailing ".encrypted" extension; otherwise appends ".decrypted".
+pub fn decrypted_filename(input_path: &Path) -> CryptoResult<String> {
+    let input_filename = input_path
+        .file_name()
+        .ok_or_else(|| CryptoError::FormatError("Invalid input path".to_string()))?
+        .to_string_lossy();
+
+    Ok(
+        if let Some(stripped) = input_filename.strip_suffix(".encrypted") {
+            stripped.to_string()
+        } else {
+            format!("{}.decrypted", input_filename)
+        },
+    )
+}
+
+/// Resolve the decryption target when the output may be a directory.
+///
+/// If `output_path` is an existing directory, the filename is derived from the
+/// input via [`decrypted_filename`]. Otherwise `output_path` is returned as-is.
+/// Collision handling is left to [`resolve_output_path`].
+pub fn decrypt_target_path(input_path: &Path, output_path: &Path) -> CryptoResult<PathBuf> {
+    if output_path.is_dir() {
+        Ok(output_path.join(decrypted_filename(input_path)?))
+    } else {
+        Ok(output_path.to_path_buf())
+    }
+}
+
+/// Restore owner permission bits recorded by metadata preservation
+///
+/// `mode` comes from [`crate::crypto::read_file_mode`] and only holds owner
+/// bits. Owner read/write are always kept so the file stays usable, so in
+/// practice this restores the execute bit; group and other stay cleared.
+#[cfg(unix)]
+pub fn apply_preserved_mode(path: &Path, mode: u32) -> CryptoResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = (mode & 0o700) | 0o600;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).with_path(path)
+}
+
+/// No permission bits are recorded off Unix, so there is nothing to restore
+#[cfg(not(unix))]
+pub fn apply_preserved_mode(_path: &Path, _mode: u32) -> CryptoResult<()> {
+    Ok(())
+}
+
 #[cfg(all(test, unix))]
 pub fn secure_write<P: AsRef<Path>>(path: P, data: &[u8]) -> Result<(), std::io::Error> {
     use std::io::Write;
@@ -109,11 +442,15 @@ pub fn atomic_write<P: AsRef<Path>>(
     data: &[u8],
     allow_overwrite: bool,
 ) -> CryptoResult<PathBuf> {
+    use crate::crypto::streaming::{remove_overwrite_target, OutputLock};
+    use crate::security::persist_tempfile;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
     let requested_path = path.as_ref();
+    // resolve_output_path also enforces the output allowlist
     let resolved_path = resolve_output_path(requested_path, allow_overwrite)?;
+    let _lock = OutputLock::acquire(&resolved_path)?;
     let parent = resolved_path.parent().unwrap_or_else(|| Path::new("."));
 
     let mut temp_file = NamedTempFile::new_in(parent).map_err(CryptoError::Io)?;
@@ -145,18 +482,16 @@ pub fn atomic_write<P: AsRef<Path>>(
     temp_file.write_all(data).map_err(CryptoError::Io)?;
     temp_file.flush().map_err(CryptoError::Io)?;
 
-    if allow_overwrite && resolved_path.exists() {
-        fs::remove_file(&resolved_path).map_err(CryptoError::Io)?;
+    if allow_overwrite {
+        remove_overwrite_target(&resolved_path)?;
     }
 
-    match temp_file.persist(&resolved_path) {
+    match persist_tempfile(temp_file, &resolved_path) {
         Ok(_) => Ok(resolved_path),
         Err(e) => {
             if !allow_overwrite && e.error.kind() == std::io::ErrorKind::AlreadyExists {
                 let next_path = resolve_output_path(requested_path, false)?;
-                let temp_file = e.file;
-                temp_file
-                    .persist(&next_path)
+                persist_tempfile(e.file, &next_path)
                     .map_err(|persist_err| CryptoError::Io(persist_err.error))?;
                 return Ok(next_path);
             }
@@ -172,16 +507,20 @@ pub fn atomic_write<P: AsRef<Path>>(
 /// Checks:
 /// - Path exists (for input files)
 /// - Path is not a symlink (prevents symlink attacks)
+/// - Path can be opened for reading
 /// - Returns canonicalized path
+///
+/// I/O failures are reported as `IoPath`, naming the file.
 pub fn validate_input_path(path: &str) -> CryptoResult<PathBuf> {
     let path = Path::new(path);
 
     // Check if path exists
     if !path.exists() {
-        return Err(CryptoError::Io(std::io::Error::new(
+        return Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             "File not found",
-        )));
+        ))
+        .with_path(path);
     }
 
     // Check for symlinks in any path component
@@ -189,15 +528,18 @@ pub fn validate_input_path(path: &str) -> CryptoResult<PathBuf> {
 
     // Reject non-regular files (directories, devices, FIFOs, etc.).
     // All files are processed via streaming encryption with chunking.
-    let metadata = fs::metadata(path)?;
+    let metadata = fs::metadata(path).with_path(path)?;
     if !metadata.file_type().is_file() {
         return Err(CryptoError::InvalidPath(
             "Input path must be a regular file".to_string(),
         ));
     }
 
+    // Fail here, naming the file, rather than deep inside the operation
+    fs::File::open(path).with_path(path)?;
+
     // Canonicalize the path
-    let canonical = fs::canonicalize(path)?;
+    let canonical = fs::canonicalize(path).with_path(path)?;
     Ok(canonical)
 }
 
@@ -256,6 +598,161 @@ pub fn validate_batch_count(count: usize) -> CryptoResult<()> {
     Ok(())
 }
 
+/// Free space (in bytes) available to the current user on the filesystem holding `path`
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+    // SAFETY: statvfs is plain old data, so all-zero is a valid value
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is NUL-terminated and stat is a valid, writable statvfs
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // Field widths differ between platforms (u32 on macOS, u64 on Linux)
+    #[allow(clippy::unnecessary_cast)]
+    Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+/// Free space (in bytes) available to the current user on the volume holding `path`
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path_wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut free_bytes: u64 = 0;
+    // SAFETY: path_wide is NUL-terminated; the unused outputs may be null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            path_wide.as_ptr(),
+            &mut free_bytes,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(free_bytes)
+}
+
+/// Free space query is not implemented on this platform
+#[cfg(not(any(unix, windows)))]
+pub fn available_space(_path: &Path) -> std::io::Result<u64> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// Fail early if the filesystem holding `output_path` cannot fit `needed` bytes.
+///
+/// Best-effort: if free space cannot be determined the check passes, and the
+/// real write reports any disk-full error. Free space can also change while
+/// the operation runs.
+///
+/// # Errors
+/// Returns `OutOfSpace` if the reported free space is less than `needed`.
+pub fn check_free_space(output_path: &Path, needed: u64) -> CryptoResult<()> {
+    check_free_space_with(output_path, needed, available_space)
+}
+
+fn check_free_space_with<F>(output_path: &Path, needed: u64, available: F) -> CryptoResult<()>
+where
+    F: Fn(&Path) -> std::io::Result<u64>,
+{
+    let dir = output_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    match available(dir) {
+        Ok(free) if free < needed => Err(CryptoError::OutOfSpace(format!(
+            "{} bytes needed, {} bytes available in {}",
+            needed,
+            free,
+            dir.display()
+        ))),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            log::warn!("Could not check free space in {}: {}", dir.display(), e);
+            Ok(())
+        }
+    }
+}
+
+/// Remove input paths matching any of the given glob exclude patterns.
+///
+/// Patterns are matched against each path relative to the common parent of all
+/// inputs (the same relative path used for archive entry names). Patterns without
+/// a `/` match at any depth, so `.DS_Store` or `*.tmp` skip those files in every
+/// subdirectory; patterns with a `/` (e.g. `build/**`) match from the common parent.
+///
+/// # Errors
+/// Returns `InvalidPath` if a pattern is not a valid glob.
+pub fn filter_excluded_paths(
+    input_paths: &[String],
+    exclude_patterns: &[String],
+) -> CryptoResult<Vec<String>> {
+    if exclude_patterns.is_empty() {
+        return Ok(input_paths.to_vec());
+    }
+
+    let exclude_set = build_exclude_set(exclude_patterns)?;
+    let paths: Vec<PathBuf> = input_paths.iter().map(PathBuf::from).collect();
+    let common_prefix = compute_common_prefix(&paths);
+
+    let kept = input_paths
+        .iter()
+        .zip(&paths)
+        .filter(|(_, path)| {
+            let relative = path
+                .strip_prefix(&common_prefix)
+                .ok()
+                .filter(|relative| !relative.as_os_str().is_empty() && !relative.is_absolute())
+                .or_else(|| path.file_name().map(Path::new))
+                .unwrap_or(path);
+            let excluded = exclude_set.is_match(relative);
+            if excluded {
+                log::debug!("Excluding {} from batch", relative.display());
+            }
+            !excluded
+        })
+        .map(|(input, _)| input.clone())
+        .collect();
+
+    Ok(kept)
+}
+
+/// Compile exclude patterns into a single glob set.
+fn build_exclude_set(patterns: &[String]) -> CryptoResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            continue;
+        }
+        // Unanchored patterns match at any depth
+        let anchored = if pattern.contains('/') {
+            pattern.to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob = Glob::new(&anchored).map_err(|e| {
+            CryptoError::InvalidPath(format!("Invalid exclude pattern '{}': {}", pattern, e))
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| CryptoError::InvalidPath(format!("Invalid exclude patterns: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,6 +798,21 @@ mod tests {
         assert_eq!(files, vec!["output.bin".to_string()]);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_write_overwrite_read_only_target() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("locked.txt");
+        fs::write(&path, b"original").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+
+        let result = atomic_write(&path, b"replacement", true);
+        assert!(matches!(result, Err(CryptoError::ReadOnlyTarget(_))));
+        assert_eq!(fs::read(&path).unwrap(), b"original");
+    }
+
     #[test]
     fn test_atomic_write_collision_renames() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -318,6 +830,72 @@ mod tests {
         assert_eq!(fs::read(second_path).unwrap(), b"second");
     }
 
+    #[test]
+    fn test_resolve_output_path_skips_to_first_unused_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("report.txt");
+        fs::write(&path, b"original").unwrap();
+        for index in [1, 2, 3, 5] {
+            fs::write(build_collision_path(&path, index).unwrap(), b"copy").unwrap();
+        }
+
+        let resolved = resolve_output_path(&path, false).unwrap();
+        assert_eq!(resolved, temp_dir.path().join("report (4).txt"));
+    }
+
+    #[test]
+    fn test_resolve_output_path_falls_back_after_exhausting_indices() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("report.txt");
+        fs::write(&path, b"original").unwrap();
+        for index in 1..=MAX_COLLISION_ATTEMPTS {
+            fs::write(build_collision_path(&path, index).unwrap(), b"copy").unwrap();
+        }
+
+        let resolved = resolve_output_path(&path, false).unwrap();
+        assert!(!resolved.exists());
+        assert_eq!(resolved.parent(), path.parent());
+        assert_eq!(resolved.extension().unwrap(), "txt");
+
+        // "report (YYYYMMDD-HHMMSS-xxxxxxxx).txt"
+        let name = resolved.file_name().unwrap().to_string_lossy().to_string();
+        let suffix = name
+            .strip_prefix("report (")
+            .and_then(|rest| rest.strip_suffix(").txt"))
+            .unwrap();
+        let parts: Vec<&str> = suffix.split('-').collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), 8);
+        assert_eq!(parts[1].len(), 6);
+        assert!(u32::from_str_radix(parts[2], 16).is_ok());
+    }
+
+    #[test]
+    fn test_create_new_or_next_from_two_threads() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let target = temp_dir.path().join("shared.bin");
+        let barrier = std::sync::Barrier::new(2);
+
+        let (first, second) = std::thread::scope(|scope| {
+            let create = || {
+                barrier.wait();
+                let (path, mut file) = create_new_or_next(&target).unwrap();
+                std::io::Write::write_all(&mut file, path.to_string_lossy().as_bytes()).unwrap();
+                path
+            };
+            let first = scope.spawn(create);
+            let second = scope.spawn(create);
+            (first.join().unwrap(), second.join().unwrap())
+        });
+
+        // Both threads got their own file; neither overwrote the other
+        assert_ne!(first, second);
+        assert!(first == target || second == target);
+        for path in [&first, &second] {
+            assert_eq!(fs::read_to_string(path).unwrap(), path.to_string_lossy());
+        }
+    }
+
     #[test]
     fn test_validate_batch_count() {
         assert!(validate_batch_count(100).is_ok());
@@ -325,6 +903,34 @@ mod tests {
         assert!(validate_batch_count(1001).is_err());
     }
 
+    #[test]
+    fn test_check_free_space_with_low_space_filesystem() {
+        let output = Path::new("/mock/volume/out.encrypted");
+        let low_space = |dir: &Path| {
+            assert_eq!(dir, Path::new("/mock/volume"));
+            Ok(1000)
+        };
+
+        assert!(check_free_space_with(output, 999, low_space).is_ok());
+        assert!(check_free_space_with(output, 1000, low_space).is_ok());
+        assert!(matches!(
+            check_free_space_with(output, 1001, low_space),
+            Err(CryptoError::OutOfSpace(_))
+        ));
+
+        // An unknown amount of free space does not block the operation
+        let unknown = |_: &Path| Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+        assert!(check_free_space_with(output, u64::MAX, unknown).is_ok());
+    }
+
+    #[test]
+    #[cfg(any(unix, windows))]
+    fn test_available_space_reports_temp_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(available_space(dir.path()).is_ok());
+        assert!(check_free_space(&dir.path().join("out"), 0).is_ok());
+    }
+
     #[test]
     fn test_validate_input_path_rejects_directory() {
         let dir = tempfile::tempdir().unwrap();
@@ -332,6 +938,31 @@ mod tests {
         assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
     }
 
+    #[test]
+    fn test_validate_input_path_errors_name_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.txt");
+        let err = validate_input_path(missing.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, CryptoError::IoPath { .. }));
+        assert!(err.to_string().contains(missing.to_str().unwrap()));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let locked = dir.path().join("locked.txt");
+            fs::write(&locked, b"secret").unwrap();
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+            // Root can open the file anyway, so only check the error when there is one
+            if let Err(err) = validate_input_path(locked.to_str().unwrap()) {
+                let message = err.to_string();
+                assert!(message.contains("Permission denied"), "{}", message);
+                assert!(message.contains(locked.to_str().unwrap()), "{}", message);
+            }
+        }
+    }
+
     #[test]
     fn test_resolve_output_path_allow_overwrite_returns_same() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -388,4 +1019,211 @@ mod tests {
         let result = resolve_output_path(&path, false).unwrap();
         assert_eq!(result.file_name().unwrap().to_string_lossy(), "noext (1)");
     }
+
+    #[test]
+    fn test_decrypt_target_path_directory_strips_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input = Path::new("/some/where/report.pdf.encrypted");
+
+        let target = decrypt_target_path(input, temp_dir.path()).unwrap();
+        assert_eq!(target, temp_dir.path().join("report.pdf"));
+    }
+
+    #[test]
+    fn test_decrypt_target_path_directory_without_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input = Path::new("/some/where/blob.bin");
+
+        let target = decrypt_target_path(input, temp_dir.path()).unwrap();
+        assert_eq!(target, temp_dir.path().join("blob.bin.decrypted"));
+    }
+
+    #[test]
+    fn test_decrypt_target_path_file_passthrough() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("chosen.txt");
+
+        let target = decrypt_target_path(Path::new("x.encrypted"), &output).unwrap();
+        assert_eq!(target, output);
+    }
+
+    #[test]
+    fn test_filter_excluded_paths_skips_matches() {
+        let base = Path::new("/data/photos");
+        let inputs: Vec<String> = [
+            "a.jpg",
+            ".DS_Store",
+            "trip/b.jpg",
+            "trip/.DS_Store",
+            "trip/Thumbs.db",
+            "scratch.tmp",
+            "trip/notes.tmp",
+        ]
+        .iter()
+        .map(|name| base.join(name).to_string_lossy().into_owned())
+        .collect();
+        let patterns = vec![
+            ".DS_Store".to_string(),
+            "Thumbs.db".to_string(),
+            "*.tmp".to_string(),
+        ];
+
+        let kept = filter_excluded_paths(&inputs, &patterns).unwrap();
+
+        let expected: Vec<String> = ["a.jpg", "trip/b.jpg"]
+            .iter()
+            .map(|name| base.join(name).to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(kept, expected);
+    }
+
+    #[test]
+    fn test_filter_excluded_paths_anchored_pattern() {
+        let base = Path::new("/data/project");
+        let inputs: Vec<String> = ["src/main.rs", "build/out.bin", "docs/build/index.html"]
+            .iter()
+            .map(|name| base.join(name).to_string_lossy().into_owned())
+            .collect();
+
+        let kept = filter_excluded_paths(&inputs, &["build/**".to_string()]).unwrap();
+
+        assert_eq!(kept.len(), 2);
+        assert!(kept.iter().all(|path| !path.contains("out.bin")));
+    }
+
+    #[test]
+    fn test_filter_excluded_paths_no_patterns_keeps_all() {
+        let inputs = vec!["/a/one.txt".to_string(), "/a/two.txt".to_string()];
+        assert_eq!(filter_excluded_paths(&inputs, &[]).unwrap(), inputs);
+    }
+
+    #[test]
+    fn test_filter_excluded_paths_invalid_pattern() {
+        let inputs = vec!["/a/one.txt".to_string()];
+        let result = filter_excluded_paths(&inputs, &["[unclosed".to_string()]);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_atomic_write_concurrent_same_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("shared.bin");
+        let payloads = [vec![0xAAu8; 1024 * 1024], vec![0xBBu8; 1024 * 1024]];
+        let barrier = std::sync::Barrier::new(payloads.len());
+
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = payloads
+                .iter()
+                .map(|data| {
+                    let (path, barrier) = (&path, &barrier);
+                    scope.spawn(move || {
+                        barrier.wait();
+                        atomic_write(path, data, true)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        // Each writer either finished or was turned away by the lock
+        assert!(results.iter().any(|result| result.is_ok()));
+        for result in &results {
+            assert!(matches!(result, Ok(_) | Err(CryptoError::OutputLocked(_))));
+        }
+
+        // The output is one complete payload, never a mix, and the lock is gone
+        let written = fs::read(&path).unwrap();
+        assert!(payloads.contains(&written));
+        assert!(!temp_dir.path().join(".shared.bin.lock").exists());
+    }
+
+    #[test]
+    fn test_output_allowlist_permits_allowed_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let allowed = temp_dir.path().join("allowed");
+        fs::create_dir(&allowed).unwrap();
+        let allowlist = vec![allowed.clone()];
+
+        // Existing directory, new file inside it, and a new nested file
+        assert!(check_output_allowlist(&allowed, &allowlist).is_ok());
+        assert!(check_output_allowlist(&allowed.join("out.encrypted"), &allowlist).is_ok());
+        assert!(check_output_allowlist(&allowed.join("sub").join("out.txt"), &allowlist).is_ok());
+    }
+
+    #[test]
+    fn test_output_allowlist_rejects_other_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let allowed = temp_dir.path().join("allowed");
+        let other = temp_dir.path().join("other");
+        fs::create_dir(&allowed).unwrap();
+        fs::create_dir(&other).unwrap();
+        let allowlist = vec![allowed.clone()];
+
+        let result = check_output_allowlist(&other.join("out.encrypted"), &allowlist);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+
+        // `..` cannot be used to climb out of the allowed directory
+        let escape = allowed.join("..").join("other").join("out.encrypted");
+        let result = check_output_allowlist(&escape, &allowlist);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+
+        // An empty allowlist denies everything
+        let result = check_output_allowlist(&allowed.join("out.encrypted"), &[]);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_allowlist_rejects_symlink_escape() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let allowed = temp_dir.path().join("allowed");
+        let other = temp_dir.path().join("other");
+        fs::create_dir(&allowed).unwrap();
+        fs::create_dir(&other).unwrap();
+        std::os::unix::fs::symlink(&other, allowed.join("link")).unwrap();
+
+        let result =
+            check_output_allowlist(&allowed.join("link").join("out.encrypted"), &[allowed]);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_output_blocklist_rejects_blocked_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let blocked = temp_dir.path().join("system");
+        let other = temp_dir.path().join("other");
+        fs::create_dir(&blocked).unwrap();
+        fs::create_dir(&other).unwrap();
+        let blocklist = vec![blocked.clone()];
+
+        let result = check_output_blocklist(&blocked.join("out.txt"), &blocklist);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+
+        // `..` cannot be used to sneak back into the blocked directory
+        let sneak = other.join("..").join("system").join("sub").join("out.txt");
+        let result = check_output_blocklist(&sneak, &blocklist);
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_output_blocklist_permits_other_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let blocked = temp_dir.path().join("system");
+        let other = temp_dir.path().join("other");
+        fs::create_dir(&blocked).unwrap();
+        fs::create_dir(&other).unwrap();
+
+        assert!(check_output_blocklist(&other.join("out.txt"), &[blocked]).is_ok());
+        // The default blocklist leaves ordinary temp directories writable
+        assert!(
+            check_output_blocklist(&other.join("out.txt"), &default_output_blocklist()).is_ok()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_default_output_blocklist_covers_etc() {
+        let result = check_output_blocklist(Path::new("/etc/out.txt"), &default_output_blocklist());
+        assert!(matches!(result, Err(CryptoError::InvalidPath(_))));
+    }
 }
@@ -1,14 +1,19 @@
 // commands/keyfile.rs - Key File Generation Command
 //
-// This module provides the Tauri command for generating key files.
+// This module provides the Tauri commands for generating (randomly or from a
+// seed phrase, to disk or as base64) and checking key files, plus encoding
+// small secrets as recovery blobs for paper backups.
 // Key files are used as a second authentication factor for file encryption.
 
 use std::path::Path;
 
+use base64::Engine;
 use tauri::command;
+use zeroize::Zeroizing;
 
 use crate::commands::file_utils::validate_no_symlinks;
 use crate::commands::CryptoResponse;
+use crate::crypto::Password;
 use crate::error::{CryptoError, CryptoResult};
 
 /// Validate that the output path's parent directory exists, is a directory,
@@ -69,13 +74,185 @@ pub async fn generate_key_file(output_path: String) -> CryptoResult<CryptoRespon
     Ok(CryptoResponse {
         message: format!("Key file generated successfully: {}", output_path),
         output_path,
+        already_encrypted: false,
+        ciphertext_hash: None,
+        overhead: None,
     })
 }
 
+/// Generate key file contents without writing them to disk.
+///
+/// Returns 32 random bytes as standard base64, for display as a QR code or
+/// storage in a secret manager. The raw bytes are zeroized once encoded; the
+/// returned string is the caller's responsibility.
+///
+/// # Returns
+/// The base64-encoded key file contents
+#[command]
+pub async fn generate_key_file_base64() -> CryptoResult<String> {
+    log::info!("Generating key file contents (not written to disk)");
+
+    let key_data = crate::crypto::keyfile::generate_key_file_bytes()?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(key_data.as_slice()))
+}
+
+/// Derive a key file deterministically from a seed phrase.
+///
+/// The same seed always produces the same key file, so a lost key file can be
+/// recreated. The seed effectively becomes a second password.
+///
+/// # Arguments
+/// * `output_path` - Path where the key file will be saved
+/// * `seed_phrase` - Memorized seed phrase (not logged)
+///
+/// # Returns
+/// A success response with the output path
+#[command]
+pub async fn generate_key_file_from_seed(
+    output_path: String,
+    seed_phrase: String,
+) -> CryptoResult<CryptoResponse> {
+    log::info!("Generating key file from seed: {}", output_path);
+
+    let path = Path::new(&output_path);
+
+    validate_output_path(path)?;
+
+    let seed_phrase = Password::new(seed_phrase);
+    crate::crypto::keyfile::generate_key_file_from_seed(path, &seed_phrase)?;
+
+    Ok(CryptoResponse {
+        message: format!("Key file generated from seed: {}", output_path),
+        output_path,
+        already_encrypted: false,
+        ciphertext_hash: None,
+        overhead: None,
+    })
+}
+
+/// Check a selected key file for suspiciously low entropy.
+///
+/// Advisory only: the frontend shows the returned warning but still lets the
+/// user continue with the key file.
+///
+/// # Arguments
+/// * `key_file_path` - Path to the selected key file
+///
+/// # Returns
+/// A warning message if the key file looks accidental, otherwise `None`
+#[command]
+pub async fn check_key_file(key_file_path: String) -> CryptoResult<Option<String>> {
+    let path = Path::new(&key_file_path);
+    let warning = crate::crypto::keyfile::validate_key_file_entropy(path)?;
+
+    if let Some(ref message) = warning {
+        log::warn!("Low-entropy key file selected: {}", message);
+    }
+
+    Ok(warning)
+}
+
+/// Largest payload a recovery blob may carry
+///
+/// 1 KB encodes to 1368 base64 characters, which fits a version 40 QR code
+/// even at error correction level Q (1663 bytes) and leaves room to print it.
+pub const MAX_RECOVERY_BLOB_SIZE: usize = 1024;
+
+/// Reject recovery blob payloads that are empty or too large for a QR code
+fn check_recovery_blob_size(len: usize) -> CryptoResult<()> {
+    if len == 0 || len > MAX_RECOVERY_BLOB_SIZE {
+        return Err(CryptoError::FormatError(format!(
+            "Recovery blob data must be 1 to {} bytes (got {})",
+            MAX_RECOVERY_BLOB_SIZE, len
+        )));
+    }
+    Ok(())
+}
+
+/// Encode bytes as a recovery blob
+fn encode_recovery_blob(data: &[u8]) -> CryptoResult<String> {
+    check_recovery_blob_size(data.len())?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(data))
+}
+
+/// Decode a recovery blob, ignoring whitespace left by transcription
+fn decode_recovery_blob(blob: &str) -> CryptoResult<Zeroizing<Vec<u8>>> {
+    let compact: Zeroizing<String> =
+        Zeroizing::new(blob.chars().filter(|c| !c.is_ascii_whitespace()).collect());
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(compact.as_bytes())
+        .map(Zeroizing::new)
+        .map_err(|_| CryptoError::FormatError("Recovery blob is not valid base64".into()))?;
+    check_recovery_blob_size(data.len())?;
+    Ok(data)
+}
+
+/// Encode a small secret (e.g. key file bytes) for printing as a QR code.
+///
+/// The input bytes are zeroized once encoded; the returned string is the
+/// caller's responsibility.
+///
+/// # Arguments
+/// * `data` - Bytes to encode, at most [`MAX_RECOVERY_BLOB_SIZE`]
+///
+/// # Returns
+/// The base64-encoded recovery blob
+#[command]
+pub async fn export_recovery_blob(data: Vec<u8>) -> CryptoResult<String> {
+    log::info!("Exporting recovery blob ({} bytes)", data.len());
+
+    let data = Zeroizing::new(data);
+    encode_recovery_blob(&data)
+}
+
+/// Decode a recovery blob created by `export_recovery_blob`.
+///
+/// # Arguments
+/// * `blob` - The recovery blob; whitespace and line breaks are ignored
+///
+/// # Returns
+/// The original bytes
+#[command]
+pub async fn import_recovery_blob(blob: String) -> CryptoResult<Vec<u8>> {
+    let blob = Zeroizing::new(blob);
+    let data = decode_recovery_blob(&blob)?;
+    Ok(data.to_vec())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_recovery_blob_roundtrip() {
+        let key = crate::crypto::keyfile::generate_key_file_bytes().unwrap();
+        let blob = encode_recovery_blob(key.as_slice()).unwrap();
+        assert_eq!(blob.len(), 44);
+
+        // Paper transcriptions come back with line breaks and spaces
+        let (head, tail) = blob.split_at(20);
+        let transcribed = format!("{}\n  {} ", head, tail);
+        let decoded = decode_recovery_blob(&transcribed).unwrap();
+        assert_eq!(decoded.as_slice(), key.as_slice());
+    }
+
+    #[test]
+    fn test_recovery_blob_size_limits() {
+        let max = vec![0xA5u8; MAX_RECOVERY_BLOB_SIZE];
+        let blob = encode_recovery_blob(&max).unwrap();
+        assert_eq!(decode_recovery_blob(&blob).unwrap().as_slice(), &max[..]);
+
+        let too_large = vec![0xA5u8; MAX_RECOVERY_BLOB_SIZE + 1];
+        assert!(matches!(
+            encode_recovery_blob(&too_large),
+            Err(CryptoError::FormatError(_))
+        ));
+        let oversized_blob = base64::engine::general_purpose::STANDARD.encode(&too_large);
+        assert!(decode_recovery_blob(&oversized_blob).is_err());
+        assert!(encode_recovery_blob(&[]).is_err());
+        assert!(decode_recovery_blob("not*base64").is_err());
+    }
+
     #[test]
     fn test_validate_output_path_valid() {
         let dir = tempfile::tempdir().unwrap();
@@ -11,12 +11,20 @@ use serde::Serialize;
 // which are called by the batch_encrypt_archive and batch_decrypt_archive commands.
 pub(crate) mod archive;
 
+mod audit;
 mod batch;
+mod benchmark;
+mod cancel;
 pub mod command_utils;
 mod decrypt;
+mod describe;
 mod encrypt;
 pub mod file_utils;
 mod keyfile;
+mod preview;
+mod recent;
+mod recompress;
+mod wipe;
 
 /// Standard response for encrypt/decrypt commands.
 ///
@@ -28,10 +36,59 @@ pub struct CryptoResponse {
     pub message: String,
     /// Resolved output file path (may differ from requested if auto-renamed)
     pub output_path: String,
+    /// Set by `encrypt_file` when the input was already a FileCrypter file
+    /// (a likely "encrypted twice" mistake); the output is still written
+    pub already_encrypted: bool,
+    /// BLAKE3 hash (hex) of the encrypted output, set by `encrypt_file` for
+    /// audit logs; computed while writing, so it costs no second read
+    pub ciphertext_hash: Option<String>,
+    /// Size change from encryption, set by `encrypt_file`
+    pub overhead: Option<SizeOverhead>,
+}
+
+/// How much larger (or, with compression, smaller) encryption made a file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SizeOverhead {
+    /// Size of the plaintext input in bytes
+    pub input_size: u64,
+    /// Size of the encrypted output on disk in bytes
+    pub output_size: u64,
+    /// `output_size - input_size`; negative when compression saved space
+    pub overhead_bytes: i64,
+    /// Overhead relative to the input size (None for an empty input)
+    pub overhead_percent: Option<f64>,
+}
+
+impl SizeOverhead {
+    /// Compare an input size with the size of its encrypted output
+    pub fn new(input_size: u64, output_size: u64) -> Self {
+        let overhead_bytes = output_size as i64 - input_size as i64;
+        Self {
+            input_size,
+            output_size,
+            overhead_bytes,
+            overhead_percent: (input_size > 0)
+                .then(|| overhead_bytes as f64 * 100.0 / input_size as f64),
+        }
+    }
 }
 
 // Re-export commands for registration in lib.rs
-pub use batch::{batch_decrypt, batch_decrypt_archive, batch_encrypt, batch_encrypt_archive};
+pub use audit::audit_vault;
+pub use batch::{
+    batch_decrypt, batch_decrypt_archive, batch_encrypt, batch_encrypt_archive, encrypt_tree,
+    list_archive_contents, validate_encrypted_archive, verify_batch,
+};
+pub use benchmark::{benchmark, estimate_duration};
+pub use cancel::cancel_operation;
 pub use decrypt::decrypt_file;
+pub use describe::{check_truncation, describe_file};
 pub use encrypt::encrypt_file;
-pub use keyfile::generate_key_file;
+pub use keyfile::{
+    check_key_file, export_recovery_blob, generate_key_file, generate_key_file_base64,
+    generate_key_file_from_seed, import_recovery_blob,
+};
+pub use preview::{cleanup_preview_files, decrypt_to_temp_and_open};
+pub use recent::{clear_recent_operations, get_recent_operations, RecentOperations};
+pub use recompress::set_compression;
+pub use wipe::wipe_secrets;
@@ -0,0 +1,270 @@
+// commands/preview.rs - Decrypt-and-View Command Handler
+//
+// This module implements the "view decrypted" feature: an encrypted file is
+// decrypted into a private temporary directory and opened with the OS default
+// application, without the user choosing an output location.
+//
+// Security:
+// - Each preview lives in its own temp directory (Unix: 0o700, Windows: owner-only DACL)
+// - The decrypted file itself is created with 0o600 / owner-only DACL by streaming decryption
+// - Previews are shredded (overwritten with zeros, then deleted) after `PREVIEW_LIFETIME`
+// - Any previews still on disk are shredded when the app exits
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{command, AppHandle, Emitter};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::commands::command_utils::{
+    app_progress_emitter, create_progress_callback, progress_model_for,
+};
+use crate::commands::file_utils::validate_input_path;
+use crate::commands::CryptoResponse;
+use crate::crypto::{decrypt_file_streaming, Password};
+use crate::error::{CryptoError, CryptoResult};
+use crate::events::{ProgressEvent, CRYPTO_PROGRESS_EVENT};
+use crate::security;
+
+/// How long a decrypted preview stays on disk before it is shredded (10 minutes)
+const PREVIEW_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// Prefix for preview temp directories
+const PREVIEW_DIR_PREFIX: &str = "filecrypter-view-";
+
+/// Preview directories that still need to be shredded
+static PREVIEW_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Decrypt a file into a private temp directory and open it with the OS default handler.
+///
+/// The decrypted copy is shredded after 10 minutes or when the app exits,
+/// whichever comes first.
+///
+/// # Arguments
+/// * `input_path` - Path to the encrypted file
+/// * `password` - User's password
+/// * `key_file_path` - Optional key file used during encryption
+///
+/// # Returns
+/// A success response whose `output_path` is the temporary decrypted file
+#[command]
+pub async fn decrypt_to_temp_and_open(
+    app: AppHandle,
+    input_path: String,
+    password: String,
+    key_file_path: Option<String>,
+) -> CryptoResult<CryptoResponse> {
+    log::info!("Decrypting file for viewing: {}", input_path);
+
+    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::reading());
+
+    if password.is_empty() && key_file_path.is_none() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+    let password = Password::new(password);
+    let input = validate_input_path(&input_path)?;
+    let kf_path = key_file_path.as_deref().map(Path::new);
+
+    let model = progress_model_for(&input);
+    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::deriving_key());
+
+    let progress_callback = create_progress_callback(
+        app_progress_emitter(app.clone()),
+        model,
+        "decrypting",
+        "Decrypting file...",
+    );
+    let output = decrypt_to_temp(&input, &password, kf_path, Some(progress_callback))?;
+
+    let _ = app.emit(CRYPTO_PROGRESS_EVENT, ProgressEvent::decrypt_complete());
+
+    if let Err(e) = app
+        .opener()
+        .open_path(output.to_string_lossy(), None::<&str>)
+    {
+        log::warn!("Failed to open decrypted preview: {}", e);
+        if let Some(dir) = output.parent() {
+            shred_preview(dir);
+        }
+        return Err(CryptoError::Io(std::io::Error::other(
+            "Failed to open decrypted file",
+        )));
+    }
+
+    // Shred the preview after its lifetime; exit cleanup handles the rest
+    if let Some(dir) = output.parent().map(Path::to_path_buf) {
+        std::thread::spawn(move || {
+            std::thread::sleep(PREVIEW_LIFETIME);
+            shred_preview(&dir);
+        });
+    }
+
+    let output_path = output.to_string_lossy().to_string();
+    Ok(CryptoResponse {
+        message: "File decrypted for viewing. The temporary copy will be deleted automatically."
+            .to_string(),
+        output_path,
+        already_encrypted: false,
+        ciphertext_hash: None,
+        overhead: None,
+    })
+}
+
+/// Decrypt a file into a fresh private temp directory and register it for cleanup.
+///
+/// The decrypted file keeps the input's name with `.encrypted` stripped so the
+/// OS can pick the right application from its extension.
+fn decrypt_to_temp(
+    input: &Path,
+    password: &Password,
+    key_file_path: Option<&Path>,
+    progress_callback: Option<crate::crypto::streaming::ProgressCallback>,
+) -> CryptoResult<PathBuf> {
+    let temp_dir = tempfile::Builder::new()
+        .prefix(PREVIEW_DIR_PREFIX)
+        .tempdir()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(temp_dir.path(), std::fs::Permissions::from_mode(0o700))?;
+    }
+    security::set_owner_only_dacl(temp_dir.path())?;
+
+    let file_name = input
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.strip_suffix(".encrypted").unwrap_or(name))
+        .filter(|name| !name.is_empty())
+        .unwrap_or("decrypted");
+    let output = temp_dir.path().join(file_name);
+
+    // On failure the TempDir guard removes the (empty) directory
+    decrypt_file_streaming(
+        input,
+        &output,
+        password,
+        progress_callback,
+        false,
+        key_file_path,
+    )?;
+
+    let dir = temp_dir.keep();
+    register_preview(dir.clone());
+
+    Ok(dir.join(file_name))
+}
+
+fn register_preview(dir: PathBuf) {
+    if let Ok(mut dirs) = PREVIEW_DIRS.lock() {
+        dirs.push(dir);
+    }
+}
+
+/// Shred every file in a preview directory and remove it.
+///
+/// Does nothing (and returns false) if the directory was already cleaned up.
+fn shred_preview(dir: &Path) -> bool {
+    let registered = match PREVIEW_DIRS.lock() {
+        Ok(mut dirs) => {
+            let before = dirs.len();
+            dirs.retain(|d| d != dir);
+            dirs.len() != before
+        }
+        Err(_) => false,
+    };
+    if !registered {
+        return false;
+    }
+
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if let Err(e) = security::shred_file(&entry.path()) {
+                log::warn!("Failed to shred preview file: {}", e);
+            }
+        }
+    }
+    if let Err(e) = std::fs::remove_dir_all(dir) {
+        log::warn!("Failed to remove preview directory: {}", e);
+    }
+    true
+}
+
+/// Shred all decrypted previews that are still on disk.
+///
+/// Called when the application exits and by `wipe_secrets`. Returns the
+/// number of previews removed.
+pub fn cleanup_preview_files() -> usize {
+    let dirs: Vec<PathBuf> = match PREVIEW_DIRS.lock() {
+        Ok(dirs) => dirs.clone(),
+        Err(_) => return 0,
+    };
+    dirs.iter().filter(|dir| shred_preview(dir)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{encrypt_file_streaming, DEFAULT_CHUNK_SIZE};
+    use std::fs;
+
+    fn encrypt_sample(dir: &Path, password: &Password) -> PathBuf {
+        let input = dir.join("report.txt");
+        fs::write(&input, b"Preview content").unwrap();
+        let encrypted = dir.join("report.txt.encrypted");
+        encrypt_file_streaming(
+            &input,
+            &encrypted,
+            password,
+            DEFAULT_CHUNK_SIZE,
+            None,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+        encrypted
+    }
+
+    #[test]
+    fn test_decrypt_to_temp_writes_private_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let password = Password::new("preview_password".to_string());
+        let encrypted = encrypt_sample(dir.path(), &password);
+
+        let output = decrypt_to_temp(&encrypted, &password, None, None).unwrap();
+
+        assert_eq!(output.file_name().unwrap(), "report.txt");
+        assert_ne!(output.parent().unwrap(), dir.path());
+        assert_eq!(fs::read(&output).unwrap(), b"Preview content");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&output).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            let dir_mode = fs::metadata(output.parent().unwrap())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(dir_mode & 0o777, 0o700);
+        }
+
+        assert!(shred_preview(output.parent().unwrap()));
+        assert!(!output.exists());
+        assert!(!output.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn test_decrypt_to_temp_wrong_password_leaves_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let password = Password::new("preview_password".to_string());
+        let encrypted = encrypt_sample(dir.path(), &password);
+
+        let wrong = Password::new("wrong_password".to_string());
+        assert!(decrypt_to_temp(&encrypted, &wrong, None, None).is_err());
+    }
+}
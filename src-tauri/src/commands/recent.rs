@@ -0,0 +1,160 @@
+// commands/recent.rs - Recently Processed Files
+//
+// Single-file encrypt/decrypt commands record each attempt here so the
+// frontend can offer a "recent files" list. Only paths, the operation kind,
+// a timestamp, and the outcome are kept - never passwords or key material.
+// The list lives in Tauri managed state for the lifetime of the app and is
+// bounded.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{command, State};
+
+/// Maximum number of operations kept; the oldest are dropped first
+pub const MAX_RECENT_OPERATIONS: usize = 50;
+
+/// Kind of a recorded operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    /// `encrypt_file`
+    Encrypt,
+    /// `decrypt_file`
+    Decrypt,
+}
+
+/// One encrypt/decrypt attempt
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentOperation {
+    /// File that was encrypted or decrypted
+    pub input_path: String,
+    /// Resolved output path (None if the operation failed)
+    pub output_path: Option<String>,
+    /// Whether the file was encrypted or decrypted
+    pub operation: OperationKind,
+    /// When the operation finished, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Whether the operation succeeded
+    pub success: bool,
+}
+
+/// Recorded operations, newest first
+///
+/// Registered with `.manage()` in `lib.rs`; commands reach it through
+/// `State<'_, RecentOperations>` or `app.state::<RecentOperations>()`.
+#[derive(Debug, Default)]
+pub struct RecentOperations {
+    operations: Mutex<VecDeque<RecentOperation>>,
+}
+
+impl RecentOperations {
+    /// Record a finished operation, evicting the oldest beyond the limit
+    pub fn record(
+        &self,
+        operation: OperationKind,
+        input_path: &str,
+        output_path: Option<&str>,
+        success: bool,
+    ) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        let entry = RecentOperation {
+            input_path: input_path.to_string(),
+            output_path: output_path.map(str::to_string),
+            operation,
+            timestamp,
+            success,
+        };
+
+        let mut recent = self.lock();
+        recent.push_front(entry);
+        recent.truncate(MAX_RECENT_OPERATIONS);
+    }
+
+    /// Snapshot of the recorded operations, newest first
+    pub fn snapshot(&self) -> Vec<RecentOperation> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Forget all recorded operations
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Lock the list, recovering it if a previous holder panicked
+    fn lock(&self) -> MutexGuard<'_, VecDeque<RecentOperation>> {
+        match self.operations.lock() {
+            Ok(recent) => recent,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// List recently processed files
+///
+/// # Returns
+/// Up to `MAX_RECENT_OPERATIONS` operations, newest first
+#[command]
+pub fn get_recent_operations(recent: State<'_, RecentOperations>) -> Vec<RecentOperation> {
+    recent.snapshot()
+}
+
+/// Forget all recorded operations
+#[command]
+pub fn clear_recent_operations(recent: State<'_, RecentOperations>) {
+    recent.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_operations_recorded_and_bounded() {
+        let recent_operations = RecentOperations::default();
+
+        recent_operations.record(
+            OperationKind::Encrypt,
+            "/docs/a.txt",
+            Some("/docs/a.txt.encrypted"),
+            true,
+        );
+        recent_operations.record(OperationKind::Decrypt, "/docs/b.encrypted", None, false);
+
+        let recent = recent_operations.snapshot();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].input_path, "/docs/b.encrypted");
+        assert_eq!(recent[0].operation, OperationKind::Decrypt);
+        assert!(!recent[0].success);
+        assert_eq!(recent[0].output_path, None);
+        assert_eq!(
+            recent[1].output_path.as_deref(),
+            Some("/docs/a.txt.encrypted")
+        );
+        assert!(recent[1].success);
+
+        for i in 0..MAX_RECENT_OPERATIONS + 5 {
+            recent_operations.record(
+                OperationKind::Encrypt,
+                &format!("/docs/{}.txt", i),
+                None,
+                true,
+            );
+        }
+        let recent = recent_operations.snapshot();
+        assert_eq!(recent.len(), MAX_RECENT_OPERATIONS);
+        let newest = format!("/docs/{}.txt", MAX_RECENT_OPERATIONS + 4);
+        assert_eq!(recent[0].input_path, newest);
+        assert!(recent
+            .iter()
+            .all(|op| op.operation == OperationKind::Encrypt));
+
+        recent_operations.clear();
+        assert!(recent_operations.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+// commands/recompress.rs - Change Compression of an Encrypted File
+//
+// This module implements a Tauri command that rewrites an existing encrypted
+// file at a different ZSTD level without changing its password or key file.
+//
+// The file is decrypted and re-encrypted in a single streaming pass (see
+// `reencrypt_file_streaming`), and the original is replaced atomically.
+
+use tauri::command;
+
+use crate::commands::command_utils::format_success_response;
+use crate::commands::file_utils::validate_input_path;
+use crate::commands::CryptoResponse;
+use crate::crypto::{reencrypt_file_streaming, CompressionConfig, Password};
+use crate::error::{CryptoError, CryptoResult};
+
+/// Recompress an encrypted file in place at a new ZSTD level
+///
+/// # Arguments
+/// * `input_path` - Encrypted file to rewrite
+/// * `password` - Password the file was encrypted with
+/// * `key_file_path` - Key file, if the file was encrypted with one
+/// * `new_level` - ZSTD compression level 1-22
+///
+/// # Returns
+/// A success response containing the message and the (unchanged) file path
+#[command]
+pub async fn set_compression(
+    input_path: String,
+    password: String,
+    key_file_path: Option<String>,
+    new_level: i32,
+) -> CryptoResult<CryptoResponse> {
+    log::info!("Recompressing file at level {}: {}", new_level, input_path);
+
+    if password.is_empty() && key_file_path.is_none() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+
+    let input = validate_input_path(&input_path)?;
+    reencrypt_file_streaming(
+        &input,
+        &Password::new(password),
+        key_file_path.as_deref().map(std::path::Path::new),
+        Some(CompressionConfig::new(new_level)),
+    )?;
+
+    Ok(format_success_response(&input, "recompressed"))
+}
@@ -0,0 +1,31 @@
+// commands/wipe.rs - "Lock Everything Now"
+//
+// A panic button for shared machines. The backend keeps no session password
+// or derived key between commands: each operation owns its `Password` and
+// key material and zeroizes them on drop. Wiping therefore cancels every
+// running operation, so each one unwinds and drops its secrets at its next
+// poll point, and shreds decrypted previews still on disk.
+
+use tauri::command;
+
+use crate::commands::cancel::cancel_all_operations;
+use crate::commands::preview::cleanup_preview_files;
+
+/// Cancel all running operations and shred all decrypted previews
+///
+/// Operations that were cancelled return `CryptoError::Cancelled` and leave
+/// no output behind.
+///
+/// # Returns
+/// Number of secrets wiped: operations cancelled plus previews shredded
+#[command]
+pub async fn wipe_secrets() -> usize {
+    let cancelled = cancel_all_operations();
+    let previews = cleanup_preview_files();
+    log::info!(
+        "Wiped secrets: cancelled {} operation(s), shredded {} preview(s)",
+        cancelled,
+        previews
+    );
+    cancelled + previews
+}
@@ -0,0 +1,580 @@
+// crypto/bundle.rs - Multi-File Encrypted Bundles
+//
+// A bundle packs a flat set of files into one encrypted output without TAR or
+// compression. All parts share one key (one salt, one Argon2id derivation):
+//
+// File layout:
+// [MAGIC:4 "FCBN"][VERSION:1][INDEX_LEN:8][index stream][file stream 1]...[file stream N]
+//
+// Each stream is a regular Version 4/6 streaming-encrypted blob (see
+// streaming.rs) with its own random base nonce. The index stream decrypts to:
+//
+// [COUNT:4] then per file:
+// [NAME_LEN:2][NAME:N][SIZE:8][OFFSET:8][LENGTH:8][HASH:32]
+//
+// OFFSET is relative to the first byte after the index stream, LENGTH is the
+// encrypted stream length, and HASH is the BLAKE3 hash of the encrypted stream.
+// The index is authenticated by AES-GCM, and the hash ties each file stream to
+// its index entry, so streams cannot be swapped or reordered undetected.
+//
+// The index length does not depend on the offsets, so a placeholder index is
+// written first and rewritten in place once every file has been encrypted.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::crypto::kdf::KdfParams;
+use crate::crypto::secure::Password;
+use crate::crypto::streaming::{
+    decrypt_with_key, derive_decryption_key, derive_encryption_key, encrypt_with_key,
+    read_stream_header, DerivedKey, DEFAULT_CHUNK_SIZE,
+};
+use crate::error::{CryptoError, CryptoResult};
+use crate::security::{create_secure_tempfile, create_shredding_tempfile, persist_tempfile};
+use zeroize::Zeroizing;
+
+/// Magic bytes identifying a bundle file
+const BUNDLE_MAGIC: [u8; 4] = *b"FCBN";
+
+/// Current bundle format version
+const BUNDLE_VERSION: u8 = 1;
+
+/// Length of the unencrypted prefix (magic + version + index stream length)
+const BUNDLE_PREFIX_LEN: u64 = BUNDLE_MAGIC.len() as u64 + 1 + 8;
+
+/// Maximum number of files in one bundle (matches the batch limit)
+const MAX_BUNDLE_FILES: usize = 1000;
+
+/// Size of a BLAKE3 hash in bytes
+const HASH_SIZE: usize = 32;
+
+/// Index entry describing one file stream in the bundle
+struct BundleEntry {
+    name: String,
+    size: u64,
+    offset: u64,
+    length: u64,
+    hash: [u8; HASH_SIZE],
+}
+
+/// Encrypt several files into one bundle under a single key
+///
+/// Only the file names (not directories) are recorded, so names must be
+/// unique across `inputs`.
+///
+/// # Arguments
+/// * `inputs` - Files to include, in index order
+/// * `output` - Path for the bundle file
+/// * `password` - Password for key derivation
+/// * `key_file_path` - Optional key file for two-factor encryption
+///
+/// # Returns
+/// Ok(()) on success, or CryptoError on failure
+pub fn encrypt_files_bundle<P: AsRef<Path>, Q: AsRef<Path>>(
+    inputs: &[P],
+    output: Q,
+    password: &Password,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<()> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+    if inputs.is_empty() {
+        return Err(CryptoError::FormatError(
+            "No files provided for bundle".to_string(),
+        ));
+    }
+    if inputs.len() > MAX_BUNDLE_FILES {
+        return Err(CryptoError::TooManyFiles(format!(
+            "Bundle supports at most {} files",
+            MAX_BUNDLE_FILES
+        )));
+    }
+
+    let mut entries = Vec::with_capacity(inputs.len());
+    let mut names = HashSet::new();
+    for input in inputs {
+        let input = input.as_ref();
+        let name = input
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| CryptoError::InvalidPath("Invalid input file name".to_string()))?
+            .to_string();
+        if !names.insert(name.clone()) {
+            return Err(CryptoError::FormatError(format!(
+                "Duplicate file name in bundle: {}",
+                name
+            )));
+        }
+        entries.push(BundleEntry {
+            name,
+            size: fs::metadata(input)?.len(),
+            offset: 0,
+            length: 0,
+            hash: [0u8; HASH_SIZE],
+        });
+    }
+
+    let output = output.as_ref();
+    let output_parent = output.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_secure_tempfile(output_parent)?;
+    let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+    let key = derive_encryption_key(password, key_file_path, KdfParams::default())?;
+
+    // Placeholder prefix and index: same length as the final ones, rewritten below
+    write_prefix(&mut writer, 0)?;
+    write_index(&mut writer, &entries, &key)?;
+    let data_start = writer.stream_position()?;
+
+    for (input, entry) in inputs.iter().zip(entries.iter_mut()) {
+        let mut reader = BufReader::new(File::open(input.as_ref())?);
+        let start = writer.stream_position()?;
+
+        let mut hashing_writer = HashingWriter::new(&mut writer);
+        encrypt_with_key(
+            &mut reader,
+            entry.size,
+            &mut hashing_writer,
+            &key,
+            DEFAULT_CHUNK_SIZE,
+            None,
+            false,
+            None,
+        )?;
+        entry.hash = hashing_writer.finalize();
+
+        entry.offset = start - data_start;
+        entry.length = writer.stream_position()? - start;
+    }
+
+    writer.seek(SeekFrom::Start(0))?;
+    write_prefix(&mut writer, data_start - BUNDLE_PREFIX_LEN)?;
+    write_index(&mut writer, &entries, &key)?;
+    if writer.stream_position()? != data_start {
+        return Err(CryptoError::EncryptionFailed);
+    }
+
+    writer.flush()?;
+    drop(writer);
+
+    if let Err(err) = persist_tempfile(temp_file, output) {
+        let _ = fs::remove_file(err.file.path());
+        return Err(CryptoError::Io(err.error));
+    }
+
+    Ok(())
+}
+
+/// Decrypt a bundle and write each file into `output_dir`
+///
+/// Existing files with the same name are replaced.
+///
+/// # Arguments
+/// * `input` - Path to the bundle file
+/// * `output_dir` - Existing directory where files will be written
+/// * `password` - Password used when the bundle was created
+/// * `key_file_path` - Key file, if the bundle was created with one
+///
+/// # Returns
+/// Paths of the extracted files in index order, or CryptoError on failure
+pub fn extract_bundle<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output_dir: Q,
+    password: &Password,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<Vec<PathBuf>> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+
+    let output_dir = output_dir.as_ref();
+    if !output_dir.is_dir() {
+        return Err(CryptoError::FormatError(
+            "Output directory does not exist".to_string(),
+        ));
+    }
+
+    let input_file = File::open(input.as_ref())?;
+    let file_size = input_file.metadata()?.len();
+    let mut reader = BufReader::new(input_file);
+
+    let mut prefix = [0u8; BUNDLE_PREFIX_LEN as usize];
+    reader.read_exact(&mut prefix)?;
+    if prefix[..4] != BUNDLE_MAGIC {
+        return Err(CryptoError::FormatError("Not a bundle file".to_string()));
+    }
+    if prefix[4] != BUNDLE_VERSION {
+        return Err(CryptoError::FormatError(format!(
+            "Unsupported bundle version: {}",
+            prefix[4]
+        )));
+    }
+    let index_len = u64::from_le_bytes(prefix[5..].try_into().unwrap());
+    let data_start = BUNDLE_PREFIX_LEN
+        .checked_add(index_len)
+        .filter(|&start| start <= file_size)
+        .ok_or_else(|| CryptoError::FormatError("Bundle index length is invalid".to_string()))?;
+    let mut index_reader = (&mut reader).take(index_len);
+
+    // Decrypt the index; the key derived here is shared by every file stream
+    let index_header = read_stream_header(&mut index_reader)?;
+    if index_header.key_file_required() && key_file_path.is_none() {
+        return Err(CryptoError::KeyFileRequired);
+    }
+    let key = derive_decryption_key(&index_header, password, key_file_path)?;
+
+    let mut index = Zeroizing::new(Vec::new());
+    decrypt_with_key(
+        &mut index_reader,
+        index_len,
+        &mut *index,
+        &index_header,
+        None,
+        &key,
+        None,
+        false,
+    )?;
+    let entries = parse_index(&index)?;
+
+    let data_len = file_size - data_start;
+
+    let mut extracted = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let in_bounds = entry
+            .offset
+            .checked_add(entry.length)
+            .is_some_and(|end| end <= data_len);
+        if !in_bounds {
+            return Err(CryptoError::FormatError(format!(
+                "Bundle entry '{}' lies outside the file",
+                entry.name
+            )));
+        }
+
+        reader.seek(SeekFrom::Start(data_start + entry.offset))?;
+        let mut segment = HashingReader::new((&mut reader).take(entry.length));
+
+        let header = read_stream_header(&mut segment)?;
+        if header.salt() != index_header.salt() {
+            return Err(entry_mismatch(&entry.name));
+        }
+
+        let output_path = output_dir.join(&entry.name);
+        let mut temp_file = create_shredding_tempfile(output_dir)?;
+        let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+        decrypt_with_key(
+            &mut segment,
+            entry.length,
+            &mut writer,
+            &header,
+            None,
+            &key,
+            None,
+            false,
+        )?;
+
+        // The whole stream must be consumed and match the authenticated hash
+        if segment.inner.limit() != 0 || segment.finalize() != entry.hash {
+            return Err(entry_mismatch(&entry.name));
+        }
+
+        writer.flush()?;
+        drop(writer);
+
+        temp_file.persist(&output_path)?;
+        extracted.push(output_path);
+    }
+
+    Ok(extracted)
+}
+
+/// Write the unencrypted bundle prefix
+fn write_prefix<W: Write>(writer: &mut W, index_len: u64) -> CryptoResult<()> {
+    writer.write_all(&BUNDLE_MAGIC)?;
+    writer.write_all(&[BUNDLE_VERSION])?;
+    writer.write_all(&index_len.to_le_bytes())?;
+    Ok(())
+}
+
+/// Serialize the index and write it as one encrypted stream
+fn write_index<W: Write>(
+    writer: &mut W,
+    entries: &[BundleEntry],
+    key: &DerivedKey,
+) -> CryptoResult<()> {
+    let mut buf = Zeroizing::new(Vec::new());
+    buf.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        let name = entry.name.as_bytes();
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| CryptoError::InvalidPath(format!("File name too long: {}", entry.name)))?;
+        buf.extend_from_slice(&name_len.to_le_bytes());
+        buf.extend_from_slice(name);
+        buf.extend_from_slice(&entry.size.to_le_bytes());
+        buf.extend_from_slice(&entry.offset.to_le_bytes());
+        buf.extend_from_slice(&entry.length.to_le_bytes());
+        buf.extend_from_slice(&entry.hash);
+    }
+
+    let mut plaintext = buf.as_slice();
+    encrypt_with_key(
+        &mut plaintext,
+        buf.len() as u64,
+        writer,
+        key,
+        DEFAULT_CHUNK_SIZE,
+        None,
+        false,
+        None,
+    )
+}
+
+/// Parse a decrypted index, rejecting names that could escape the output directory
+fn parse_index(mut data: &[u8]) -> CryptoResult<Vec<BundleEntry>> {
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> CryptoResult<&'a [u8]> {
+        if data.len() < len {
+            return Err(CryptoError::FormatError(
+                "Bundle index is truncated".to_string(),
+            ));
+        }
+        let (head, tail) = data.split_at(len);
+        *data = tail;
+        Ok(head)
+    }
+    fn take_u64(data: &mut &[u8]) -> CryptoResult<u64> {
+        Ok(u64::from_le_bytes(take(data, 8)?.try_into().unwrap()))
+    }
+
+    let count = u32::from_le_bytes(take(&mut data, 4)?.try_into().unwrap()) as usize;
+    if count > MAX_BUNDLE_FILES {
+        return Err(CryptoError::FormatError(format!(
+            "Bundle index lists {} files (max {})",
+            count, MAX_BUNDLE_FILES
+        )));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    let mut names = HashSet::new();
+    for _ in 0..count {
+        let name_len = u16::from_le_bytes(take(&mut data, 2)?.try_into().unwrap()) as usize;
+        let name = std::str::from_utf8(take(&mut data, name_len)?)
+            .map_err(|_| CryptoError::FormatError("Bundle file name is not UTF-8".to_string()))?
+            .to_string();
+
+        // Names must be a single plain path component
+        if Path::new(&name).file_name().and_then(|n| n.to_str()) != Some(name.as_str())
+            || name.contains(['/', '\\'])
+        {
+            return Err(CryptoError::PathTraversal(format!(
+                "Invalid file name in bundle: {}",
+                name
+            )));
+        }
+        if !names.insert(name.clone()) {
+            return Err(CryptoError::FormatError(format!(
+                "Duplicate file name in bundle: {}",
+                name
+            )));
+        }
+
+        entries.push(BundleEntry {
+            name,
+            size: take_u64(&mut data)?,
+            offset: take_u64(&mut data)?,
+            length: take_u64(&mut data)?,
+            hash: take(&mut data, HASH_SIZE)?.try_into().unwrap(),
+        });
+    }
+
+    if !data.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Bundle index has trailing data".to_string(),
+        ));
+    }
+
+    Ok(entries)
+}
+
+fn entry_mismatch(name: &str) -> CryptoError {
+    CryptoError::FormatError(format!("Bundle entry '{}' does not match its index", name))
+}
+
+/// Writer adapter that hashes everything written through it
+struct HashingWriter<W> {
+    inner: W,
+    hasher: blake3::Hasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    fn finalize(&self) -> [u8; HASH_SIZE] {
+        *self.hasher.finalize().as_bytes()
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader adapter that hashes everything read through it
+struct HashingReader<R> {
+    inner: R,
+    hasher: blake3::Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    fn finalize(&self) -> [u8; HASH_SIZE] {
+        *self.hasher.finalize().as_bytes()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    fn test_password() -> Password {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let now_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        Password::new(format!("{now_nanos:x}{counter:x}"))
+    }
+
+    fn write_inputs(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        [
+            ("empty.txt", Vec::new()),
+            ("notes.md", b"# Notes\n\nbundle me\n".to_vec()),
+            (
+                "large.bin",
+                (0..DEFAULT_CHUNK_SIZE + 4321)
+                    .map(|i| (i % 251) as u8)
+                    .collect(),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, content)| {
+            let path = dir.join(name);
+            fs::write(&path, &content).unwrap();
+            (path, content)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_bundle_roundtrip_three_files() {
+        let temp = tempdir().unwrap();
+        let input_dir = temp.path().join("input");
+        let extract_dir = temp.path().join("extract");
+        fs::create_dir_all(&input_dir).unwrap();
+        fs::create_dir_all(&extract_dir).unwrap();
+
+        let inputs = write_inputs(&input_dir);
+        let paths: Vec<&Path> = inputs.iter().map(|(p, _)| p.as_path()).collect();
+        let bundle_path = temp.path().join("files.bundle");
+        let password = test_password();
+
+        encrypt_files_bundle(&paths, &bundle_path, &password, None).unwrap();
+        assert_eq!(&fs::read(&bundle_path).unwrap()[..4], &BUNDLE_MAGIC);
+
+        let extracted = extract_bundle(&bundle_path, &extract_dir, &password, None).unwrap();
+        assert_eq!(extracted.len(), 3);
+        for ((input, content), output) in inputs.iter().zip(&extracted) {
+            assert_eq!(output.file_name(), input.file_name());
+            assert_eq!(&fs::read(output).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn test_bundle_wrong_password() {
+        let temp = tempdir().unwrap();
+        let inputs = write_inputs(temp.path());
+        let paths: Vec<&Path> = inputs.iter().map(|(p, _)| p.as_path()).collect();
+        let bundle_path = temp.path().join("files.bundle");
+
+        encrypt_files_bundle(&paths, &bundle_path, &test_password(), None).unwrap();
+
+        let extract_dir = tempdir().unwrap();
+        let result = extract_bundle(&bundle_path, extract_dir.path(), &test_password(), None);
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_bundle_detects_tampered_file_stream() {
+        let temp = tempdir().unwrap();
+        let inputs = write_inputs(temp.path());
+        let paths: Vec<&Path> = inputs.iter().map(|(p, _)| p.as_path()).collect();
+        let bundle_path = temp.path().join("files.bundle");
+        let password = test_password();
+
+        encrypt_files_bundle(&paths, &bundle_path, &password, None).unwrap();
+        let mut data = fs::read(&bundle_path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        fs::write(&bundle_path, &data).unwrap();
+
+        let extract_dir = tempdir().unwrap();
+        let result = extract_bundle(&bundle_path, extract_dir.path(), &password, None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_bundle_rejects_duplicate_names() {
+        let temp = tempdir().unwrap();
+        let a = temp.path().join("a");
+        let b = temp.path().join("b");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(a.join("same.txt"), b"1").unwrap();
+        fs::write(b.join("same.txt"), b"2").unwrap();
+
+        let result = encrypt_files_bundle(
+            &[a.join("same.txt"), b.join("same.txt")],
+            temp.path().join("files.bundle"),
+            &test_password(),
+            None,
+        );
+        assert!(matches!(result, Err(CryptoError::FormatError(_))));
+    }
+}
@@ -0,0 +1,188 @@
+// crypto/cancel.rs - Cooperative Cancellation
+//
+// Long operations are cancelled through a shared flag that is polled at three
+// kinds of points, so a cancel takes effect promptly wherever the work is:
+// - Key derivation runs on a helper thread; the caller polls the flag while
+//   waiting and abandons the thread on cancel (its result is dropped and
+//   zeroized when it eventually finishes)
+// - Input reads are split into bounded slices, each preceded by a flag check
+// - The flag is checked again before the output is persisted, so a cancelled
+//   operation never leaves an output file behind
+
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{CryptoError, CryptoResult};
+
+/// Largest single read passed through [`CancellableReader`] (64 KB)
+const MAX_READ_SLICE: usize = 64 * 1024;
+
+/// How often a waiting caller re-checks the flag during background work
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Shared cancellation flag for one operation
+///
+/// Clones share the same flag, so a clone handed to another thread (or kept
+/// in a registry by the command layer) can cancel the original.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether cancellation has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Return `Cancelled` if cancellation has been requested
+    pub fn check(&self) -> CryptoResult<()> {
+        if self.is_cancelled() {
+            Err(CryptoError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Replace `err` with `Cancelled` if it was caused by a cancel
+    ///
+    /// Cancelled reads surface as I/O errors deep inside the stream code; this
+    /// turns them back into the error the caller expects.
+    pub(crate) fn map_error(&self, err: CryptoError) -> CryptoError {
+        if self.is_cancelled() {
+            CryptoError::Cancelled
+        } else {
+            err
+        }
+    }
+
+    /// Whether `other` shares this token's flag
+    pub(crate) fn ptr_eq(&self, other: &CancellationToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Run `task` on a helper thread, returning early if `cancel` fires
+///
+/// The flag is checked before the task starts and again after it finishes.
+/// On cancel the thread is abandoned rather than joined, since Argon2 cannot
+/// be interrupted mid-derivation.
+pub(crate) fn run_cancellable<T, F>(cancel: &CancellationToken, task: F) -> CryptoResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> CryptoResult<T> + Send + 'static,
+{
+    cancel.check()?;
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(task());
+    });
+
+    loop {
+        match receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+            Ok(result) => {
+                cancel.check()?;
+                return result;
+            }
+            Err(RecvTimeoutError::Timeout) => cancel.check()?,
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(CryptoError::Io(std::io::Error::other(
+                    "Background task stopped unexpectedly",
+                )))
+            }
+        }
+    }
+}
+
+/// Reader that polls a cancellation flag between bounded reads
+///
+/// Each read is capped at 64 KB, so even a single large read request checks
+/// the flag regularly. A cancelled read fails with an `ErrorKind::Other` error
+/// (not `Interrupted`, which `read_exact` would retry); use
+/// [`CancellationToken::map_error`] to report `Cancelled`.
+pub(crate) struct CancellableReader<R> {
+    inner: R,
+    cancel: CancellationToken,
+}
+
+impl<R: Read> CancellableReader<R> {
+    pub(crate) fn new(inner: R, cancel: CancellationToken) -> Self {
+        Self { inner, cancel }
+    }
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.cancel.is_cancelled() {
+            return Err(std::io::Error::other("Operation cancelled"));
+        }
+        let len = buf.len().min(MAX_READ_SLICE);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<R: Seek> Seek for CancellableReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_run_cancellable_abandons_slow_task() {
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            canceller.cancel();
+        });
+
+        let start = Instant::now();
+        let result = run_cancellable(&cancel, || {
+            std::thread::sleep(Duration::from_secs(10));
+            Ok(())
+        });
+
+        assert!(matches!(result, Err(CryptoError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_run_cancellable_returns_task_result() {
+        let cancel = CancellationToken::new();
+        assert_eq!(run_cancellable(&cancel, || Ok(42)).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_cancellable_reader_bounds_and_stops_reads() {
+        let cancel = CancellationToken::new();
+        let data = vec![7u8; MAX_READ_SLICE * 3];
+        let mut reader = CancellableReader::new(data.as_slice(), cancel.clone());
+
+        let mut buf = vec![0u8; data.len()];
+        assert_eq!(reader.read(&mut buf).unwrap(), MAX_READ_SLICE);
+
+        cancel.cancel();
+        assert!(reader.read(&mut buf).is_err());
+        assert!(matches!(
+            cancel.map_error(CryptoError::DecryptionFailed),
+            CryptoError::Cancelled
+        ));
+    }
+}
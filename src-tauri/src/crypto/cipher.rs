@@ -1,20 +1,110 @@
-// crypto/cipher.rs - AES-256-GCM Encryption and Decryption (Test Utilities)
+// crypto/cipher.rs - Chunk Cipher Selection
 //
-// This module provides standalone encrypt/decrypt functions used exclusively
-// in tests. Production encryption/decryption goes through the streaming module
-// (crypto/streaming.rs) which processes files in chunks.
+// Streaming files (crypto/streaming.rs) are encrypted with one of two AEADs:
 //
-// AES-256-GCM Properties:
+// AES-256-GCM (default, every format version):
 // - Encryption: AES in counter mode with 256-bit keys
 // - Authentication: GMAC (Galois Message Authentication Code)
-// - Nonce: 96 bits (12 bytes) - must be unique for each encryption
-// - Tag: 128 bits (16 bytes) - verifies data hasn't been tampered
+// - Fast where the CPU has AES instructions
+//
+// ChaCha20-Poly1305 (Version 9 with EXT_FLAG_CHACHA20_POLY1305):
+// - Encryption: ChaCha20 stream cipher with 256-bit keys
+// - Authentication: Poly1305
+// - Constant-time and fast in software, for machines without AES hardware
+//
+// Both use 96-bit (12-byte) nonces and 128-bit (16-byte) tags, so the chunk
+// layout, nonce derivation, and size bounds are the same for either. Files
+// without the flag decrypt as AES-256-GCM.
+//
+// The test module also holds standalone AES-256-GCM encrypt/decrypt helpers
+// used to check the primitive directly.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use chacha20poly1305::ChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CryptoError, CryptoResult};
+
+/// AEAD used for the chunks of a streaming file
+///
+/// The header records it as an extended flag rather than an id; JSON uses
+/// `"aes-256-gcm"` and `"chacha20-poly1305"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CipherAlgorithm {
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl CipherAlgorithm {
+    /// Display name, as shown by `describe_encrypted_file`
+    pub fn name(self) -> &'static str {
+        match self {
+            CipherAlgorithm::Aes256Gcm => "AES-256-GCM",
+            CipherAlgorithm::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+        }
+    }
+}
+
+/// An initialized AEAD of either [`CipherAlgorithm`]
+///
+/// The AES key schedule is boxed; it is far larger than ChaCha20-Poly1305's key.
+pub(crate) enum AeadCipher {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl AeadCipher {
+    /// Initialize `algorithm` with a 32-byte key
+    pub(crate) fn new(algorithm: CipherAlgorithm, key: &[u8]) -> CryptoResult<Self> {
+        let cipher = match algorithm {
+            CipherAlgorithm::Aes256Gcm => {
+                Aes256Gcm::new_from_slice(key).map(|cipher| Self::Aes256Gcm(Box::new(cipher)))
+            }
+            CipherAlgorithm::ChaCha20Poly1305 => {
+                ChaCha20Poly1305::new_from_slice(key).map(Self::ChaCha20Poly1305)
+            }
+        };
+        cipher.map_err(|_| CryptoError::EncryptionFailed)
+    }
+
+    /// Encrypt `payload`, returning ciphertext with the tag appended
+    pub(crate) fn encrypt(
+        &self,
+        nonce: &Nonce<aes_gcm::aead::consts::U12>,
+        payload: Payload<'_, '_>,
+    ) -> aes_gcm::aead::Result<Vec<u8>> {
+        match self {
+            Self::Aes256Gcm(cipher) => cipher.encrypt(nonce, payload),
+            Self::ChaCha20Poly1305(cipher) => cipher.encrypt(nonce, payload),
+        }
+    }
+
+    /// Authenticate and decrypt `payload` (ciphertext with the tag appended)
+    pub(crate) fn decrypt(
+        &self,
+        nonce: &Nonce<aes_gcm::aead::consts::U12>,
+        payload: Payload<'_, '_>,
+    ) -> aes_gcm::aead::Result<Vec<u8>> {
+        match self {
+            Self::Aes256Gcm(cipher) => cipher.decrypt(nonce, payload),
+            Self::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce, payload),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
     use rand::{rngs::SysRng, TryRng};
 
+    use super::{AeadCipher, CipherAlgorithm, Payload};
+
     use crate::crypto::secure::SecureBytes;
     use crate::error::{CryptoError, CryptoResult};
 
@@ -196,4 +286,35 @@ mod tests {
         let (_, ciphertext) = encrypt(&key, plaintext).unwrap();
         assert_eq!(ciphertext.len(), plaintext.len() + 16);
     }
+
+    #[test]
+    fn test_aead_cipher_algorithms_are_distinct() {
+        let key = [7u8; 32];
+        let nonce = Nonce::from_slice(&[1u8; NONCE_SIZE]);
+        let payload = || Payload {
+            msg: b"chunk",
+            aad: b"header",
+        };
+
+        let aes = AeadCipher::new(CipherAlgorithm::Aes256Gcm, &key).unwrap();
+        let chacha = AeadCipher::new(CipherAlgorithm::ChaCha20Poly1305, &key).unwrap();
+        let aes_ciphertext = aes.encrypt(nonce, payload()).unwrap();
+        let chacha_ciphertext = chacha.encrypt(nonce, payload()).unwrap();
+
+        assert_eq!(chacha_ciphertext.len(), aes_ciphertext.len());
+        assert_ne!(chacha_ciphertext, aes_ciphertext);
+        let open = |cipher: &AeadCipher, msg: &[u8]| {
+            cipher.decrypt(
+                nonce,
+                Payload {
+                    msg,
+                    aad: b"header",
+                },
+            )
+        };
+        assert_eq!(open(&chacha, &chacha_ciphertext).unwrap(), b"chunk");
+        assert!(open(&aes, &chacha_ciphertext).is_err());
+        assert!(open(&chacha, &aes_ciphertext).is_err());
+        assert!(AeadCipher::new(CipherAlgorithm::ChaCha20Poly1305, &key[..16]).is_err());
+    }
 }
@@ -23,8 +23,33 @@
 //   VeraCrypt, 7-Zip, and similar tools.
 // - No compression oracle risk: FileCrypter has no adaptive chosen-plaintext path
 // - AES-GCM authentication prevents tampering with compressed data
-
+//
+// ## Dictionaries
+//
+// Chunks are compressed independently, so a file smaller than one chunk gives
+// ZSTD little history to work with. A dictionary trained on similar files
+// (logs, JSON records) supplies that history up front. Dictionaries are built
+// from user data, so they are kept zeroized and stored encrypted in the file.
+//
+// ## Stored Chunks
+//
+// If ZSTD fails on a chunk (e.g. an allocation failure at a high level), the
+// chunk is written as a ZSTD frame of raw (uncompressed) blocks instead of
+// aborting the file. Any ZSTD decoder reads such a frame, so no per-chunk
+// indicator is needed and the file format is unchanged.
+//
+// ## Long-Distance Matching
+//
+// `CompressionConfig::with_long_distance` enables ZSTD's long-distance
+// matching (`zstd --long`) with a larger window, which pays off for large
+// chunks with repetition spread far apart (VM images, backups). Nothing is
+// stored in the header: the window is recorded in each ZSTD frame, and the
+// window log is capped at the decoder's default limit so every reader
+// accepts it.
+
+use std::fmt;
 use std::io::{BufReader, Cursor, Read};
+use std::sync::Arc;
 
 use zeroize::Zeroizing;
 
@@ -42,14 +67,50 @@ pub enum CompressionAlgorithm {
 
 impl CompressionAlgorithm {
     /// Convert from u8 byte (from file header)
+    ///
+    /// Algorithms whose cargo feature is disabled are rejected with a
+    /// "not supported in this build" error rather than reported as unknown.
     pub fn from_u8(value: u8) -> CryptoResult<Self> {
-        match value {
-            0x00 => Ok(CompressionAlgorithm::None),
-            0x01 => Ok(CompressionAlgorithm::Zstd),
-            _ => Err(CryptoError::FormatError(format!(
-                "Unknown compression algorithm: 0x{:02x}",
-                value
-            ))),
+        let algorithm = match value {
+            0x00 => CompressionAlgorithm::None,
+            0x01 => CompressionAlgorithm::Zstd,
+            _ => {
+                return Err(CryptoError::FormatError(format!(
+                    "Unknown compression algorithm: 0x{:02x}",
+                    value
+                )))
+            }
+        };
+        algorithm.check_supported()?;
+        Ok(algorithm)
+    }
+
+    /// Whether this build accepts the algorithm on decryption
+    ///
+    /// ZSTD requires the `compression-zstd` feature (on by default).
+    pub fn is_supported(self) -> bool {
+        match self {
+            CompressionAlgorithm::None => true,
+            CompressionAlgorithm::Zstd => cfg!(feature = "compression-zstd"),
+        }
+    }
+
+    /// Fail with a clear error if the algorithm's feature is disabled
+    fn check_supported(self) -> CryptoResult<()> {
+        if self.is_supported() {
+            return Ok(());
+        }
+        Err(CryptoError::FormatError(format!(
+            "Compression algorithm {} not supported in this build",
+            self.name()
+        )))
+    }
+
+    /// Display name for error messages and file reports
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CompressionAlgorithm::None => "none",
+            CompressionAlgorithm::Zstd => "ZSTD",
         }
     }
 
@@ -62,6 +123,91 @@ impl CompressionAlgorithm {
 /// Default ZSTD compression level (balanced speed/ratio)
 pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
 
+/// Lowest ZSTD level accepted when a level is chosen explicitly
+pub const MIN_ZSTD_LEVEL: i32 = 1;
+
+/// Highest standard ZSTD level (levels above 19 are "ultra" and memory hungry)
+pub const MAX_ZSTD_LEVEL: i32 = 22;
+
+/// Smallest ZSTD window log (1 KB window)
+pub const MIN_WINDOW_LOG: u32 = 10;
+
+/// Largest window log for long-distance matching (128 MB window), which is
+/// both the `zstd --long` default and the decoder's default window limit
+pub const MAX_WINDOW_LOG: u32 = 27;
+
+/// Bytes of input examined by [`CompressionConfig::auto`] (64 KB)
+pub const AUTO_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Probe output at or above this fraction of the sample means "incompressible"
+const AUTO_INCOMPRESSIBLE_RATIO: f64 = 0.9;
+
+/// Probe output at or below this fraction of the sample means "highly compressible"
+const AUTO_FAST_RATIO: f64 = 0.25;
+
+/// Largest dictionary accepted when supplied or read from a file header (1 MB)
+pub const MAX_DICTIONARY_SIZE: usize = 1024 * 1024;
+
+/// Default size for trained dictionaries (ZSTD's own default, ~110 KB)
+pub const DEFAULT_DICTIONARY_SIZE: usize = 110 * 1024;
+
+/// A ZSTD dictionary shared by every chunk of a file
+#[derive(Clone)]
+pub struct CompressionDictionary {
+    id: u32,
+    bytes: Zeroizing<Vec<u8>>,
+}
+
+impl CompressionDictionary {
+    /// Use existing dictionary bytes
+    ///
+    /// Accepts both trained ZSTD dictionaries and raw content (any bytes,
+    /// used as shared history). Raw content dictionaries have id 0.
+    pub fn from_bytes(bytes: Vec<u8>) -> CryptoResult<Self> {
+        let bytes = Zeroizing::new(bytes);
+        if bytes.is_empty() || bytes.len() > MAX_DICTIONARY_SIZE {
+            return Err(CryptoError::FormatError(format!(
+                "Invalid compression dictionary size: {} bytes (must be 1-{})",
+                bytes.len(),
+                MAX_DICTIONARY_SIZE
+            )));
+        }
+        let id = zstd_safe::get_dict_id_from_dict(&bytes).map_or(0, |id| id.get());
+        Ok(Self { id, bytes })
+    }
+
+    /// Train a dictionary of at most `max_size` bytes from sample files
+    ///
+    /// ZSTD needs a reasonable number of samples (dozens or more) to find
+    /// shared content; training fails if there is too little to learn from.
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> CryptoResult<Self> {
+        let max_size = max_size.min(MAX_DICTIONARY_SIZE);
+        let trained = zstd::dict::from_samples(samples, max_size)
+            .map_err(|e| CryptoError::FormatError(format!("Dictionary training failed: {}", e)))?;
+        Self::from_bytes(trained)
+    }
+
+    /// Dictionary id embedded by the trainer (0 for raw content)
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Raw dictionary bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl fmt::Debug for CompressionDictionary {
+    // Contents are derived from user data; never print them
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressionDictionary")
+            .field("id", &self.id)
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}
+
 /// Configuration for compression operations
 #[derive(Debug, Clone)]
 pub struct CompressionConfig {
@@ -69,6 +215,12 @@ pub struct CompressionConfig {
     pub algorithm: CompressionAlgorithm,
     /// Compression level (0-22 for ZSTD, 3 is recommended)
     pub level: i32,
+    /// Optional ZSTD dictionary (ignored when compression is disabled)
+    pub dictionary: Option<Arc<CompressionDictionary>>,
+    /// Enable ZSTD long-distance matching
+    pub long_distance: bool,
+    /// ZSTD window log (None for the level's default window)
+    pub window_log: Option<u32>,
 }
 
 impl Default for CompressionConfig {
@@ -76,6 +228,9 @@ impl Default for CompressionConfig {
         Self {
             algorithm: CompressionAlgorithm::Zstd,
             level: DEFAULT_COMPRESSION_LEVEL,
+            dictionary: None,
+            long_distance: false,
+            window_log: None,
         }
     }
 }
@@ -86,6 +241,9 @@ impl CompressionConfig {
         Self {
             algorithm: CompressionAlgorithm::Zstd,
             level,
+            dictionary: None,
+            long_distance: false,
+            window_log: None,
         }
     }
 
@@ -94,6 +252,58 @@ impl CompressionConfig {
         Self {
             algorithm: CompressionAlgorithm::None,
             level: 0,
+            dictionary: None,
+            long_distance: false,
+            window_log: None,
+        }
+    }
+
+    /// Compress with `dictionary` (ZSTD only)
+    pub fn with_dictionary(mut self, dictionary: CompressionDictionary) -> Self {
+        self.dictionary = Some(Arc::new(dictionary));
+        self
+    }
+
+    /// Enable long-distance matching with a `2^window_log` byte window
+    /// (ZSTD only), clamped to `MIN_WINDOW_LOG..=MAX_WINDOW_LOG`
+    ///
+    /// Chunks are compressed independently, so the window never usefully
+    /// exceeds the chunk size; ZSTD shrinks it to fit each chunk.
+    pub fn with_long_distance(mut self, window_log: u32) -> Self {
+        self.long_distance = true;
+        self.window_log = Some(window_log.clamp(MIN_WINDOW_LOG, MAX_WINDOW_LOG));
+        self
+    }
+
+    /// Pick a config from a sample of the input (e.g. its first chunk)
+    ///
+    /// The first [`AUTO_SAMPLE_SIZE`] bytes are compressed at the fastest ZSTD
+    /// level to estimate compressibility:
+    /// - Barely shrinks (already compressed or random data): no compression
+    /// - Shrinks to a quarter or less: fastest level, which already captures
+    ///   most of the gain on highly redundant input
+    /// - Otherwise: the default level
+    ///
+    /// ZSTD is the only compressor in the file format, so the fast and
+    /// balanced choices differ by level rather than algorithm.
+    pub fn auto(sample: &[u8]) -> Self {
+        let sample = &sample[..sample.len().min(AUTO_SAMPLE_SIZE)];
+        if sample.is_empty() {
+            return Self::none();
+        }
+
+        let probe_len = match compress_zstd(sample, MIN_ZSTD_LEVEL) {
+            Ok(compressed) => compressed.len(),
+            Err(_) => return Self::none(),
+        };
+        let ratio = probe_len as f64 / sample.len() as f64;
+
+        if ratio >= AUTO_INCOMPRESSIBLE_RATIO {
+            Self::none()
+        } else if ratio <= AUTO_FAST_RATIO {
+            Self::new(MIN_ZSTD_LEVEL)
+        } else {
+            Self::default()
         }
     }
 
@@ -125,20 +335,94 @@ fn decompress_zstd(data: &[u8]) -> CryptoResult<Zeroizing<Vec<u8>>> {
         .map_err(|e| CryptoError::FormatError(format!("Decompression failed: {}", e)))
 }
 
+/// Compress data using ZSTD with a dictionary
+pub fn compress_zstd_with_dictionary(
+    data: &[u8],
+    level: i32,
+    dictionary: &CompressionDictionary,
+) -> CryptoResult<Zeroizing<Vec<u8>>> {
+    zstd::bulk::Compressor::with_dictionary(level, dictionary.as_bytes())
+        .and_then(|mut compressor| compressor.compress(data))
+        .map(Zeroizing::new)
+        .map_err(|e| CryptoError::FormatError(format!("Compression failed: {}", e)))
+}
+
+/// Compress data using ZSTD with the long-distance and window settings of `config`
+pub fn compress_zstd_with_params(
+    data: &[u8],
+    config: &CompressionConfig,
+) -> CryptoResult<Zeroizing<Vec<u8>>> {
+    use zstd::zstd_safe::CParameter;
+
+    let compress = || -> std::io::Result<Vec<u8>> {
+        let mut compressor = match config.dictionary.as_deref() {
+            Some(dictionary) => {
+                zstd::bulk::Compressor::with_dictionary(config.level, dictionary.as_bytes())?
+            }
+            None => zstd::bulk::Compressor::new(config.level)?,
+        };
+        compressor.set_parameter(CParameter::EnableLongDistanceMatching(config.long_distance))?;
+        if let Some(window_log) = config.window_log {
+            compressor.set_parameter(CParameter::WindowLog(window_log))?;
+        }
+        compressor.compress(data)
+    };
+    compress()
+        .map(Zeroizing::new)
+        .map_err(|e| CryptoError::FormatError(format!("Compression failed: {}", e)))
+}
+
+/// Growth step for bounded decompression output buffers
+///
+/// Letting `Vec` double would overshoot `max_size` by up to 2x and leave the
+/// abandoned allocations holding plaintext that `Zeroizing` never sees.
+const DECOMPRESS_GROWTH_STEP: usize = 1024 * 1024;
+
+/// Make room for `additional` more bytes, growing by at most one step
+///
+/// The caller has already checked `output.len() + additional <= max_size`.
+/// Growth copies into a fresh buffer of exact capacity (never above
+/// `max_size`) and the old one is zeroized when dropped.
+fn reserve_bounded(output: &mut Zeroizing<Vec<u8>>, additional: usize, max_size: usize) {
+    if output.capacity() - output.len() >= additional {
+        return;
+    }
+    let capacity = (output.capacity() + DECOMPRESS_GROWTH_STEP)
+        .max(output.len() + additional)
+        .min(max_size);
+    let mut grown = Zeroizing::new(Vec::with_capacity(capacity));
+    grown.extend_from_slice(output);
+    *output = grown;
+}
+
 /// Decompress ZSTD-compressed data with a hard output size limit
 ///
+/// The output buffer grows in bounded steps and its capacity never exceeds
+/// `max_size`, so a hostile stream cannot force a larger allocation.
+///
 /// # Arguments
 /// * `data` - Compressed data
+/// * `dictionary` - Dictionary the data was compressed with, if any
 /// * `max_size` - Maximum allowed decompressed size in bytes
 ///
 /// # Returns
 /// Decompressed data as Vec<u8>
 pub fn decompress_zstd_with_limit(
     data: &[u8],
+    dictionary: Option<&CompressionDictionary>,
     max_size: usize,
 ) -> CryptoResult<Zeroizing<Vec<u8>>> {
-    let cursor = Cursor::new(data);
-    let mut decoder = zstd::Decoder::new(BufReader::new(cursor))
+    let cursor = BufReader::new(Cursor::new(data));
+    let decoder = match dictionary {
+        Some(dictionary) => zstd::Decoder::with_dictionary(cursor, dictionary.as_bytes()),
+        None => zstd::Decoder::with_buffer(cursor),
+    };
+    let mut decoder = decoder
+        .and_then(|mut decoder| {
+            // Accept long-distance frames without relying on the library default
+            decoder.window_log_max(MAX_WINDOW_LOG)?;
+            Ok(decoder)
+        })
         .map_err(|e| CryptoError::FormatError(format!("Failed to create decompressor: {}", e)))?;
     let mut output = Zeroizing::new(Vec::with_capacity(std::cmp::min(max_size, 64 * 1024)));
     let mut buffer = [0u8; 8192];
@@ -156,12 +440,60 @@ pub fn decompress_zstd_with_limit(
                 max_size
             )));
         }
+        reserve_bounded(&mut output, read, max_size);
         output.extend_from_slice(&buffer[..read]);
     }
 
     Ok(output)
 }
 
+/// ZSTD frame magic number (little-endian on disk)
+const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+
+/// Largest ZSTD block (128 KiB)
+const ZSTD_MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+#[cfg(test)]
+thread_local! {
+    /// Number of upcoming ZSTD compressions on this thread to fail (tests only)
+    static INJECTED_FAILURES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Make the next `count` ZSTD compressions on this thread fail (tests only)
+#[cfg(test)]
+pub(crate) fn inject_compression_failures(count: usize) {
+    INJECTED_FAILURES.with(|failures| failures.set(count));
+}
+
+/// Store `data` uncompressed as a single ZSTD frame of raw blocks
+///
+/// Used when compressing a chunk fails. The frame is single-segment with an
+/// 8-byte content size and no checksum, and never exceeds
+/// `zstd_safe::compress_bound(data.len())`, so it fits the same ciphertext
+/// bound as a compressed chunk.
+pub fn store_zstd(data: &[u8]) -> Zeroizing<Vec<u8>> {
+    let blocks = data.len().div_ceil(ZSTD_MAX_BLOCK_SIZE).max(1);
+    let mut frame = Zeroizing::new(Vec::with_capacity(4 + 1 + 8 + blocks * 3 + data.len()));
+    frame.extend_from_slice(&ZSTD_MAGIC.to_le_bytes());
+    // Frame header descriptor: 8-byte content size, single segment
+    frame.push(0b1110_0000);
+    frame.extend_from_slice(&(data.len() as u64).to_le_bytes());
+
+    let mut pieces = data.chunks(ZSTD_MAX_BLOCK_SIZE).peekable();
+    if pieces.peek().is_none() {
+        // Empty content still needs one (last, empty) block
+        frame.extend_from_slice(&[1, 0, 0]);
+    }
+    while let Some(piece) = pieces.next() {
+        let last = pieces.peek().is_none() as u32;
+        // Block header: last flag, block type 0 (raw), 21-bit size
+        let block_header = last | ((piece.len() as u32) << 3);
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(piece);
+    }
+    frame
+}
+
 /// Compress data using the specified algorithm
 ///
 /// # Arguments
@@ -171,9 +503,27 @@ pub fn decompress_zstd_with_limit(
 /// # Returns
 /// Compressed data (or original data if compression disabled)
 pub fn compress(data: &[u8], config: &CompressionConfig) -> CryptoResult<Zeroizing<Vec<u8>>> {
-    match config.algorithm {
-        CompressionAlgorithm::None => Ok(Zeroizing::new(data.to_vec())),
-        CompressionAlgorithm::Zstd => compress_zstd(data, config.level),
+    match (config.algorithm, config.dictionary.as_deref()) {
+        (CompressionAlgorithm::None, _) => Ok(Zeroizing::new(data.to_vec())),
+        #[cfg(test)]
+        (CompressionAlgorithm::Zstd, _)
+            if INJECTED_FAILURES.with(|failures| {
+                let remaining = failures.get();
+                failures.set(remaining.saturating_sub(1));
+                remaining > 0
+            }) =>
+        {
+            Err(CryptoError::FormatError(
+                "Compression failed: injected failure".to_string(),
+            ))
+        }
+        (CompressionAlgorithm::Zstd, _) if config.long_distance || config.window_log.is_some() => {
+            compress_zstd_with_params(data, config)
+        }
+        (CompressionAlgorithm::Zstd, Some(dictionary)) => {
+            compress_zstd_with_dictionary(data, config.level, dictionary)
+        }
+        (CompressionAlgorithm::Zstd, None) => compress_zstd(data, config.level),
     }
 }
 
@@ -191,6 +541,7 @@ fn decompress(data: &[u8], algorithm: CompressionAlgorithm) -> CryptoResult<Zero
 /// # Arguments
 /// * `data` - Compressed data
 /// * `algorithm` - Algorithm used for compression
+/// * `dictionary` - Dictionary used for compression, if any (ZSTD only)
 /// * `max_size` - Maximum allowed decompressed size in bytes
 ///
 /// # Returns
@@ -198,8 +549,10 @@ fn decompress(data: &[u8], algorithm: CompressionAlgorithm) -> CryptoResult<Zero
 pub fn decompress_with_limit(
     data: &[u8],
     algorithm: CompressionAlgorithm,
+    dictionary: Option<&CompressionDictionary>,
     max_size: usize,
 ) -> CryptoResult<Zeroizing<Vec<u8>>> {
+    algorithm.check_supported()?;
     match algorithm {
         CompressionAlgorithm::None => {
             if data.len() > max_size {
@@ -210,7 +563,7 @@ pub fn decompress_with_limit(
             }
             Ok(Zeroizing::new(data.to_vec()))
         }
-        CompressionAlgorithm::Zstd => decompress_zstd_with_limit(data, max_size),
+        CompressionAlgorithm::Zstd => decompress_zstd_with_limit(data, dictionary, max_size),
     }
 }
 
@@ -237,6 +590,7 @@ mod tests {
             CompressionAlgorithm::from_u8(0x00).unwrap(),
             CompressionAlgorithm::None
         );
+        #[cfg(feature = "compression-zstd")]
         assert_eq!(
             CompressionAlgorithm::from_u8(0x01).unwrap(),
             CompressionAlgorithm::Zstd
@@ -244,6 +598,42 @@ mod tests {
         assert!(CompressionAlgorithm::from_u8(0xFF).is_err());
     }
 
+    #[cfg(not(feature = "compression-zstd"))]
+    #[test]
+    fn test_zstd_rejected_without_feature() {
+        let expected = "Compression algorithm ZSTD not supported in this build";
+        match CompressionAlgorithm::from_u8(0x01) {
+            Err(CryptoError::FormatError(message)) => assert_eq!(message, expected),
+            other => panic!("expected FormatError, got {:?}", other),
+        }
+        match decompress_with_limit(b"data", CompressionAlgorithm::Zstd, None, 16) {
+            Err(CryptoError::FormatError(message)) => assert_eq!(message, expected),
+            other => panic!(
+                "expected FormatError, got {:?}",
+                other.map(|data| data.len())
+            ),
+        }
+        assert!(CompressionAlgorithm::None.is_supported());
+    }
+
+    #[test]
+    fn test_auto_picks_compressor_for_text() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(2000);
+        let config = CompressionConfig::auto(text.as_bytes());
+        assert!(config.is_enabled());
+        assert_eq!(config.algorithm, CompressionAlgorithm::Zstd);
+    }
+
+    #[test]
+    fn test_auto_skips_random_data() {
+        use rand::{rngs::SysRng, TryRng};
+
+        let mut data = vec![0u8; AUTO_SAMPLE_SIZE];
+        SysRng.try_fill_bytes(&mut data).unwrap();
+        assert!(!CompressionConfig::auto(&data).is_enabled());
+        assert!(!CompressionConfig::auto(&[]).is_enabled());
+    }
+
     #[test]
     fn test_compress_decompress_roundtrip() {
         let original = b"Hello, this is test data for compression! ".repeat(100);
@@ -323,8 +713,128 @@ mod tests {
     fn test_decompress_with_limit_rejects_oversize() {
         let original = b"0123456789".repeat(100);
         let compressed = compress_zstd(&original, 3).unwrap();
-        let result =
-            decompress_with_limit(&compressed, CompressionAlgorithm::Zstd, original.len() - 1);
+        let result = decompress_with_limit(
+            &compressed,
+            CompressionAlgorithm::Zstd,
+            None,
+            original.len() - 1,
+        );
         assert!(result.is_err());
     }
+
+    /// Small JSON-like records that share most of their structure
+    fn sample_records(count: usize) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|i| {
+                format!(
+                    "{{\"id\":{i},\"level\":\"info\",\"service\":\"filecrypter\",\
+                     \"message\":\"request {} completed\",\"duration_ms\":{}}}",
+                    i * 7919 % 1000,
+                    i % 97
+                )
+                .into_bytes()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dictionary_roundtrip_and_mismatch() {
+        let records = sample_records(1);
+        let dictionary = CompressionDictionary::from_bytes(sample_records(50).concat()).unwrap();
+        let config = CompressionConfig::new(3).with_dictionary(dictionary.clone());
+
+        let compressed = compress(&records[0], &config).unwrap();
+        assert!(compressed.len() < compress_zstd(&records[0], 3).unwrap().len());
+
+        let decompressed = decompress_with_limit(
+            &compressed,
+            CompressionAlgorithm::Zstd,
+            Some(&dictionary),
+            records[0].len(),
+        )
+        .unwrap();
+        assert_eq!(*decompressed, records[0]);
+
+        // Data compressed against a dictionary cannot be read without it
+        let result = decompress_with_limit(
+            &compressed,
+            CompressionAlgorithm::Zstd,
+            None,
+            records[0].len(),
+        );
+        assert!(result.map_or(true, |data| *data != records[0]));
+    }
+
+    #[test]
+    fn test_decompress_capacity_bounded_by_limit() {
+        // Just past a doubling boundary, where Vec growth would overshoot most
+        let data: Vec<u8> = (0..(3 * DECOMPRESS_GROWTH_STEP + 100))
+            .map(|i| (i % 7) as u8)
+            .collect();
+        let compressed = compress_zstd(&data, 3).unwrap();
+
+        for max_size in [data.len(), data.len() + 10] {
+            let decompressed = decompress_zstd_with_limit(&compressed, None, max_size).unwrap();
+            assert_eq!(*decompressed, data);
+            assert!(decompressed.capacity() <= max_size);
+            assert!(decompressed.capacity() <= data.len() + DECOMPRESS_GROWTH_STEP);
+        }
+
+        assert!(decompress_zstd_with_limit(&compressed, None, data.len() - 1).is_err());
+    }
+
+    #[test]
+    fn test_stored_frame_roundtrip() {
+        for len in [
+            0,
+            1,
+            ZSTD_MAX_BLOCK_SIZE,
+            ZSTD_MAX_BLOCK_SIZE + 1,
+            3 * ZSTD_MAX_BLOCK_SIZE + 7,
+        ] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let stored = store_zstd(&data);
+            assert!(stored.len() <= zstd_safe::compress_bound(len));
+            assert_eq!(*decompress_zstd(&stored).unwrap(), data);
+            assert_eq!(
+                *decompress_zstd_with_limit(&stored, None, len).unwrap(),
+                data
+            );
+
+            // Readable by a dictionary-configured decoder too
+            let dictionary =
+                CompressionDictionary::from_bytes(sample_records(20).concat()).unwrap();
+            let restored = decompress_zstd_with_limit(&stored, Some(&dictionary), len).unwrap();
+            assert_eq!(*restored, data);
+        }
+    }
+
+    #[test]
+    fn test_long_distance_matching_improves_distant_repeats() {
+        // 1 MB of noise repeated once: the repeat is beyond level 1's window
+        let mut block = vec![0u8; 1024 * 1024];
+        blake3::Hasher::new().finalize_xof().fill(&mut block);
+        let data = [block.as_slice(), block.as_slice()].concat();
+
+        let plain = compress(&data, &CompressionConfig::new(1)).unwrap();
+        let config = CompressionConfig::new(1).with_long_distance(MAX_WINDOW_LOG);
+        let long = compress(&data, &config).unwrap();
+        assert!(long.len() < plain.len() * 3 / 4);
+
+        let restored = decompress_with_limit(&long, CompressionAlgorithm::Zstd, None, data.len());
+        assert_eq!(restored.unwrap().as_slice(), data.as_slice());
+
+        let clamped = CompressionConfig::new(1).with_long_distance(40);
+        assert_eq!(clamped.window_log, Some(MAX_WINDOW_LOG));
+    }
+
+    #[test]
+    fn test_trained_dictionary_has_id() {
+        let dictionary = CompressionDictionary::train(&sample_records(1000), 4096).unwrap();
+        assert_ne!(dictionary.id(), 0);
+        assert!(dictionary.as_bytes().len() <= 4096);
+
+        assert!(CompressionDictionary::from_bytes(Vec::new()).is_err());
+        assert!(CompressionDictionary::from_bytes(vec![0; MAX_DICTIONARY_SIZE + 1]).is_err());
+    }
 }
@@ -0,0 +1,735 @@
+// crypto/incremental.rs - Incremental Re-Encryption
+//
+// An incremental file lets a large, frequently changing file (database, disk
+// image) be re-encrypted by rewriting only the chunks whose plaintext changed.
+// Unchanged ciphertext chunks are copied from the previous encrypted version.
+//
+// File layout:
+// [MAGIC:4 "FCIN"][VERSION:1][INDEX_LEN:8][index stream][chunk 0]...[chunk N-1]
+//
+// The index stream is a regular Version 4/6/8 streaming-encrypted blob (see
+// streaming.rs) that carries the salt and KDF parameters. It decrypts to:
+//
+// [CHUNK_SIZE:4][ORIGINAL_SIZE:8][FILE_NONCE:12][COUNT:4] then per chunk:
+// [CHUNK_NONCE:12][HASH:32]
+//
+// HASH is a BLAKE3 hash of the chunk plaintext, keyed from the file key, so it
+// cannot be checked against guessed contents without the password. It is
+// deterministic, though: equal chunks have equal hashes, so the index reveals
+// which chunks are equal within a version and across versions (as does the
+// copied ciphertext itself). Each chunk is AES-256-GCM ciphertext (plaintext +
+// 16-byte tag, so offsets follow from CHUNK_SIZE) with:
+// - Nonce: CHUNK_NONCE
+// - AAD: FILE_NONCE || index || CHUNK_NONCE || HASH
+//
+// CHUNK_NONCE is drawn at random whenever a chunk is encrypted; copied chunks
+// keep theirs. Nonces cannot be derived from the version history: two updates
+// of the same previous file (or of a restored backup) would otherwise encrypt
+// different plaintexts under one nonce. Random 96-bit nonces stay far from the
+// collision bound for the chunk counts allowed here. The AAD ties each chunk
+// to its index entry, so chunks cannot be swapped or combined with the index
+// of another version.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use rand::{rngs::SysRng, TryRng};
+use zeroize::Zeroizing;
+
+use crate::crypto::kdf::KdfParams;
+use crate::crypto::secure::Password;
+use crate::crypto::streaming::{
+    decrypt_with_key, derive_decryption_key, derive_encryption_key, encrypt_with_key,
+    normalize_chunk_size, read_stream_header, DerivedKey,
+};
+use crate::error::{CryptoError, CryptoResult};
+use crate::security::{create_secure_tempfile, create_shredding_tempfile, persist_tempfile};
+
+/// Magic bytes identifying an incremental file
+const INCREMENTAL_MAGIC: [u8; 4] = *b"FCIN";
+
+/// Current incremental format version (1 derived chunk nonces from an update
+/// counter and is no longer accepted)
+const INCREMENTAL_VERSION: u8 = 2;
+
+/// Length of the unencrypted prefix (magic + version + index stream length)
+const INCREMENTAL_PREFIX_LEN: u64 = INCREMENTAL_MAGIC.len() as u64 + 1 + 8;
+
+/// Maximum number of chunks (keeps the index under 40 MB)
+const MAX_INCREMENTAL_CHUNKS: u64 = 1_000_000;
+
+/// Size of a BLAKE3 hash in bytes
+const HASH_SIZE: usize = 32;
+
+/// AES-GCM nonce size in bytes
+const NONCE_SIZE: usize = 12;
+
+/// AES-GCM authentication tag size in bytes
+const TAG_SIZE: u64 = 16;
+
+/// Fixed part of the decrypted index before the chunk entries
+const INDEX_FIXED_LEN: usize = 4 + 8 + NONCE_SIZE + 4;
+
+/// Length of one chunk entry in the decrypted index
+const INDEX_ENTRY_LEN: usize = NONCE_SIZE + HASH_SIZE;
+
+/// BLAKE3 context for the key used to hash chunk plaintext
+const CHUNK_HASH_CONTEXT: &str = "filecrypter incremental chunk hash v1";
+
+/// Result of [`update_incremental`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalUpdate {
+    /// Indices of chunks that were encrypted again, in ascending order
+    pub rewritten_chunks: Vec<u64>,
+    /// Number of chunks whose ciphertext was copied unchanged
+    pub reused_chunks: u64,
+}
+
+/// Index entry for one chunk
+#[derive(Clone)]
+struct ChunkEntry {
+    nonce: [u8; NONCE_SIZE],
+    hash: [u8; HASH_SIZE],
+}
+
+/// Decrypted index contents
+struct IncrementalIndex {
+    chunk_size: usize,
+    original_size: u64,
+    file_nonce: [u8; NONCE_SIZE],
+    chunks: Vec<ChunkEntry>,
+}
+
+/// An opened incremental file, positioned anywhere in its chunk data
+struct OpenedIncremental {
+    reader: BufReader<File>,
+    index: IncrementalIndex,
+    data_start: u64,
+}
+
+/// Encrypt a file in the incremental format
+///
+/// # Arguments
+/// * `input_path` - Path to the plaintext file
+/// * `output_path` - Path where the incremental file will be saved
+/// * `password` - Password for key derivation
+/// * `key_file_path` - Optional key file for two-factor encryption
+/// * `chunk_size` - Size of each chunk in bytes (0 selects the 1MB default)
+///
+/// # Returns
+/// Ok(()) on success, or CryptoError on failure
+pub fn encrypt_incremental<P: AsRef<Path>, Q: AsRef<Path>>(
+    input_path: P,
+    output_path: Q,
+    password: &Password,
+    key_file_path: Option<&Path>,
+    chunk_size: usize,
+) -> CryptoResult<()> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+    let chunk_size = normalize_chunk_size(chunk_size)?;
+
+    let file_nonce = random_nonce()?;
+    let key = derive_encryption_key(password, key_file_path, KdfParams::default())?;
+    write_incremental(
+        input_path.as_ref(),
+        output_path.as_ref(),
+        &key,
+        chunk_size,
+        file_nonce,
+        None,
+    )?;
+    Ok(())
+}
+
+/// Re-encrypt a changed plaintext, rewriting only the chunks that differ
+///
+/// `previous_path` is the incremental file for an earlier version of the
+/// plaintext. Chunks whose plaintext hash matches the previous index are
+/// copied as-is; the rest are encrypted under fresh random nonces. The key, salt,
+/// and chunk size carry over, so the password and key file must be the ones
+/// the previous version was created with. Copied chunks are not decrypted
+/// here, so damage in them is reported when the result is decrypted.
+///
+/// `output_path` may be the same as `previous_path`; the new version is
+/// written to a temp file and only replaces the output once complete.
+///
+/// # Arguments
+/// * `previous_path` - Incremental file holding the previous version
+/// * `input_path` - Path to the current plaintext
+/// * `output_path` - Path where the updated incremental file will be saved
+/// * `password` - Password the previous version was encrypted with
+/// * `key_file_path` - Key file, if the previous version used one
+///
+/// # Returns
+/// Which chunks were rewritten and how many were reused
+pub fn update_incremental<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
+    previous_path: P,
+    input_path: Q,
+    output_path: R,
+    password: &Password,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<IncrementalUpdate> {
+    let (previous, key) = open_incremental(previous_path.as_ref(), password, key_file_path)?;
+    let chunk_size = previous.index.chunk_size;
+    let file_nonce = previous.index.file_nonce;
+
+    write_incremental(
+        input_path.as_ref(),
+        output_path.as_ref(),
+        &key,
+        chunk_size,
+        file_nonce,
+        Some(previous),
+    )
+}
+
+/// Decrypt an incremental file
+///
+/// # Arguments
+/// * `input_path` - Path to the incremental file
+/// * `output_path` - Path where the plaintext will be saved (replaced if it exists)
+/// * `password` - Password used when the file was created
+/// * `key_file_path` - Key file, if the file was created with one
+///
+/// # Returns
+/// Ok(()) on success, or CryptoError on failure
+pub fn decrypt_incremental<P: AsRef<Path>, Q: AsRef<Path>>(
+    input_path: P,
+    output_path: Q,
+    password: &Password,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<()> {
+    let (mut opened, key) = open_incremental(input_path.as_ref(), password, key_file_path)?;
+    let cipher = Aes256Gcm::new_from_slice(key.key().as_slice())
+        .map_err(|_| CryptoError::DecryptionFailed)?;
+    let hash_key = chunk_hash_key(&key);
+
+    let output_path = output_path.as_ref();
+    let output_parent = output_path.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_shredding_tempfile(output_parent)?;
+    let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+    opened.reader.seek(SeekFrom::Start(opened.data_start))?;
+    let index = &opened.index;
+    let mut ciphertext = Vec::with_capacity(index.chunk_size + TAG_SIZE as usize);
+    for (chunk_index, entry) in index.chunks.iter().enumerate() {
+        let chunk_index = chunk_index as u64;
+        let plaintext_len = chunk_plaintext_len(index, chunk_index);
+        ciphertext.resize(plaintext_len + TAG_SIZE as usize, 0);
+        opened.reader.read_exact(&mut ciphertext)?;
+
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(
+                    Nonce::from_slice(&entry.nonce),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: &chunk_aad(&index.file_nonce, chunk_index, entry),
+                    },
+                )
+                .map_err(|_| CryptoError::InvalidPassword)?,
+        );
+        if plaintext.len() != plaintext_len
+            || blake3::keyed_hash(&hash_key, &plaintext) != blake3::Hash::from(entry.hash)
+        {
+            return Err(CryptoError::FormatError(format!(
+                "Chunk {} does not match the index",
+                chunk_index
+            )));
+        }
+        writer.write_all(&plaintext)?;
+    }
+
+    writer.flush()?;
+    drop(writer);
+    temp_file.persist(output_path)?;
+    Ok(())
+}
+
+/// Read the prefix and index of an incremental file and derive its key
+fn open_incremental(
+    path: &Path,
+    password: &Password,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<(OpenedIncremental, DerivedKey)> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+
+    let input_file = File::open(path)?;
+    let file_size = input_file.metadata()?.len();
+    let mut reader = BufReader::new(input_file);
+
+    let mut prefix = [0u8; INCREMENTAL_PREFIX_LEN as usize];
+    reader.read_exact(&mut prefix)?;
+    if prefix[..4] != INCREMENTAL_MAGIC {
+        return Err(CryptoError::FormatError(
+            "Not an incremental file".to_string(),
+        ));
+    }
+    if prefix[4] != INCREMENTAL_VERSION {
+        return Err(CryptoError::FormatError(format!(
+            "Unsupported incremental version: {}",
+            prefix[4]
+        )));
+    }
+    let index_len = u64::from_le_bytes(prefix[5..].try_into().unwrap());
+    let data_start = INCREMENTAL_PREFIX_LEN
+        .checked_add(index_len)
+        .filter(|&start| start <= file_size)
+        .ok_or_else(|| {
+            CryptoError::FormatError("Incremental index length is invalid".to_string())
+        })?;
+    let mut index_reader = (&mut reader).take(index_len);
+
+    let header = read_stream_header(&mut index_reader)?;
+    if header.key_file_required() && key_file_path.is_none() {
+        return Err(CryptoError::KeyFileRequired);
+    }
+    let key = derive_decryption_key(&header, password, key_file_path)?;
+
+    let mut index = Zeroizing::new(Vec::new());
+    decrypt_with_key(
+        &mut index_reader,
+        index_len,
+        &mut *index,
+        &header,
+        None,
+        &key,
+        None,
+        false,
+    )?;
+    let index = parse_index(&index)?;
+
+    let expected_size = index
+        .chunks
+        .len()
+        .try_into()
+        .ok()
+        .and_then(|count: u64| count.checked_mul(TAG_SIZE))
+        .and_then(|tags| tags.checked_add(index.original_size))
+        .and_then(|data_len| data_len.checked_add(data_start));
+    if expected_size != Some(file_size) {
+        return Err(CryptoError::TruncatedFile(format!(
+            "Incremental file is {} bytes but its index describes a different length",
+            file_size
+        )));
+    }
+
+    Ok((
+        OpenedIncremental {
+            reader,
+            index,
+            data_start,
+        },
+        DerivedKey::from_header(&header, key),
+    ))
+}
+
+/// Encrypt `input_path` into a new incremental file at `output_path`
+///
+/// With `previous`, chunks whose plaintext hash matches are copied from it
+/// instead of being encrypted again.
+fn write_incremental(
+    input_path: &Path,
+    output_path: &Path,
+    key: &DerivedKey,
+    chunk_size: usize,
+    file_nonce: [u8; NONCE_SIZE],
+    mut previous: Option<OpenedIncremental>,
+) -> CryptoResult<IncrementalUpdate> {
+    let input_file = File::open(input_path)?;
+    let original_size = input_file.metadata()?.len();
+    let mut reader = BufReader::new(input_file);
+
+    let total_chunks = original_size.div_ceil(chunk_size as u64);
+    if total_chunks > MAX_INCREMENTAL_CHUNKS {
+        return Err(CryptoError::FormatError(format!(
+            "File too large for incremental encryption: {} chunks (max {})",
+            total_chunks, MAX_INCREMENTAL_CHUNKS
+        )));
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key.key().as_slice())
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    let hash_key = chunk_hash_key(key);
+    let mut index = IncrementalIndex {
+        chunk_size,
+        original_size,
+        file_nonce,
+        chunks: vec![
+            ChunkEntry {
+                nonce: [0u8; NONCE_SIZE],
+                hash: [0u8; HASH_SIZE],
+            };
+            total_chunks as usize
+        ],
+    };
+
+    let output_parent = output_path.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_secure_tempfile(output_parent)?;
+    let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+    // Placeholder prefix and index: same length as the final ones, rewritten below
+    write_prefix(&mut writer, 0)?;
+    write_index(&mut writer, &index, key)?;
+    let data_start = writer.stream_position()?;
+
+    let mut update = IncrementalUpdate {
+        rewritten_chunks: Vec::new(),
+        reused_chunks: 0,
+    };
+    let mut plaintext = Zeroizing::new(vec![0u8; chunk_size]);
+    let mut copied = Vec::new();
+    for chunk_index in 0..total_chunks {
+        let plaintext_len = chunk_plaintext_len(&index, chunk_index);
+        let plaintext = &mut plaintext[..plaintext_len];
+        reader.read_exact(plaintext)?;
+        let hash = *blake3::keyed_hash(&hash_key, plaintext).as_bytes();
+
+        // Reuse the previous ciphertext when this chunk's plaintext is unchanged
+        let reusable_nonce = previous
+            .as_ref()
+            .and_then(|previous| previous.index.chunks.get(chunk_index as usize))
+            .filter(|entry| blake3::Hash::from(entry.hash) == blake3::Hash::from(hash))
+            .map(|entry| entry.nonce);
+        if let (Some(previous_nonce), Some(previous)) = (reusable_nonce, previous.as_mut()) {
+            let offset = previous.data_start + chunk_index * (chunk_size as u64 + TAG_SIZE);
+            copied.resize(plaintext_len + TAG_SIZE as usize, 0);
+            previous.reader.seek(SeekFrom::Start(offset))?;
+            previous.reader.read_exact(&mut copied)?;
+            writer.write_all(&copied)?;
+
+            index.chunks[chunk_index as usize] = ChunkEntry {
+                nonce: previous_nonce,
+                hash,
+            };
+            update.reused_chunks += 1;
+            continue;
+        }
+
+        let entry = ChunkEntry {
+            nonce: random_nonce()?,
+            hash,
+        };
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&entry.nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &chunk_aad(&file_nonce, chunk_index, &entry),
+                },
+            )
+            .map_err(|_| CryptoError::EncryptionFailed)?;
+        writer.write_all(&ciphertext)?;
+
+        index.chunks[chunk_index as usize] = entry;
+        update.rewritten_chunks.push(chunk_index);
+    }
+
+    // Input grew after its length was read
+    if reader.read(&mut [0u8; 1])? != 0 {
+        return Err(CryptoError::FormatError(
+            "Input file changed while it was being encrypted".to_string(),
+        ));
+    }
+
+    writer.seek(SeekFrom::Start(0))?;
+    write_prefix(&mut writer, data_start - INCREMENTAL_PREFIX_LEN)?;
+    write_index(&mut writer, &index, key)?;
+    if writer.stream_position()? != data_start {
+        return Err(CryptoError::EncryptionFailed);
+    }
+
+    writer.flush()?;
+    drop(writer);
+
+    // Release the previous version first: it may be the file being replaced
+    drop(previous);
+    if let Err(err) = persist_tempfile(temp_file, output_path) {
+        let _ = fs::remove_file(err.file.path());
+        return Err(CryptoError::Io(err.error));
+    }
+
+    Ok(update)
+}
+
+/// Write the unencrypted incremental prefix
+fn write_prefix<W: Write>(writer: &mut W, index_len: u64) -> CryptoResult<()> {
+    writer.write_all(&INCREMENTAL_MAGIC)?;
+    writer.write_all(&[INCREMENTAL_VERSION])?;
+    writer.write_all(&index_len.to_le_bytes())?;
+    Ok(())
+}
+
+/// Serialize the index and write it as one encrypted stream
+fn write_index<W: Write>(
+    writer: &mut W,
+    index: &IncrementalIndex,
+    key: &DerivedKey,
+) -> CryptoResult<()> {
+    let mut buf = Zeroizing::new(Vec::with_capacity(
+        INDEX_FIXED_LEN + index.chunks.len() * INDEX_ENTRY_LEN,
+    ));
+    buf.extend_from_slice(&(index.chunk_size as u32).to_le_bytes());
+    buf.extend_from_slice(&index.original_size.to_le_bytes());
+    buf.extend_from_slice(&index.file_nonce);
+    buf.extend_from_slice(&(index.chunks.len() as u32).to_le_bytes());
+    for entry in &index.chunks {
+        buf.extend_from_slice(&entry.nonce);
+        buf.extend_from_slice(&entry.hash);
+    }
+
+    let mut plaintext = buf.as_slice();
+    encrypt_with_key(
+        &mut plaintext,
+        buf.len() as u64,
+        writer,
+        key,
+        0,
+        None,
+        false,
+        None,
+    )
+}
+
+/// Parse a decrypted index, checking it is consistent with its own sizes
+fn parse_index(data: &[u8]) -> CryptoResult<IncrementalIndex> {
+    let truncated = || CryptoError::FormatError("Incremental index is truncated".to_string());
+    if data.len() < INDEX_FIXED_LEN {
+        return Err(truncated());
+    }
+    let (fixed, entries) = data.split_at(INDEX_FIXED_LEN);
+
+    let chunk_size = u32::from_le_bytes(fixed[0..4].try_into().unwrap()) as usize;
+    let original_size = u64::from_le_bytes(fixed[4..12].try_into().unwrap());
+    let file_nonce: [u8; NONCE_SIZE] = fixed[12..24].try_into().unwrap();
+    let count = u32::from_le_bytes(fixed[24..28].try_into().unwrap()) as u64;
+
+    if chunk_size == 0 || normalize_chunk_size(chunk_size)? != chunk_size {
+        return Err(CryptoError::FormatError(format!(
+            "Invalid incremental chunk size: {}",
+            chunk_size
+        )));
+    }
+    if count > MAX_INCREMENTAL_CHUNKS || count != original_size.div_ceil(chunk_size as u64) {
+        return Err(CryptoError::FormatError(format!(
+            "Incremental index lists {} chunks for {} bytes",
+            count, original_size
+        )));
+    }
+    if entries.len() as u64 != count * INDEX_ENTRY_LEN as u64 {
+        return Err(truncated());
+    }
+
+    let chunks = entries
+        .chunks_exact(INDEX_ENTRY_LEN)
+        .map(|entry| ChunkEntry {
+            nonce: entry[..NONCE_SIZE].try_into().unwrap(),
+            hash: entry[NONCE_SIZE..].try_into().unwrap(),
+        })
+        .collect();
+
+    Ok(IncrementalIndex {
+        chunk_size,
+        original_size,
+        file_nonce,
+        chunks,
+    })
+}
+
+/// Plaintext length of chunk `chunk_index` (only the last chunk may be short)
+fn chunk_plaintext_len(index: &IncrementalIndex, chunk_index: u64) -> usize {
+    let start = chunk_index * index.chunk_size as u64;
+    (index.original_size - start).min(index.chunk_size as u64) as usize
+}
+
+/// Key for hashing chunk plaintext, derived from the file key
+fn chunk_hash_key(key: &DerivedKey) -> Zeroizing<[u8; HASH_SIZE]> {
+    Zeroizing::new(blake3::derive_key(CHUNK_HASH_CONTEXT, key.key().as_slice()))
+}
+
+/// Fresh random nonce for the file or for one encryption of a chunk
+fn random_nonce() -> CryptoResult<[u8; NONCE_SIZE]> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    SysRng
+        .try_fill_bytes(&mut nonce)
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    Ok(nonce)
+}
+
+/// Associated data binding a chunk to its position and index entry
+fn chunk_aad(file_nonce: &[u8; NONCE_SIZE], chunk_index: u64, entry: &ChunkEntry) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(NONCE_SIZE + 8 + NONCE_SIZE + HASH_SIZE);
+    aad.extend_from_slice(file_nonce);
+    aad.extend_from_slice(&chunk_index.to_le_bytes());
+    aad.extend_from_slice(&entry.nonce);
+    aad.extend_from_slice(&entry.hash);
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    const CHUNK: usize = 1024;
+
+    fn test_password() -> Password {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let now_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        Password::new(format!("{now_nanos:x}{counter:x}"))
+    }
+
+    /// Ciphertext bytes of one chunk in an incremental file
+    fn chunk_bytes(file: &[u8], chunk_index: usize) -> &[u8] {
+        let index_len = u64::from_le_bytes(file[5..13].try_into().unwrap()) as usize;
+        let start =
+            INCREMENTAL_PREFIX_LEN as usize + index_len + chunk_index * (CHUNK + TAG_SIZE as usize);
+        &file[start..(start + CHUNK + TAG_SIZE as usize).min(file.len())]
+    }
+
+    #[test]
+    fn test_incremental_roundtrip() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("input.bin");
+        let encrypted = temp.path().join("input.fcin");
+        let decrypted = temp.path().join("decrypted.bin");
+        let content: Vec<u8> = (0..CHUNK * 3 + 100).map(|i| (i % 251) as u8).collect();
+        fs::write(&input, &content).unwrap();
+
+        let password = test_password();
+        encrypt_incremental(&input, &encrypted, &password, None, CHUNK).unwrap();
+        assert_eq!(&fs::read(&encrypted).unwrap()[..4], &INCREMENTAL_MAGIC);
+
+        decrypt_incremental(&encrypted, &decrypted, &password, None).unwrap();
+        assert_eq!(fs::read(&decrypted).unwrap(), content);
+
+        let result = decrypt_incremental(&encrypted, &decrypted, &test_password(), None);
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_update_rewrites_only_changed_chunk() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("disk.img");
+        let first = temp.path().join("v1.fcin");
+        let second = temp.path().join("v2.fcin");
+        let decrypted = temp.path().join("decrypted.img");
+        let mut content: Vec<u8> = (0..CHUNK * 4).map(|i| (i % 251) as u8).collect();
+        fs::write(&input, &content).unwrap();
+
+        let password = test_password();
+        encrypt_incremental(&input, &first, &password, None, CHUNK).unwrap();
+
+        // Change one byte in chunk 2
+        content[CHUNK * 2 + 10] ^= 0xFF;
+        fs::write(&input, &content).unwrap();
+        let update = update_incremental(&first, &input, &second, &password, None).unwrap();
+        assert_eq!(update.rewritten_chunks, vec![2]);
+        assert_eq!(update.reused_chunks, 3);
+
+        let (old, new) = (fs::read(&first).unwrap(), fs::read(&second).unwrap());
+        for chunk_index in [0, 1, 3] {
+            assert_eq!(
+                chunk_bytes(&old, chunk_index),
+                chunk_bytes(&new, chunk_index)
+            );
+        }
+        assert_ne!(chunk_bytes(&old, 2), chunk_bytes(&new, 2));
+
+        decrypt_incremental(&second, &decrypted, &password, None).unwrap();
+        assert_eq!(fs::read(&decrypted).unwrap(), content);
+
+        // Updating in place works too
+        content.truncate(CHUNK + 7);
+        fs::write(&input, &content).unwrap();
+        let update = update_incremental(&second, &input, &second, &password, None).unwrap();
+        assert_eq!(update.rewritten_chunks, vec![1]);
+        decrypt_incremental(&second, &decrypted, &password, None).unwrap();
+        assert_eq!(fs::read(&decrypted).unwrap(), content);
+    }
+
+    #[test]
+    fn test_two_updates_of_one_version_use_different_nonces() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("disk.img");
+        let first = temp.path().join("v1.fcin");
+        let (branch_a, branch_b) = (temp.path().join("v2a.fcin"), temp.path().join("v2b.fcin"));
+        let mut content: Vec<u8> = (0..CHUNK * 3).map(|i| (i % 251) as u8).collect();
+        fs::write(&input, &content).unwrap();
+
+        let password = test_password();
+        encrypt_incremental(&input, &first, &password, None, CHUNK).unwrap();
+
+        // Two different edits of the same chunk, both based on v1
+        for (byte, output) in [(0x11, &branch_a), (0x22, &branch_b)] {
+            content[CHUNK + 5] = byte;
+            fs::write(&input, &content).unwrap();
+            let update = update_incremental(&first, &input, output, &password, None).unwrap();
+            assert_eq!(update.rewritten_chunks, vec![1]);
+        }
+
+        let nonces = |path: &Path| {
+            let (opened, _) = open_incremental(path, &password, None).unwrap();
+            opened
+                .index
+                .chunks
+                .iter()
+                .map(|entry| entry.nonce)
+                .collect::<Vec<_>>()
+        };
+        let (original, a, b) = (nonces(&first), nonces(&branch_a), nonces(&branch_b));
+        assert_ne!(a[1], b[1]);
+        assert_ne!(a[1], original[1]);
+        // Copied chunks keep their nonce
+        assert_eq!(a[0], original[0]);
+        assert_eq!(b[2], original[2]);
+
+        let decrypted = temp.path().join("out.img");
+        decrypt_incremental(&branch_b, &decrypted, &password, None).unwrap();
+        assert_eq!(fs::read(&decrypted).unwrap(), content);
+    }
+
+    #[test]
+    fn test_swapped_chunks_rejected() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("input.bin");
+        let encrypted = temp.path().join("input.fcin");
+        fs::write(&input, vec![7u8; CHUNK * 2]).unwrap();
+
+        let password = test_password();
+        encrypt_incremental(&input, &encrypted, &password, None, CHUNK).unwrap();
+
+        // Identical plaintext chunks still differ by position in the AAD
+        let mut data = fs::read(&encrypted).unwrap();
+        let (first, second) = (
+            chunk_bytes(&data, 0).to_vec(),
+            chunk_bytes(&data, 1).to_vec(),
+        );
+        let start = data.len() - 2 * first.len();
+        data[start..start + first.len()].copy_from_slice(&second);
+        data[start + first.len()..].copy_from_slice(&first);
+        fs::write(&encrypted, &data).unwrap();
+
+        let result = decrypt_incremental(&encrypted, temp.path().join("out.bin"), &password, None);
+        assert!(result.is_err());
+    }
+}
@@ -15,8 +15,11 @@
 // - Modern CPU: ~100-300ms per derivation
 // - This is intentionally slow to prevent brute-force attacks
 
+use std::time::{Duration, Instant};
+
 use argon2::{Algorithm, Argon2, Params, Version};
 use rand::{rngs::SysRng, TryRng};
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroizing;
 
 use crate::crypto::secure::{Password, SecureBytes};
@@ -26,7 +29,11 @@ use crate::error::{CryptoError, CryptoResult};
 ///
 /// These identifiers are serialized into encrypted file headers to make each file
 /// self-describing. Unknown values must be rejected.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Headers store the numeric id (`to_u8`); JSON uses the lowercase name
+/// (`"argon2id"`). Add new variants to both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum KdfAlgorithm {
     Argon2id = 1,
 }
@@ -75,16 +82,42 @@ const MAX_TIME_COST: u32 = 10;
 const MIN_PARALLELISM: u32 = 1;
 const MAX_PARALLELISM: u32 = 16;
 const MIN_SALT_LENGTH: u32 = 16; // Current default, minimum for security
-const MAX_SALT_LENGTH: u32 = 64; // Allow future flexibility without format changes
-                                 // AES-256 requires a 32-byte key, so the allowed range is fixed for now.
-const MIN_KEY_LENGTH: u32 = 32;
-const MAX_KEY_LENGTH: u32 = 32;
+pub(crate) const MAX_SALT_LENGTH: u32 = 64; // Allow future flexibility without format changes
+
+/// Key length the cipher requires (AES-256-GCM and ChaCha20-Poly1305 take exactly 32 bytes)
+const CIPHER_KEY_LENGTH: u32 = KEY_LENGTH as u32;
+
+#[cfg(test)]
+thread_local! {
+    /// Argon2 runs on the current thread, so tests can assert none happened
+    static DERIVATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Number of key derivations attempted on the current thread (tests only)
+#[cfg(test)]
+pub(crate) fn derivation_count() -> usize {
+    DERIVATIONS.with(|count| count.get())
+}
+
+/// Weakest Argon2id cost treated as secure, as memory (KiB) x iterations.
+/// OWASP's lowest recommended configuration is 19 MiB with 2 iterations.
+const SECURITY_FLOOR_COST: u64 = 19 * 1024 * 2;
+
+/// A derivation finishing faster than this is not doing the configured work
+const MIN_PLAUSIBLE_DERIVATION: Duration = Duration::from_millis(2);
+
+/// Output length range for `derive_key_material` (e.g. 64 bytes split into two keys)
+const MIN_KEY_MATERIAL_LENGTH: usize = 16;
+const MAX_KEY_MATERIAL_LENGTH: usize = 1024;
 
 /// KDF parameters stored in encrypted file headers.
 ///
 /// These are public, integrity-protected metadata. They must be validated to
 /// prevent malicious inputs from causing excessive CPU/memory usage.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Serializable so commands can report and accept them; deserialized values
+/// are untrusted and go through `validate` before any derivation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KdfParams {
     pub algorithm: KdfAlgorithm,
     pub memory_cost_kib: u32,
@@ -111,7 +144,8 @@ impl KdfParams {
     /// Validate KDF parameters and enforce guardrails.
     ///
     /// This rejects values that are too small (weak) or too large (DoS risk),
-    /// and currently pins key length to AES-256 (32 bytes).
+    /// and a key length other than the cipher's (32 bytes for AES-256), which
+    /// would otherwise only surface as an opaque failure when the cipher is built.
     pub fn validate(&self) -> CryptoResult<()> {
         match self.algorithm {
             KdfAlgorithm::Argon2id => {}
@@ -135,10 +169,10 @@ impl KdfParams {
                 self.parallelism, MIN_PARALLELISM, MAX_PARALLELISM
             )));
         }
-        if self.key_length < MIN_KEY_LENGTH || self.key_length > MAX_KEY_LENGTH {
+        if self.key_length != CIPHER_KEY_LENGTH {
             return Err(CryptoError::FormatError(format!(
-                "Invalid KDF key length: {} bytes (must be {}-{})",
-                self.key_length, MIN_KEY_LENGTH, MAX_KEY_LENGTH
+                "Invalid KDF key length: {} bytes (AES-256-GCM requires {})",
+                self.key_length, CIPHER_KEY_LENGTH
             )));
         }
         if self.salt_length < MIN_SALT_LENGTH || self.salt_length > MAX_SALT_LENGTH {
@@ -192,7 +226,52 @@ pub fn derive_key_with_params(
     salt: &[u8],
     params: &KdfParams,
 ) -> CryptoResult<SecureBytes> {
-    derive_key_with_material(password.as_bytes(), salt, params)
+    derive_key_with_params_checked(password, salt, params, false)
+}
+
+/// [`derive_key_with_params`] with a watchdog for misconfigured parameters.
+///
+/// Parameters below the security floor, or a derivation that completes
+/// implausibly fast, point to a build shipping weak Argon2 settings. Both are
+/// logged as warnings; with `strict` they are returned as errors instead
+/// (weak parameters are rejected before any work is done).
+pub fn derive_key_with_params_checked(
+    password: &Password,
+    salt: &[u8],
+    params: &KdfParams,
+    strict: bool,
+) -> CryptoResult<SecureBytes> {
+    let watchdog = |finding: Option<String>| match finding {
+        Some(reason) if strict => Err(CryptoError::FormatError(reason)),
+        Some(reason) => {
+            log::warn!("{}", reason);
+            Ok(())
+        }
+        None => Ok(()),
+    };
+
+    watchdog(weak_params_finding(params))?;
+    let start = Instant::now();
+    let key = derive_key_with_material(password.as_bytes(), salt, params)?;
+    watchdog(fast_derivation_finding(start.elapsed()))?;
+    Ok(key)
+}
+
+/// Describe why `params` fall below the security floor, if they do
+pub fn weak_params_finding(params: &KdfParams) -> Option<String> {
+    let cost = u64::from(params.memory_cost_kib) * u64::from(params.time_cost);
+    (cost < SECURITY_FLOOR_COST).then(|| {
+        format!(
+            "KDF parameters below security floor: {} KiB x {} iterations",
+            params.memory_cost_kib, params.time_cost
+        )
+    })
+}
+
+/// Describe why a derivation taking `elapsed` looks misconfigured, if it does
+fn fast_derivation_finding(elapsed: Duration) -> Option<String> {
+    (elapsed < MIN_PLAUSIBLE_DERIVATION)
+        .then(|| format!("Key derivation implausibly fast: {:?}", elapsed))
 }
 
 /// Derive a key from raw key material bytes using explicit KDF parameters.
@@ -210,8 +289,39 @@ pub fn derive_key_with_material(
     salt: &[u8],
     params: &KdfParams,
 ) -> CryptoResult<SecureBytes> {
+    derive_key_material(key_material, salt, params, params.key_length as usize)
+}
+
+/// Derive `len` bytes of key material using explicit KDF parameters.
+///
+/// Same as `derive_key_with_material`, but the output length is chosen by the
+/// caller instead of `params.key_length`. Deriving e.g. 64 bytes and splitting
+/// them with `SecureBytes::split_at` yields independent encryption and
+/// authentication/commitment keys from one Argon2id run.
+///
+/// # Arguments
+/// * `key_material` - Raw bytes to derive from (e.g., password bytes or password || key_file_hash)
+/// * `salt` - Random salt bytes
+/// * `params` - KDF parameters from the file header
+/// * `len` - Output length in bytes (16-1024)
+pub fn derive_key_material(
+    key_material: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+    len: usize,
+) -> CryptoResult<SecureBytes> {
+    if !(MIN_KEY_MATERIAL_LENGTH..=MAX_KEY_MATERIAL_LENGTH).contains(&len) {
+        return Err(CryptoError::FormatError(format!(
+            "Invalid key material length: {} bytes (must be {}-{})",
+            len, MIN_KEY_MATERIAL_LENGTH, MAX_KEY_MATERIAL_LENGTH
+        )));
+    }
+
     params.validate()?;
 
+    #[cfg(test)]
+    DERIVATIONS.with(|count| count.set(count.get() + 1));
+
     // Validate salt length matches the header parameter (not just range).
     if salt.len() != params.salt_length as usize {
         return Err(CryptoError::FormatError(format!(
@@ -225,7 +335,7 @@ pub fn derive_key_with_material(
         params.memory_cost_kib,
         params.time_cost,
         params.parallelism,
-        Some(params.key_length as usize),
+        Some(len),
     )
     .map_err(|_| CryptoError::EncryptionFailed)?;
 
@@ -233,7 +343,7 @@ pub fn derive_key_with_material(
         KdfAlgorithm::Argon2id => Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params),
     };
 
-    let mut key_bytes = Zeroizing::new(vec![0u8; params.key_length as usize]);
+    let mut key_bytes = Zeroizing::new(vec![0u8; len]);
 
     argon2
         .hash_password_into(key_material, salt, &mut key_bytes)
@@ -399,6 +509,31 @@ mod tests {
         assert!(duration.as_secs() < 5, "Key derivation too slow");
     }
 
+    #[test]
+    fn test_watchdog_flags_weak_params() {
+        let password = Password::new("test".to_string());
+        let salt = generate_salt().unwrap();
+        let weak = KdfParams {
+            memory_cost_kib: MIN_MEMORY_COST,
+            time_cost: MIN_TIME_COST,
+            ..KdfParams::default()
+        };
+
+        assert!(weak_params_finding(&weak).is_some());
+        assert!(weak_params_finding(&KdfParams::default()).is_none());
+
+        // Strict mode refuses before deriving; the default only warns
+        let result = derive_key_with_params_checked(&password, &salt, &weak, true);
+        assert!(matches!(result, Err(CryptoError::FormatError(msg)) if msg.contains("floor")));
+        assert!(derive_key_with_params(&password, &salt, &weak).is_ok());
+    }
+
+    #[test]
+    fn test_watchdog_flags_fast_derivation() {
+        assert!(fast_derivation_finding(Duration::from_micros(100)).is_some());
+        assert!(fast_derivation_finding(Duration::from_millis(150)).is_none());
+    }
+
     #[test]
     fn test_derive_key_invalid_salt_length() {
         let password = Password::new("test".to_string());
@@ -434,6 +569,40 @@ mod tests {
         assert!(derive_key(&password, &valid_salt).is_ok());
     }
 
+    #[test]
+    fn test_derive_key_material_length() {
+        let params = KdfParams {
+            memory_cost_kib: MIN_MEMORY_COST,
+            time_cost: 1,
+            parallelism: 1,
+            ..KdfParams::default()
+        };
+        let salt = vec![7u8; SALT_LENGTH];
+
+        let material = derive_key_material(b"password", &salt, &params, 64).unwrap();
+        assert_eq!(material.len(), 64);
+
+        // Deterministic for the same inputs
+        let again = derive_key_material(b"password", &salt, &params, 64).unwrap();
+        assert_eq!(material.as_slice(), again.as_slice());
+
+        let (enc_key, auth_key) = material.split_at(KEY_LENGTH);
+        assert_eq!(enc_key.len(), KEY_LENGTH);
+        assert_eq!(auth_key.len(), 64 - KEY_LENGTH);
+        assert_ne!(enc_key.as_slice(), auth_key.as_slice());
+    }
+
+    #[test]
+    fn test_derive_key_material_rejects_bad_length() {
+        let salt = vec![7u8; SALT_LENGTH];
+        let params = KdfParams::default();
+
+        for len in [0, MIN_KEY_MATERIAL_LENGTH - 1, MAX_KEY_MATERIAL_LENGTH + 1] {
+            let result = derive_key_material(b"password", &salt, &params, len);
+            assert!(matches!(result, Err(CryptoError::FormatError(_))));
+        }
+    }
+
     #[test]
     fn test_kdf_params_validate_rejects_out_of_bounds() {
         let mut params = KdfParams::default();
@@ -456,9 +625,9 @@ mod tests {
         assert!(params.validate().is_err());
         params.parallelism = PARALLELISM;
 
-        params.key_length = MIN_KEY_LENGTH - 1;
+        params.key_length = CIPHER_KEY_LENGTH - 1;
         assert!(params.validate().is_err());
-        params.key_length = MAX_KEY_LENGTH + 1;
+        params.key_length = CIPHER_KEY_LENGTH + 1;
         assert!(params.validate().is_err());
         params.key_length = KEY_LENGTH as u32;
 
@@ -467,4 +636,39 @@ mod tests {
         params.salt_length = MAX_SALT_LENGTH + 1;
         assert!(params.validate().is_err());
     }
+
+    #[test]
+    fn test_kdf_params_json_roundtrip() {
+        let params = KdfParams {
+            memory_cost_kib: 32 * 1024,
+            time_cost: 4,
+            salt_length: 32,
+            ..KdfParams::default()
+        };
+
+        let json = serde_json::to_value(params).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "algorithm": "argon2id",
+                "memory_cost_kib": 32768,
+                "time_cost": 4,
+                "parallelism": PARALLELISM,
+                "key_length": 32,
+                "salt_length": 32,
+            })
+        );
+        let parsed: KdfParams = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, params);
+
+        // The JSON name and the header id identify the same algorithm
+        let algorithm = KdfAlgorithm::Argon2id;
+        assert_eq!(KdfAlgorithm::from_u8(algorithm.to_u8()).unwrap(), algorithm);
+        assert_eq!(serde_json::to_string(&algorithm).unwrap(), "\"argon2id\"");
+        assert_eq!(
+            serde_json::from_str::<KdfAlgorithm>("\"argon2id\"").unwrap(),
+            algorithm
+        );
+        assert!(serde_json::from_str::<KdfAlgorithm>("\"argon2i\"").is_err());
+    }
 }
@@ -13,6 +13,11 @@
 // - Key files are streamed in 8KB chunks (constant memory usage)
 // - Empty files and files >10MB are rejected
 // - Generated key files contain 32 cryptographically random bytes
+// - Low-entropy key files (all zeros, repeated bytes) can be flagged with an
+//   advisory check before use
+// - Seed-derived key files are only as strong as the seed phrase: anyone who
+//   knows (or guesses) the seed can recreate the key file, so the seed is
+//   effectively a second password and should be chosen and stored like one
 
 use std::fs::File;
 use std::io::{BufReader, Read, Write};
@@ -21,7 +26,8 @@ use std::path::Path;
 use rand::{rngs::SysRng, TryRng};
 use zeroize::Zeroizing;
 
-use crate::crypto::secure::SecureBytes;
+use crate::crypto::kdf::{derive_key_with_material, KdfAlgorithm, KdfParams};
+use crate::crypto::secure::{Password, SecureBytes};
 use crate::error::{CryptoError, CryptoResult};
 use crate::security;
 
@@ -34,6 +40,22 @@ const HASH_BUFFER_SIZE: usize = 8 * 1024;
 /// Size of generated key files (32 bytes of random data)
 const GENERATED_KEY_FILE_SIZE: usize = 32;
 
+/// Fixed domain-separation salt for seed-derived key files (32 bytes)
+const SEED_KEY_FILE_SALT: &[u8; 32] = b"filecrypter/keyfile-from-seed/v1";
+
+/// Argon2id parameters for seed-derived key files.
+///
+/// Pinned explicitly (not `KdfParams::default()`) so that changing the default
+/// file encryption parameters never changes the key file produced by a seed.
+const SEED_KDF_PARAMS: KdfParams = KdfParams {
+    algorithm: KdfAlgorithm::Argon2id,
+    memory_cost_kib: 65536,
+    time_cost: 3,
+    parallelism: 4,
+    key_length: GENERATED_KEY_FILE_SIZE as u32,
+    salt_length: SEED_KEY_FILE_SALT.len() as u32,
+};
+
 /// Hash a key file's contents using BLAKE3 to produce 32 bytes.
 ///
 /// The file is streamed in 8KB chunks for constant memory usage.
@@ -49,6 +71,71 @@ const GENERATED_KEY_FILE_SIZE: usize = 32;
 /// - Files larger than 10MB are rejected
 /// - I/O errors during reading
 pub fn hash_key_file(path: &Path) -> CryptoResult<SecureBytes> {
+    let mut reader = open_key_file(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = [0u8; HASH_BUFFER_SIZE];
+
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    let hash = hasher.finalize();
+    Ok(SecureBytes::new(hash.as_bytes().to_vec()))
+}
+
+/// Check a key file for suspiciously low entropy.
+///
+/// This is advisory only: such key files still work, but they are usually
+/// created by accident (e.g. an "empty" text file holding just a newline) and
+/// lead to confusing "wrong key file" errors later. Flagged patterns:
+/// - all bytes are zero
+/// - a single byte value repeated for the whole file
+/// - whitespace only
+///
+/// # Arguments
+/// * `path` - Path to the key file
+///
+/// # Returns
+/// `Some(warning)` describing the problem, or `None` if nothing looks off
+///
+/// # Errors
+/// Same validation errors as [`hash_key_file`]
+pub fn validate_key_file_entropy(path: &Path) -> CryptoResult<Option<String>> {
+    let mut reader = open_key_file(path)?;
+    let mut buffer = Zeroizing::new([0u8; HASH_BUFFER_SIZE]);
+    let mut first_byte: Option<u8> = None;
+    let mut single_byte = true;
+    let mut whitespace_only = true;
+
+    loop {
+        let bytes_read = reader.read(buffer.as_mut())?;
+        if bytes_read == 0 {
+            break;
+        }
+        for &byte in &buffer[..bytes_read] {
+            let first = *first_byte.get_or_insert(byte);
+            single_byte &= byte == first;
+            whitespace_only &= byte.is_ascii_whitespace();
+        }
+        if !single_byte && !whitespace_only {
+            return Ok(None);
+        }
+    }
+
+    let warning = match first_byte {
+        Some(0) if single_byte => "Key file contains only zero bytes",
+        _ if whitespace_only => "Key file contains only whitespace",
+        _ => "Key file consists of a single repeated byte",
+    };
+    Ok(Some(warning.to_string()))
+}
+
+/// Open a key file for reading after validating its size and type.
+fn open_key_file(path: &Path) -> CryptoResult<BufReader<File>> {
     let file = File::open(path)?;
     let metadata = file.metadata()?;
     let file_size = metadata.len();
@@ -71,20 +158,24 @@ pub fn hash_key_file(path: &Path) -> CryptoResult<SecureBytes> {
         ));
     }
 
-    let mut reader = BufReader::new(file);
-    let mut hasher = blake3::Hasher::new();
-    let mut buffer = [0u8; HASH_BUFFER_SIZE];
+    Ok(BufReader::new(file))
+}
 
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
+/// Generate key file contents: 32 cryptographically random bytes.
+///
+/// Use this when the key material should not touch the disk (e.g. shown as a
+/// QR code or stored in a secret manager). The bytes are zeroized on drop.
+///
+/// # Errors
+/// - RNG failure
+pub fn generate_key_file_bytes() -> CryptoResult<SecureBytes> {
+    let mut key_data = Zeroizing::new(vec![0u8; GENERATED_KEY_FILE_SIZE]);
+    let mut rng = SysRng;
+    rng.try_fill_bytes(&mut key_data)
+        .map_err(|_| CryptoError::EncryptionFailed)?;
 
-    let hash = hasher.finalize();
-    Ok(SecureBytes::new(hash.as_bytes().to_vec()))
+    // Move the buffer (no copy) into SecureBytes, which zeroizes on drop
+    Ok(SecureBytes::new(std::mem::take(&mut *key_data)))
 }
 
 /// Generate a key file containing 32 cryptographically random bytes.
@@ -96,13 +187,52 @@ pub fn hash_key_file(path: &Path) -> CryptoResult<SecureBytes> {
 /// - I/O errors during writing
 /// - RNG failure
 pub fn generate_key_file(path: &Path) -> CryptoResult<()> {
-    let mut key_data = Zeroizing::new([0u8; GENERATED_KEY_FILE_SIZE]);
-    let mut rng = SysRng;
-    rng.try_fill_bytes(key_data.as_mut())
-        .map_err(|_| CryptoError::EncryptionFailed)?;
+    let key_data = generate_key_file_bytes()?;
 
     let mut file = security::create_secure_file(path)?;
-    file.write_all(key_data.as_ref())?;
+    file.write_all(key_data.as_slice())?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Derive a key file deterministically from a seed phrase.
+///
+/// The seed is run through Argon2id with a fixed salt, so the same seed always
+/// produces the same 32-byte key file. This lets users recreate a lost key file
+/// from a memorized phrase. Whitespace is normalized (trimmed, runs collapsed to
+/// a single space) so retyping the seed with different spacing still matches.
+///
+/// # Tradeoff
+/// The key file is no more secret than the seed: it becomes a second password
+/// rather than a random second factor.
+///
+/// # Arguments
+/// * `path` - Path where the key file will be created
+/// * `seed_phrase` - The seed phrase (zeroized on drop)
+///
+/// # Errors
+/// - Empty or whitespace-only seed phrases are rejected
+/// - I/O errors during writing
+pub fn generate_key_file_from_seed(path: &Path, seed_phrase: &Password) -> CryptoResult<()> {
+    let normalized = Zeroizing::new(
+        seed_phrase
+            .as_str()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    if normalized.is_empty() {
+        return Err(CryptoError::KeyFileError(
+            "Seed phrase cannot be empty".to_string(),
+        ));
+    }
+
+    let key_data =
+        derive_key_with_material(normalized.as_bytes(), SEED_KEY_FILE_SALT, &SEED_KDF_PARAMS)?;
+
+    let mut file = security::create_secure_file(path)?;
+    file.write_all(key_data.as_slice())?;
     file.flush()?;
 
     Ok(())
@@ -205,6 +335,88 @@ mod tests {
         assert_ne!(data1, data2);
     }
 
+    #[test]
+    fn test_generate_key_file_bytes_32_and_unique() {
+        let first = generate_key_file_bytes().unwrap();
+        let second = generate_key_file_bytes().unwrap();
+
+        assert_eq!(first.len(), 32);
+        assert_eq!(second.len(), 32);
+        assert_ne!(first.as_slice(), second.as_slice());
+    }
+
+    #[test]
+    fn test_validate_key_file_entropy_flags_all_zero() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), [0u8; 64]).unwrap();
+
+        let warning = validate_key_file_entropy(file.path()).unwrap();
+        assert!(warning.unwrap().contains("zero"));
+    }
+
+    #[test]
+    fn test_validate_key_file_entropy_flags_repeated_and_whitespace() {
+        let repeated = NamedTempFile::new().unwrap();
+        fs::write(repeated.path(), [0xAAu8; 20_000]).unwrap();
+        let warning = validate_key_file_entropy(repeated.path()).unwrap();
+        assert!(warning.unwrap().contains("repeated"));
+
+        let whitespace = NamedTempFile::new().unwrap();
+        fs::write(whitespace.path(), b" \n\r\n\t").unwrap();
+        let warning = validate_key_file_entropy(whitespace.path()).unwrap();
+        assert!(warning.unwrap().contains("whitespace"));
+    }
+
+    #[test]
+    fn test_validate_key_file_entropy_accepts_generated_key() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.key");
+        generate_key_file(&path).unwrap();
+
+        assert!(validate_key_file_entropy(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_validate_key_file_entropy_rejects_empty() {
+        let file = NamedTempFile::new().unwrap();
+
+        assert!(validate_key_file_entropy(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_generate_key_file_from_seed_is_reproducible() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path1 = temp_dir.path().join("seed1.key");
+        let path2 = temp_dir.path().join("seed2.key");
+        let path3 = temp_dir.path().join("other.key");
+
+        let seed = Password::new("correct horse battery staple".to_string());
+        generate_key_file_from_seed(&path1, &seed).unwrap();
+        // Different spacing normalizes to the same seed
+        let respaced = Password::new("  correct horse\tbattery   staple\n".to_string());
+        generate_key_file_from_seed(&path2, &respaced).unwrap();
+        let other = Password::new("correct horse battery stapler".to_string());
+        generate_key_file_from_seed(&path3, &other).unwrap();
+
+        let data1 = fs::read(&path1).unwrap();
+        let data2 = fs::read(&path2).unwrap();
+        let data3 = fs::read(&path3).unwrap();
+
+        assert_eq!(data1.len(), GENERATED_KEY_FILE_SIZE);
+        assert_eq!(data1, data2);
+        assert_ne!(data1, data3);
+    }
+
+    #[test]
+    fn test_generate_key_file_from_seed_rejects_empty() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("seed.key");
+
+        let result = generate_key_file_from_seed(&path, &Password::new(" \t ".to_string()));
+        assert!(matches!(result, Err(CryptoError::KeyFileError(_))));
+        assert!(!path.exists());
+    }
+
     #[test]
     fn test_combine_password_and_keyfile() {
         let password = b"password123";
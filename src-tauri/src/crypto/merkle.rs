@@ -0,0 +1,214 @@
+// crypto/merkle.rs - Per-Chunk Merkle Tree
+//
+// With the `merkle` feature, encryption can record a Merkle root over the
+// chunk records in the integrity trailer (V9 EXT_FLAG_MERKLE). A client that
+// fetched only some chunks of a large file (e.g. with HTTP range requests) can
+// then check each one against the root with a short inclusion proof, without
+// the password and without the rest of the file.
+//
+// The tree follows RFC 6962: a leaf is BLAKE3(0x00 || chunk record), where the
+// record is the chunk as stored ([CHUNK_LEN:4][CIPHERTEXT+TAG]), an interior
+// node is BLAKE3(0x01 || left || right), and a tree that is not full splits at
+// the largest power of two below its leaf count. Only the root is stored;
+// proofs are rebuilt from the chunk records, which needs no key.
+//
+// The root cannot live in the header, which is the AAD of every chunk, so it
+// is part of the trailer fields and authenticated by the trailer tag.
+// Decryption always checks it, with or without the feature.
+
+use blake3::Hasher;
+
+/// Size of a tree hash (BLAKE3 output)
+pub const MERKLE_HASH_SIZE: usize = 32;
+
+/// Domain separation prefix for leaf hashes
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Domain separation prefix for interior node hashes
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hash of one chunk record, given as the pieces it is stored in
+pub(crate) fn leaf_hash(record: &[&[u8]]) -> [u8; MERKLE_HASH_SIZE] {
+    let mut hasher = Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    for part in record {
+        hasher.update(part);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn node_hash(
+    left: &[u8; MERKLE_HASH_SIZE],
+    right: &[u8; MERKLE_HASH_SIZE],
+) -> [u8; MERKLE_HASH_SIZE] {
+    let mut hasher = Hasher::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+/// Incremental root computation, one leaf at a time in chunk order
+///
+/// Keeps the roots of the complete subtrees seen so far (at most one per
+/// power of two), so memory stays O(log n) however many chunks the file has.
+#[derive(Default)]
+pub(crate) struct MerkleBuilder {
+    /// (leaf count, root) of each complete subtree, largest first
+    subtrees: Vec<(u64, [u8; MERKLE_HASH_SIZE])>,
+}
+
+impl MerkleBuilder {
+    /// Append the next leaf
+    pub(crate) fn push(&mut self, leaf: [u8; MERKLE_HASH_SIZE]) {
+        let (mut size, mut hash) = (1u64, leaf);
+        while let Some(&(left_size, left)) = self.subtrees.last() {
+            if left_size != size {
+                break;
+            }
+            self.subtrees.pop();
+            size *= 2;
+            hash = node_hash(&left, &hash);
+        }
+        self.subtrees.push((size, hash));
+    }
+
+    /// Root of the leaves pushed so far
+    pub(crate) fn root(&self) -> [u8; MERKLE_HASH_SIZE] {
+        let mut subtrees = self.subtrees.iter().rev();
+        let Some(&(_, mut root)) = subtrees.next() else {
+            return *blake3::hash(&[]).as_bytes();
+        };
+        for (_, left) in subtrees {
+            root = node_hash(left, &root);
+        }
+        root
+    }
+}
+
+/// Inclusion proof for one chunk of a file with a Merkle tree
+#[cfg(feature = "merkle")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkProof {
+    /// Zero-based index of the chunk
+    pub chunk_index: u64,
+    /// Number of chunks (leaves) in the file
+    pub total_chunks: u64,
+    /// Byte offset of the chunk record in the encrypted file
+    pub offset: u64,
+    /// Length of the chunk record (length prefix included)
+    pub length: u64,
+    /// Sibling hashes from the leaf up to the root
+    pub siblings: Vec<[u8; MERKLE_HASH_SIZE]>,
+    /// Root recorded in the file's trailer
+    pub root: [u8; MERKLE_HASH_SIZE],
+}
+
+/// Sibling hashes proving leaf `index` of `leaves`, bottom up (RFC 6962 PATH)
+#[cfg(feature = "merkle")]
+pub(crate) fn inclusion_path(
+    index: usize,
+    leaves: &[[u8; MERKLE_HASH_SIZE]],
+) -> Vec<[u8; MERKLE_HASH_SIZE]> {
+    fn subtree_root(leaves: &[[u8; MERKLE_HASH_SIZE]]) -> [u8; MERKLE_HASH_SIZE] {
+        let mut builder = MerkleBuilder::default();
+        leaves.iter().for_each(|leaf| builder.push(*leaf));
+        builder.root()
+    }
+
+    fn walk(
+        index: usize,
+        leaves: &[[u8; MERKLE_HASH_SIZE]],
+        path: &mut Vec<[u8; MERKLE_HASH_SIZE]>,
+    ) {
+        if leaves.len() <= 1 {
+            return;
+        }
+        // Largest power of two below the leaf count
+        let split = 1 << (usize::BITS - 1 - (leaves.len() - 1).leading_zeros());
+        if index < split {
+            walk(index, &leaves[..split], path);
+            path.push(subtree_root(&leaves[split..]));
+        } else {
+            walk(index - split, &leaves[split..], path);
+            path.push(subtree_root(&leaves[..split]));
+        }
+    }
+
+    let mut path = Vec::new();
+    walk(index, leaves, &mut path);
+    path
+}
+
+/// Check that `record` is chunk `proof.chunk_index` of a file whose Merkle root is `root`
+///
+/// `record` is the chunk exactly as stored, length prefix included (the
+/// `proof.length` bytes at `proof.offset`). `root` must come from a trusted
+/// source, such as the original file: the root inside `proof` is only what
+/// the file that produced it claims. A valid proof shows the ciphertext is
+/// unmodified; it does not decrypt or authenticate it under any key.
+#[cfg(feature = "merkle")]
+pub fn verify_chunk_proof(
+    root: &[u8; MERKLE_HASH_SIZE],
+    record: &[u8],
+    proof: &ChunkProof,
+) -> bool {
+    // RFC 9162 section 2.1.3.2
+    if proof.chunk_index >= proof.total_chunks || record.len() as u64 != proof.length {
+        return false;
+    }
+    let mut index = proof.chunk_index;
+    let mut last = proof.total_chunks - 1;
+    let mut hash = leaf_hash(&[record]);
+    for sibling in &proof.siblings {
+        if last == 0 {
+            return false;
+        }
+        if index & 1 == 1 || index == last {
+            hash = node_hash(sibling, &hash);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    // blake3::Hash equality is constant-time
+    last == 0 && blake3::Hash::from(hash) == blake3::Hash::from(*root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 6962 MTH, computed recursively
+    fn reference_root(leaves: &[[u8; MERKLE_HASH_SIZE]]) -> [u8; MERKLE_HASH_SIZE] {
+        match leaves.len() {
+            0 => *blake3::hash(&[]).as_bytes(),
+            1 => leaves[0],
+            n => {
+                let mut split = 1;
+                while split * 2 < n {
+                    split *= 2;
+                }
+                node_hash(
+                    &reference_root(&leaves[..split]),
+                    &reference_root(&leaves[split..]),
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_builder_matches_recursive_root() {
+        let leaves: Vec<_> = (0u8..20).map(|i| leaf_hash(&[&[i]])).collect();
+        for n in 0..=leaves.len() {
+            let mut builder = MerkleBuilder::default();
+            leaves[..n].iter().for_each(|leaf| builder.push(*leaf));
+            assert_eq!(builder.root(), reference_root(&leaves[..n]), "{} leaves", n);
+        }
+    }
+}
@@ -3,19 +3,58 @@
 // This module provides all cryptographic operations for FileCrypter.
 // It exports a clean API for file encryption and decryption.
 
+pub mod bundle;
+pub mod cancel;
 mod cipher;
 pub mod compression;
+pub mod incremental;
 mod kdf;
 pub mod keyfile;
+pub mod merkle;
+pub mod progress;
+pub mod range;
+pub mod recipients;
 mod secure;
 pub mod streaming;
+pub mod vault;
+pub mod xattrs;
 
 // Re-export the main types and functions for easy access
-pub use compression::{compress, CompressionAlgorithm, CompressionConfig};
+pub use bundle::{encrypt_files_bundle, extract_bundle};
+pub use cancel::CancellationToken;
+pub use cipher::CipherAlgorithm;
+pub use compression::{compress, CompressionAlgorithm, CompressionConfig, CompressionDictionary};
+pub use incremental::{
+    decrypt_incremental, encrypt_incremental, update_incremental, IncrementalUpdate,
+};
 pub use kdf::{
-    derive_key, derive_key_with_material, derive_key_with_params, generate_salt,
-    generate_salt_with_len, KdfAlgorithm, KdfParams,
+    derive_key, derive_key_material, derive_key_with_material, derive_key_with_params,
+    derive_key_with_params_checked, generate_salt, generate_salt_with_len, weak_params_finding,
+    KdfAlgorithm, KdfParams,
+};
+pub use keyfile::{
+    combine_password_and_keyfile, generate_key_file, generate_key_file_bytes,
+    generate_key_file_from_seed, hash_key_file, validate_key_file_entropy,
 };
-pub use keyfile::{combine_password_and_keyfile, generate_key_file, hash_key_file};
+#[cfg(feature = "merkle")]
+pub use merkle::{verify_chunk_proof, ChunkProof};
+pub use progress::CopyProgress;
+pub use range::{decrypt_range, encrypt_range, ByteRange};
+pub use recipients::{decrypt_for_recipient, encrypt_for_recipients, Recipient};
 pub use secure::{Password, SecureBytes};
-pub use streaming::{decrypt_file_streaming, encrypt_file_streaming, DEFAULT_CHUNK_SIZE};
+#[cfg(all(feature = "test-vectors", debug_assertions))]
+pub use streaming::encrypt_test_vector;
+#[cfg(feature = "merkle")]
+pub use streaming::prove_chunk;
+pub use streaming::{
+    check_file_truncation, decrypt_file_streaming, decrypt_file_streaming_cancellable,
+    decrypt_to_pipe, decrypt_to_writer, describe_encrypted_file, encrypt_file_streaming,
+    encrypt_file_streaming_cancellable, encrypt_file_streaming_hashed, encrypt_file_streaming_with,
+    encrypt_file_streaming_with_kdf, encrypt_to_writer, format_version_info, format_versions,
+    is_filecrypter_file, key_file_matches, max_chunks, max_encrypted_size, read_creator_version,
+    read_file_mode, read_kdf_params, read_xattrs, reencrypt_file_streaming, verify_file_streaming,
+    DecryptedMetadata, DecryptingReader, EncryptOptions, EncryptingWriter, FormatVersionInfo,
+    PipeReader, TruncationReport, DEFAULT_CHUNK_SIZE, DEFAULT_MAX_CHUNKS, MAX_CHUNKS_ENV,
+};
+pub use vault::{open_archive_handle, ArchiveHandle, VaultEntry};
+pub use xattrs::ExtendedAttribute;
@@ -0,0 +1,82 @@
+// crypto/progress.rs - Byte Counting for Streaming Writes
+//
+// Streaming paths such as archive extraction need to know how many bytes they
+// have written so far, both for progress reporting and for size limits. `CopyProgress` wraps any writer, keeps a running total, and reports
+// it to a callback after every write, so each path shares the same semantics:
+// the total counts bytes accepted by the inner writer, not bytes offered.
+
+use std::io::Write;
+
+/// Writer adapter that counts bytes and reports the running total
+///
+/// The callback receives the total number of bytes written so far, after each
+/// successful write that wrote at least one byte.
+pub struct CopyProgress<W, F> {
+    inner: W,
+    bytes_written: u64,
+    callback: F,
+}
+
+impl<W: Write, F: FnMut(u64)> CopyProgress<W, F> {
+    /// Wrap `inner`, calling `callback` with the running total after each write
+    pub fn new(inner: W, callback: F) -> Self {
+        Self {
+            inner,
+            bytes_written: 0,
+            callback,
+        }
+    }
+
+    /// Total bytes written through this wrapper
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Unwrap the inner writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write, F: FnMut(u64)> Write for CopyProgress<W, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if written > 0 {
+            self.bytes_written = self.bytes_written.saturating_add(written as u64);
+            (self.callback)(self.bytes_written);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_progress_counts_bytes() {
+        let mut writer = CopyProgress::new(Vec::new(), |_| {});
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"world").unwrap();
+        std::io::copy(&mut &b"!!"[..], &mut writer).unwrap();
+
+        assert_eq!(writer.bytes_written(), 13);
+        assert_eq!(writer.into_inner(), b"hello world!!");
+    }
+
+    #[test]
+    fn test_copy_progress_reports_running_total() {
+        let mut totals = Vec::new();
+        let mut writer = CopyProgress::new(Vec::new(), |total| totals.push(total));
+        writer.write_all(&[1u8; 10]).unwrap();
+        writer.write_all(&[]).unwrap();
+        writer.write_all(&[2u8; 5]).unwrap();
+        drop(writer);
+
+        assert_eq!(totals, vec![10, 15]);
+    }
+}
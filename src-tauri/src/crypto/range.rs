@@ -0,0 +1,368 @@
+// crypto/range.rs - Byte-Range Encryption
+//
+// Encrypts only a region of a file, e.g. to patch an encrypted disk image or
+// protect one section of a large container, and writes it back in place later.
+//
+// File layout:
+// [MAGIC:4 "FCRG"][VERSION:1][stream]
+//
+// The stream is a regular Version 4/6 streaming-encrypted blob (see
+// streaming.rs) whose plaintext is:
+//
+// [OFFSET:8][LENGTH:8][DATA:LENGTH]
+//
+// Keeping OFFSET and LENGTH inside the plaintext means they are authenticated
+// by AES-GCM along with the data, so the write position cannot be altered.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::crypto::kdf::KdfParams;
+use crate::crypto::secure::Password;
+use crate::crypto::streaming::{
+    check_stream_length, decrypt_with_key, derive_decryption_key, derive_encryption_key,
+    encrypt_with_key, read_stream_header, OutputLock, DEFAULT_CHUNK_SIZE,
+};
+use crate::error::{CryptoError, CryptoResult};
+use crate::security::{create_secure_tempfile, create_shredding_tempfile, persist_tempfile};
+
+/// Magic bytes identifying a byte-range file
+const RANGE_MAGIC: [u8; 4] = *b"FCRG";
+
+/// Current byte-range format version
+const RANGE_VERSION: u8 = 1;
+
+/// Length of the unencrypted prefix (magic + version)
+const RANGE_PREFIX_LEN: u64 = RANGE_MAGIC.len() as u64 + 1;
+
+/// Length of the authenticated range metadata (offset + length)
+const RANGE_METADATA_LEN: usize = 8 + 8;
+
+/// Byte range recorded in an encrypted range file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    /// Offset of the first byte in the original file
+    pub offset: u64,
+    /// Number of bytes in the range
+    pub length: u64,
+}
+
+/// Encrypt `[offset, offset + length)` of `input` into a range file
+///
+/// Only the selected region is read (via seek), and the offset and length are
+/// stored in the authenticated plaintext.
+///
+/// # Arguments
+/// * `input` - File to read the range from
+/// * `range` - Region to encrypt; must lie within the file
+/// * `output` - Path for the range file (replaced if it exists)
+/// * `password` - Password for key derivation
+/// * `key_file_path` - Optional key file for two-factor encryption
+///
+/// # Returns
+/// Ok(()) on success, or CryptoError on failure
+pub fn encrypt_range<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    range: ByteRange,
+    output: Q,
+    password: &Password,
+    key_file_path: Option<&Path>,
+) -> CryptoResult<()> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+
+    let mut input_file = File::open(input.as_ref())?;
+    let file_size = input_file.metadata()?.len();
+    let in_bounds = range
+        .offset
+        .checked_add(range.length)
+        .is_some_and(|end| end <= file_size);
+    if !in_bounds {
+        return Err(CryptoError::FormatError(format!(
+            "Range {}+{} lies outside the {}-byte input",
+            range.offset, range.length, file_size
+        )));
+    }
+    input_file.seek(SeekFrom::Start(range.offset))?;
+
+    let mut metadata = [0u8; RANGE_METADATA_LEN];
+    metadata[..8].copy_from_slice(&range.offset.to_le_bytes());
+    metadata[8..].copy_from_slice(&range.length.to_le_bytes());
+    let mut reader = Cursor::new(metadata).chain(BufReader::new(input_file).take(range.length));
+
+    let output = output.as_ref();
+    let _lock = OutputLock::acquire(output)?;
+    let output_parent = output.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_secure_tempfile(output_parent)?;
+    let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+    let key = derive_encryption_key(password, key_file_path, KdfParams::default())?;
+
+    writer.write_all(&RANGE_MAGIC)?;
+    writer.write_all(&[RANGE_VERSION])?;
+    encrypt_with_key(
+        &mut reader,
+        RANGE_METADATA_LEN as u64 + range.length,
+        &mut writer,
+        &key,
+        DEFAULT_CHUNK_SIZE,
+        None,
+        false,
+        None,
+    )?;
+
+    writer.flush()?;
+    drop(writer);
+
+    if let Err(err) = persist_tempfile(temp_file, output) {
+        let _ = fs::remove_file(err.file.path());
+        return Err(CryptoError::Io(err.error));
+    }
+
+    Ok(())
+}
+
+/// Decrypt a range file and write the plaintext to `output`
+///
+/// With `patch` set, `output` must already exist and the plaintext is written
+/// at the recorded offset, leaving the rest of the file untouched. Otherwise
+/// `output` is created (or replaced) as a standalone file holding only the
+/// range. In both cases the whole stream is authenticated before `output` is
+/// modified.
+///
+/// # Arguments
+/// * `input` - Path to the range file
+/// * `output` - File to patch, or path for the standalone plaintext
+/// * `password` - Password used when the range was encrypted
+/// * `key_file_path` - Key file, if the range was encrypted with one
+/// * `patch` - Write into an existing file at the recorded offset
+///
+/// # Returns
+/// The recorded byte range, or CryptoError on failure
+pub fn decrypt_range<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    password: &Password,
+    key_file_path: Option<&Path>,
+    patch: bool,
+) -> CryptoResult<ByteRange> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+
+    let output = output.as_ref();
+    if patch && !output.is_file() {
+        return Err(CryptoError::InvalidPath(
+            "Patch target does not exist".to_string(),
+        ));
+    }
+
+    let input_file = File::open(input.as_ref())?;
+    let file_size = input_file.metadata()?.len();
+    let mut reader = BufReader::new(input_file);
+
+    let mut prefix = [0u8; RANGE_PREFIX_LEN as usize];
+    reader.read_exact(&mut prefix)?;
+    if prefix[..4] != RANGE_MAGIC {
+        return Err(CryptoError::FormatError("Not a range file".to_string()));
+    }
+    if prefix[4] != RANGE_VERSION {
+        return Err(CryptoError::FormatError(format!(
+            "Unsupported range version: {}",
+            prefix[4]
+        )));
+    }
+    let stream_len = file_size - RANGE_PREFIX_LEN;
+
+    let header = read_stream_header(&mut reader)?;
+    if header.key_file_required() && key_file_path.is_none() {
+        return Err(CryptoError::KeyFileRequired);
+    }
+    check_stream_length(&header, stream_len)?;
+    let key = derive_decryption_key(&header, password, key_file_path)?;
+
+    // Decrypt into a temp file first so `output` is only touched once the
+    // whole stream has been authenticated
+    let _lock = OutputLock::acquire(output)?;
+    let output_parent = output.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_shredding_tempfile(output_parent)?;
+    let mut writer = MetadataSplitter::new(BufWriter::new(temp_file.as_file_mut()));
+
+    decrypt_with_key(
+        &mut reader,
+        stream_len,
+        &mut writer,
+        &header,
+        None,
+        &key,
+        None,
+        false,
+    )?;
+    let range = writer.range()?;
+    writer.inner.flush()?;
+    drop(writer);
+
+    if patch {
+        let temp = temp_file.as_file_mut();
+        temp.seek(SeekFrom::Start(0))?;
+
+        let mut target = OpenOptions::new().write(true).open(output)?;
+        target.seek(SeekFrom::Start(range.offset))?;
+        std::io::copy(temp, &mut target)?;
+        target.sync_all()?;
+    } else {
+        temp_file.persist(output)?;
+    }
+
+    Ok(range)
+}
+
+/// Writer that peels the range metadata off the front of the plaintext
+struct MetadataSplitter<W: Write> {
+    inner: W,
+    metadata: [u8; RANGE_METADATA_LEN],
+    metadata_len: usize,
+    data_len: u64,
+}
+
+impl<W: Write> MetadataSplitter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            metadata: [0u8; RANGE_METADATA_LEN],
+            metadata_len: 0,
+            data_len: 0,
+        }
+    }
+
+    /// Parse the metadata and check the data length matches it
+    fn range(&self) -> CryptoResult<ByteRange> {
+        if self.metadata_len < RANGE_METADATA_LEN {
+            return Err(CryptoError::FormatError(
+                "Range metadata is truncated".to_string(),
+            ));
+        }
+
+        let range = ByteRange {
+            offset: u64::from_le_bytes(self.metadata[..8].try_into().unwrap()),
+            length: u64::from_le_bytes(self.metadata[8..].try_into().unwrap()),
+        };
+        if range.length != self.data_len || range.offset.checked_add(range.length).is_none() {
+            return Err(CryptoError::FormatError(
+                "Range length does not match its data".to_string(),
+            ));
+        }
+
+        Ok(range)
+    }
+}
+
+impl<W: Write> Write for MetadataSplitter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let take = (RANGE_METADATA_LEN - self.metadata_len).min(buf.len());
+        self.metadata[self.metadata_len..self.metadata_len + take].copy_from_slice(&buf[..take]);
+        self.metadata_len += take;
+
+        self.inner.write_all(&buf[take..])?;
+        self.data_len += (buf.len() - take) as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    fn test_password() -> Password {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let now_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        Password::new(format!("{now_nanos:x}{counter:x}"))
+    }
+
+    #[test]
+    fn test_range_roundtrip_middle_kilobyte() {
+        let temp = tempdir().unwrap();
+        let password = test_password();
+        let original: Vec<u8> = (0..8192).map(|i| (i % 251) as u8).collect();
+        let image = temp.path().join("disk.img");
+        fs::write(&image, &original).unwrap();
+
+        let range = ByteRange {
+            offset: 3584,
+            length: 1024,
+        };
+        let encrypted = temp.path().join("region.fcrg");
+        encrypt_range(&image, range, &encrypted, &password, None).unwrap();
+
+        // Standalone output holds just the range
+        let standalone = temp.path().join("region.bin");
+        let recorded = decrypt_range(&encrypted, &standalone, &password, None, false).unwrap();
+        assert_eq!(recorded, range);
+        assert_eq!(fs::read(&standalone).unwrap(), &original[3584..4608]);
+
+        // Patching a copy with the region wiped restores the original
+        let mut wiped = original.clone();
+        wiped[3584..4608].fill(0);
+        let patched = temp.path().join("patched.img");
+        fs::write(&patched, &wiped).unwrap();
+        decrypt_range(&encrypted, &patched, &password, None, true).unwrap();
+        assert_eq!(fs::read(&patched).unwrap(), original);
+    }
+
+    #[test]
+    fn test_range_outside_input_rejected() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("small.bin");
+        fs::write(&input, [0u8; 100]).unwrap();
+
+        let range = ByteRange {
+            offset: 90,
+            length: 20,
+        };
+        let result = encrypt_range(
+            &input,
+            range,
+            temp.path().join("out.fcrg"),
+            &test_password(),
+            None,
+        );
+        assert!(matches!(result, Err(CryptoError::FormatError(_))));
+    }
+
+    #[test]
+    fn test_range_wrong_password_leaves_target_untouched() {
+        let temp = tempdir().unwrap();
+        let input = temp.path().join("data.bin");
+        fs::write(&input, [7u8; 2048]).unwrap();
+
+        let range = ByteRange {
+            offset: 512,
+            length: 1024,
+        };
+        let encrypted = temp.path().join("region.fcrg");
+        encrypt_range(&input, range, &encrypted, &test_password(), None).unwrap();
+
+        let target = temp.path().join("target.bin");
+        fs::write(&target, [1u8; 2048]).unwrap();
+        let result = decrypt_range(&encrypted, &target, &test_password(), None, true);
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+        assert_eq!(fs::read(&target).unwrap(), [1u8; 2048]);
+    }
+}
@@ -0,0 +1,484 @@
+// crypto/recipients.rs - Multi-Recipient Encryption
+//
+// Encrypts a file once under a random file key and stores that key wrapped
+// separately for each recipient, so a shared team file can be opened by any
+// one recipient's credentials: a password, a key file, or both.
+//
+// File layout:
+// [MAGIC:4 "FCMR"][VERSION:1][SLOT_COUNT:1][slot 1]...[slot N][stream]
+//
+// Each slot is fixed-size:
+// [KIND:1][KDF_ALG:1][KDF_MEM_COST:4][KDF_TIME_COST:4][KDF_PARALLELISM:4]
+// [SALT:16][WRAP_NONCE:12][WRAPPED_KEY:32+16]
+//
+// WRAPPED_KEY is the file key encrypted with AES-256-GCM under the Argon2id key
+// derived from the slot's credentials. The AAD is the file prefix plus the slot
+// index and kind, so a slot cannot be moved or relabelled. The stream is a
+// regular Version 4 streaming-encrypted blob (see streaming.rs) under the file
+// key; its header KDF fields are unused.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use rand::{rngs::SysRng, TryRng};
+
+use crate::crypto::kdf::{
+    derive_key_with_material, generate_salt_with_len, KdfAlgorithm, KdfParams,
+};
+use crate::crypto::keyfile::{combine_password_and_keyfile, hash_key_file};
+use crate::crypto::secure::{Password, SecureBytes};
+use crate::crypto::streaming::{
+    check_stream_length, decrypt_with_key, encrypt_with_key, read_stream_header, DerivedKey,
+    OutputLock, DEFAULT_CHUNK_SIZE,
+};
+use crate::error::{CryptoError, CryptoResult};
+use crate::security::{create_secure_tempfile, create_shredding_tempfile, persist_tempfile};
+
+/// Magic bytes identifying a multi-recipient file
+const RECIPIENTS_MAGIC: [u8; 4] = *b"FCMR";
+
+/// Current multi-recipient format version
+const RECIPIENTS_VERSION: u8 = 1;
+
+/// Length of the unencrypted prefix (magic + version + slot count)
+const RECIPIENTS_PREFIX_LEN: usize = RECIPIENTS_MAGIC.len() + 1 + 1;
+
+/// Maximum number of recipients in one file
+const MAX_RECIPIENTS: usize = 16;
+
+/// File key length (AES-256)
+const FILE_KEY_SIZE: usize = 32;
+
+/// Salt length stored in each slot
+const SLOT_SALT_SIZE: usize = 16;
+
+/// Nonce size for AES-GCM key wrapping
+const WRAP_NONCE_SIZE: usize = 12;
+
+/// Wrapped file key: ciphertext plus AES-GCM tag
+const WRAPPED_KEY_SIZE: usize = FILE_KEY_SIZE + 16;
+
+/// Size of one recipient slot
+const SLOT_SIZE: usize = 1 + 1 + 4 + 4 + 4 + SLOT_SALT_SIZE + WRAP_NONCE_SIZE + WRAPPED_KEY_SIZE;
+
+/// Slot kind: password only
+const KIND_PASSWORD: u8 = 1;
+
+/// Slot kind: key file only
+const KIND_KEY_FILE: u8 = 2;
+
+/// Slot kind: password and key file
+const KIND_BOTH: u8 = 3;
+
+/// Credentials protecting one recipient slot (and used to open it)
+#[derive(Debug)]
+pub enum Recipient {
+    /// Password only
+    Password(Password),
+    /// Key file only
+    KeyFile(PathBuf),
+    /// Password combined with a key file
+    Both {
+        password: Password,
+        key_file: PathBuf,
+    },
+}
+
+impl Recipient {
+    fn kind(&self) -> u8 {
+        match self {
+            Recipient::Password(_) => KIND_PASSWORD,
+            Recipient::KeyFile(_) => KIND_KEY_FILE,
+            Recipient::Both { .. } => KIND_BOTH,
+        }
+    }
+
+    /// Raw key material for Argon2id, matching the single-recipient scheme
+    fn key_material(&self) -> CryptoResult<SecureBytes> {
+        match self {
+            Recipient::Password(password) => {
+                if password.is_empty() {
+                    return Err(CryptoError::FormatError(
+                        "Password cannot be empty".to_string(),
+                    ));
+                }
+                Ok(SecureBytes::new(password.as_bytes().to_vec()))
+            }
+            Recipient::KeyFile(key_file) => hash_key_file(key_file),
+            Recipient::Both { password, key_file } => {
+                if password.is_empty() {
+                    return Err(CryptoError::FormatError(
+                        "Password cannot be empty".to_string(),
+                    ));
+                }
+                let kf_hash = hash_key_file(key_file)?;
+                Ok(combine_password_and_keyfile(
+                    password.as_bytes(),
+                    kf_hash.as_slice(),
+                ))
+            }
+        }
+    }
+}
+
+/// Encrypt a file so that any one of `recipients` can decrypt it
+///
+/// # Arguments
+/// * `input` - File to encrypt
+/// * `output` - Path for the multi-recipient file (replaced if it exists)
+/// * `recipients` - One slot is written per recipient, in order
+///
+/// # Returns
+/// Ok(()) on success, or CryptoError on failure
+pub fn encrypt_for_recipients<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    recipients: &[Recipient],
+) -> CryptoResult<()> {
+    if recipients.is_empty() {
+        return Err(CryptoError::FormatError(
+            "At least one recipient is required".to_string(),
+        ));
+    }
+    if recipients.len() > MAX_RECIPIENTS {
+        return Err(CryptoError::FormatError(format!(
+            "At most {} recipients are supported",
+            MAX_RECIPIENTS
+        )));
+    }
+
+    let mut file_key = vec![0u8; FILE_KEY_SIZE];
+    SysRng
+        .try_fill_bytes(&mut file_key)
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    let file_key = SecureBytes::new(file_key);
+
+    let prefix = prefix_bytes(recipients.len() as u8);
+    let mut slots = Vec::with_capacity(recipients.len() * SLOT_SIZE);
+    for (index, recipient) in recipients.iter().enumerate() {
+        slots.extend_from_slice(&wrap_slot(&prefix, index, recipient, &file_key)?);
+    }
+
+    let input_file = File::open(input.as_ref())?;
+    let input_len = input_file.metadata()?.len();
+    let mut reader = BufReader::new(input_file);
+
+    let output = output.as_ref();
+    let _lock = OutputLock::acquire(output)?;
+    let output_parent = output.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_secure_tempfile(output_parent)?;
+    let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+    writer.write_all(&prefix)?;
+    writer.write_all(&slots)?;
+    encrypt_with_key(
+        &mut reader,
+        input_len,
+        &mut writer,
+        &DerivedKey::from_file_key(file_key)?,
+        DEFAULT_CHUNK_SIZE,
+        None,
+        false,
+        None,
+    )?;
+
+    writer.flush()?;
+    drop(writer);
+
+    if let Err(err) = persist_tempfile(temp_file, output) {
+        let _ = fs::remove_file(err.file.path());
+        return Err(CryptoError::Io(err.error));
+    }
+
+    Ok(())
+}
+
+/// Decrypt a multi-recipient file with one recipient's credentials
+///
+/// Every slot of the matching kind is tried; the first that unwraps the file
+/// key is used.
+///
+/// # Arguments
+/// * `input` - Path to the multi-recipient file
+/// * `output` - Path for the decrypted file (replaced if it exists)
+/// * `credentials` - Password, key file, or both, as given for one recipient
+///
+/// # Returns
+/// Ok(()) on success, `InvalidPassword` if no slot opens with `credentials`,
+/// or another CryptoError on failure
+pub fn decrypt_for_recipient<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    credentials: &Recipient,
+) -> CryptoResult<()> {
+    let input_file = File::open(input.as_ref())?;
+    let file_size = input_file.metadata()?.len();
+    let mut reader = BufReader::new(input_file);
+
+    let mut prefix = [0u8; RECIPIENTS_PREFIX_LEN];
+    reader.read_exact(&mut prefix)?;
+    if prefix[..4] != RECIPIENTS_MAGIC {
+        return Err(CryptoError::FormatError(
+            "Not a multi-recipient file".to_string(),
+        ));
+    }
+    if prefix[4] != RECIPIENTS_VERSION {
+        return Err(CryptoError::FormatError(format!(
+            "Unsupported multi-recipient version: {}",
+            prefix[4]
+        )));
+    }
+    let slot_count = prefix[5] as usize;
+    if slot_count == 0 || slot_count > MAX_RECIPIENTS {
+        return Err(CryptoError::FormatError(format!(
+            "Invalid recipient count: {}",
+            slot_count
+        )));
+    }
+
+    let mut slots = vec![0u8; slot_count * SLOT_SIZE];
+    reader.read_exact(&mut slots)?;
+
+    let material = credentials.key_material()?;
+    let mut file_key = None;
+    for (index, slot) in slots.chunks_exact(SLOT_SIZE).enumerate() {
+        if slot[0] != credentials.kind() {
+            continue;
+        }
+        if let Some(key) = unwrap_slot(&prefix, index, slot, &material)? {
+            file_key = Some(key);
+            break;
+        }
+    }
+    let file_key = file_key.ok_or(CryptoError::InvalidPassword)?;
+
+    let stream_len = file_size - (RECIPIENTS_PREFIX_LEN + slots.len()) as u64;
+    let header = read_stream_header(&mut reader)?;
+    check_stream_length(&header, stream_len)?;
+
+    let output = output.as_ref();
+    let _lock = OutputLock::acquire(output)?;
+    let output_parent = output.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp_file = create_shredding_tempfile(output_parent)?;
+    let mut writer = BufWriter::new(temp_file.as_file_mut());
+
+    decrypt_with_key(
+        &mut reader,
+        stream_len,
+        &mut writer,
+        &header,
+        None,
+        &file_key,
+        None,
+        false,
+    )?;
+
+    writer.flush()?;
+    drop(writer);
+
+    temp_file.persist(output)?;
+
+    Ok(())
+}
+
+/// Unencrypted prefix, also the start of every slot's AAD
+fn prefix_bytes(slot_count: u8) -> [u8; RECIPIENTS_PREFIX_LEN] {
+    let mut prefix = [0u8; RECIPIENTS_PREFIX_LEN];
+    prefix[..4].copy_from_slice(&RECIPIENTS_MAGIC);
+    prefix[4] = RECIPIENTS_VERSION;
+    prefix[5] = slot_count;
+    prefix
+}
+
+/// AAD binding a wrapped key to its position and kind
+fn slot_aad(prefix: &[u8], index: usize, kind: u8) -> Vec<u8> {
+    let mut aad = prefix.to_vec();
+    aad.push(index as u8);
+    aad.push(kind);
+    aad
+}
+
+/// Derive the wrapping key for a slot from raw key material
+fn slot_key(material: &SecureBytes, salt: &[u8], params: &KdfParams) -> CryptoResult<Aes256Gcm> {
+    let key = derive_key_with_material(material.as_slice(), salt, params)?;
+    Aes256Gcm::new_from_slice(key.as_slice()).map_err(|_| CryptoError::EncryptionFailed)
+}
+
+/// Build the slot for `recipient` wrapping `file_key`
+fn wrap_slot(
+    prefix: &[u8],
+    index: usize,
+    recipient: &Recipient,
+    file_key: &SecureBytes,
+) -> CryptoResult<[u8; SLOT_SIZE]> {
+    let params = KdfParams {
+        salt_length: SLOT_SALT_SIZE as u32,
+        ..KdfParams::default()
+    };
+    let salt = generate_salt_with_len(SLOT_SALT_SIZE)?;
+    let mut nonce = [0u8; WRAP_NONCE_SIZE];
+    SysRng
+        .try_fill_bytes(&mut nonce)
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+
+    let kind = recipient.kind();
+    let cipher = slot_key(&recipient.key_material()?, &salt, &params)?;
+    let wrapped = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: file_key.as_slice(),
+                aad: &slot_aad(prefix, index, kind),
+            },
+        )
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+
+    let mut slot = [0u8; SLOT_SIZE];
+    slot[0] = kind;
+    slot[1] = params.algorithm.to_u8();
+    slot[2..6].copy_from_slice(&params.memory_cost_kib.to_le_bytes());
+    slot[6..10].copy_from_slice(&params.time_cost.to_le_bytes());
+    slot[10..14].copy_from_slice(&params.parallelism.to_le_bytes());
+    let salt_end = 14 + SLOT_SALT_SIZE;
+    slot[14..salt_end].copy_from_slice(&salt);
+    let nonce_end = salt_end + WRAP_NONCE_SIZE;
+    slot[salt_end..nonce_end].copy_from_slice(&nonce);
+    slot[nonce_end..].copy_from_slice(&wrapped);
+    Ok(slot)
+}
+
+/// Try to unwrap the file key from `slot`; `None` if the credentials don't match
+fn unwrap_slot(
+    prefix: &[u8],
+    index: usize,
+    slot: &[u8],
+    material: &SecureBytes,
+) -> CryptoResult<Option<SecureBytes>> {
+    let read_u32 = |start: usize| u32::from_le_bytes(slot[start..start + 4].try_into().unwrap());
+    let params = KdfParams {
+        algorithm: KdfAlgorithm::from_u8(slot[1])?,
+        memory_cost_kib: read_u32(2),
+        time_cost: read_u32(6),
+        parallelism: read_u32(10),
+        key_length: FILE_KEY_SIZE as u32,
+        salt_length: SLOT_SALT_SIZE as u32,
+    };
+    // Slot parameters are untrusted: reject values that would be weak or costly
+    params.validate()?;
+
+    let salt_end = 14 + SLOT_SALT_SIZE;
+    let nonce_end = salt_end + WRAP_NONCE_SIZE;
+    let cipher = slot_key(material, &slot[14..salt_end], &params)?;
+    let unwrapped = cipher.decrypt(
+        Nonce::from_slice(&slot[salt_end..nonce_end]),
+        Payload {
+            msg: &slot[nonce_end..],
+            aad: &slot_aad(prefix, index, slot[0]),
+        },
+    );
+    Ok(unwrapped.ok().map(SecureBytes::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::keyfile::generate_key_file;
+    use tempfile::tempdir;
+
+    fn password(value: &str) -> Password {
+        Password::new(value.to_string())
+    }
+
+    #[test]
+    fn test_password_and_key_file_recipients_open_same_file() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("team.txt");
+        let encrypted = dir.path().join("team.fcmr");
+        let key_file = dir.path().join("bob.key");
+        let content = b"shared team notes ".repeat(100);
+        fs::write(&input, &content).unwrap();
+        generate_key_file(&key_file).unwrap();
+
+        let recipients = [
+            Recipient::Password(password("alice-password")),
+            Recipient::KeyFile(key_file.clone()),
+        ];
+        encrypt_for_recipients(&input, &encrypted, &recipients).unwrap();
+
+        let alice_out = dir.path().join("alice.txt");
+        decrypt_for_recipient(
+            &encrypted,
+            &alice_out,
+            &Recipient::Password(password("alice-password")),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&alice_out).unwrap(), content);
+
+        let bob_out = dir.path().join("bob.txt");
+        decrypt_for_recipient(&encrypted, &bob_out, &Recipient::KeyFile(key_file)).unwrap();
+        assert_eq!(fs::read(&bob_out).unwrap(), content);
+    }
+
+    #[test]
+    fn test_both_recipient_requires_password_and_key_file() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("plan.txt");
+        let encrypted = dir.path().join("plan.fcmr");
+        let output = dir.path().join("plan.out");
+        let key_file = dir.path().join("carol.key");
+        fs::write(&input, b"quarterly plan").unwrap();
+        generate_key_file(&key_file).unwrap();
+
+        let recipients = [Recipient::Both {
+            password: password("carol-password"),
+            key_file: key_file.clone(),
+        }];
+        encrypt_for_recipients(&input, &encrypted, &recipients).unwrap();
+
+        // Either factor alone matches no slot
+        let result = decrypt_for_recipient(
+            &encrypted,
+            &output,
+            &Recipient::Password(password("carol-password")),
+        );
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+        let result =
+            decrypt_for_recipient(&encrypted, &output, &Recipient::KeyFile(key_file.clone()));
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+        assert!(!output.exists());
+
+        decrypt_for_recipient(
+            &encrypted,
+            &output,
+            &Recipient::Both {
+                password: password("carol-password"),
+                key_file,
+            },
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"quarterly plan");
+    }
+
+    #[test]
+    fn test_wrong_password_matches_no_slot() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("secret.txt");
+        let encrypted = dir.path().join("secret.fcmr");
+        fs::write(&input, b"secret").unwrap();
+
+        let recipients = [Recipient::Password(password("right-password"))];
+        encrypt_for_recipients(&input, &encrypted, &recipients).unwrap();
+
+        let result = decrypt_for_recipient(
+            &encrypted,
+            dir.path().join("out.txt"),
+            &Recipient::Password(password("wrong-password")),
+        );
+        assert!(matches!(result, Err(CryptoError::InvalidPassword)));
+    }
+}
@@ -9,8 +9,15 @@
 // - Prevents accidental logging or display of sensitive data
 // - Type safety ensures passwords/keys are handled correctly
 
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::Path;
+
+use unicode_normalization::UnicodeNormalization;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::error::{CryptoError, CryptoResult};
+
 /// Secure wrapper for byte arrays (derived keys and other sensitive cryptographic material)
 ///
 /// This wrapper automatically zeros the contained bytes when dropped,
@@ -53,6 +60,22 @@ impl SecureBytes {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Split into `[0, mid)` and `[mid, len)`, consuming self
+    ///
+    /// Both halves zeroize on drop, so derived key material can be divided into
+    /// separate keys without an unprotected intermediate copy.
+    ///
+    /// # Panics
+    /// Panics if `mid > len`, like `slice::split_at`.
+    pub fn split_at(mut self, mid: usize) -> (SecureBytes, SecureBytes) {
+        assert!(mid <= self.len(), "split_at: mid > len");
+        let mut head = std::mem::take(&mut self.0);
+        let tail = head.split_off(mid);
+        // head keeps its full allocation; Zeroize clears the spare capacity
+        // holding the moved tail bytes when it is dropped
+        (Self(head), Self(tail))
+    }
 }
 
 // Implement Debug to prevent accidental logging of sensitive data
@@ -97,6 +120,23 @@ impl Password {
         Self(password)
     }
 
+    /// Take over the bytes in `bytes` as a password, without a `String` copy
+    ///
+    /// The buffer is reused in place, so input read straight into a
+    /// `SecureBytes` (files, pipes, terminals) never exists as an unzeroized
+    /// `String`. Fails, zeroizing the bytes, if they are not valid UTF-8.
+    pub fn from_secure_bytes(mut bytes: SecureBytes) -> CryptoResult<Password> {
+        match String::from_utf8(std::mem::take(&mut bytes.0)) {
+            Ok(password) => Ok(Password(password)),
+            Err(err) => {
+                err.into_bytes().zeroize();
+                Err(CryptoError::FormatError(
+                    "Password is not valid UTF-8".to_string(),
+                ))
+            }
+        }
+    }
+
     /// Get the password as bytes for cryptographic operations
     ///
     /// This is the primary way to access the password data for
@@ -108,7 +148,6 @@ impl Password {
     /// Get the password as a string slice
     ///
     /// Use sparingly - prefer `as_bytes()` for crypto operations
-    #[cfg(test)]
     pub fn as_str(&self) -> &str {
         &self.0
     }
@@ -123,6 +162,94 @@ impl Password {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    /// Copy of the password with trailing whitespace removed
+    ///
+    /// Used by the opt-in password trimming, so a stray space from a
+    /// mobile keyboard does not lock the user out of a file.
+    pub fn trim_trailing_whitespace(&self) -> Password {
+        Password(self.0.trim_end().to_string())
+    }
+
+    /// Copy of the password in Unicode Normalization Form C
+    ///
+    /// Used by the opt-in password normalization, so a password typed with
+    /// decomposed characters (e.g. "e" followed by a combining accent) opens
+    /// a file encrypted with the precomposed form, and the other way round.
+    pub fn nfc_normalized(&self) -> Password {
+        Password(self.0.nfc().collect())
+    }
+
+    /// Read one line from `reader` as a password, without the line ending
+    ///
+    /// Meant for a no-echo terminal reader in a command-line front end. The
+    /// line is read into a `SecureBytes` buffer that the `Password` then takes
+    /// over, so no unzeroized copy is left behind; on error the buffer is
+    /// zeroed.
+    pub fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<Password> {
+        let mut line = SecureBytes::new(Vec::new());
+        if reader.read_until(b'\n', &mut line.0)? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "No password entered",
+            ));
+        }
+        let len = line
+            .0
+            .iter()
+            .rposition(|&b| b != b'\n' && b != b'\r')
+            .map_or(0, |i| i + 1);
+        line.0[len..].zeroize();
+        line.0.truncate(len);
+        Password::from_secure_bytes(line)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    }
+
+    /// Read a password stored in a file, as written by `echo secret > file`
+    ///
+    /// Exactly one trailing line ending is stripped: `\r\n` or `\n`, which
+    /// matches `openssl -pass file:` and `gpg --passphrase-file` for the usual
+    /// single-line file. Anything else is part of the password, including a
+    /// lone `\r`, trailing spaces, or a second newline. The contents are read straight into a `SecureBytes`
+    /// buffer and stripped bytes are zeroed.
+    ///
+    /// # Errors
+    /// Returns `Io` if the file cannot be read and `FormatError` if it is not
+    /// valid UTF-8
+    pub fn from_file<P: AsRef<Path>>(path: P) -> CryptoResult<Password> {
+        let mut file = File::open(path)?;
+        // Sized up front so the buffer is never reallocated, which would
+        // leave an unzeroized copy of the password behind
+        let capacity = file.metadata()?.len() as usize + 1;
+        let mut contents = SecureBytes::new(Vec::with_capacity(capacity));
+        file.read_to_end(&mut contents.0)?;
+
+        let len = contents.0.len();
+        let stripped = if contents.0.ends_with(b"\r\n") {
+            2
+        } else if contents.0.ends_with(b"\n") {
+            1
+        } else {
+            0
+        };
+        contents.0[len - stripped..].zeroize();
+        contents.0.truncate(len - stripped);
+        Password::from_secure_bytes(contents)
+    }
+
+    /// Read a new password twice from `reader`, requiring both entries to match
+    ///
+    /// Used when encrypting, where a typo would lock the user out of the file.
+    pub fn read_confirmed<R: BufRead>(reader: &mut R) -> CryptoResult<Password> {
+        let password = Password::read_line(reader)?;
+        let confirmation = Password::read_line(reader)?;
+        if password.as_bytes() != confirmation.as_bytes() {
+            return Err(CryptoError::FormatError(
+                "Passwords do not match".to_string(),
+            ));
+        }
+        Ok(password)
+    }
 }
 
 // Implement Debug to prevent accidental logging of passwords
@@ -164,6 +291,25 @@ mod tests {
         assert!(debug_output.contains("3 bytes"));
     }
 
+    #[test]
+    fn test_secure_bytes_split_at() {
+        let secure = SecureBytes::new((0u8..64).collect());
+        let (head, tail) = secure.split_at(32);
+
+        assert_eq!(head.as_slice(), (0u8..32).collect::<Vec<_>>().as_slice());
+        assert_eq!(tail.as_slice(), (32u8..64).collect::<Vec<_>>().as_slice());
+
+        let (empty, all) = SecureBytes::new(vec![1, 2]).split_at(0);
+        assert!(empty.is_empty());
+        assert_eq!(all.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "mid > len")]
+    fn test_secure_bytes_split_at_out_of_range() {
+        let _ = SecureBytes::new(vec![1, 2, 3]).split_at(4);
+    }
+
     #[test]
     fn test_password_creation() {
         let password = Password::new("test_password".to_string());
@@ -184,6 +330,128 @@ mod tests {
         assert!(!debug_output.contains("secret123"));
     }
 
+    #[test]
+    fn test_password_nfc_normalized() {
+        let decomposed = Password::new("cafe\u{301}".to_string());
+        assert_eq!(decomposed.nfc_normalized().as_str(), "caf\u{e9}");
+        assert_eq!(
+            Password::new("caf\u{e9}".to_string())
+                .nfc_normalized()
+                .as_str(),
+            "caf\u{e9}"
+        );
+    }
+
+    #[test]
+    fn test_password_trim_trailing_whitespace() {
+        let password = Password::new("  correct horse \t\u{00A0}\n".to_string());
+        assert_eq!(
+            password.trim_trailing_whitespace().as_str(),
+            "  correct horse"
+        );
+        assert_eq!(
+            Password::new("plain".to_string())
+                .trim_trailing_whitespace()
+                .as_str(),
+            "plain"
+        );
+    }
+
+    #[test]
+    fn test_password_read_line_strips_line_ending() {
+        let mut input = std::io::Cursor::new(b"hunter2 \r\nsecond\n".to_vec());
+        assert_eq!(
+            Password::read_line(&mut input).unwrap().as_str(),
+            "hunter2 "
+        );
+        assert_eq!(Password::read_line(&mut input).unwrap().as_str(), "second");
+
+        let err = Password::read_line(&mut input).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_password_read_line_zeroizes_line_ending() {
+        let mut input = std::io::Cursor::new(b"secret\r\n".to_vec());
+        let password = Password::read_line(&mut input).unwrap();
+        assert_eq!(password.as_str(), "secret");
+
+        // Stripped bytes stay in the allocation but must already be zeroed
+        let spare = password.0.capacity() - password.0.len();
+        assert!(spare >= 2);
+        // SAFETY: the two bytes past len are within capacity and were
+        // initialized by read_line before the string was truncated
+        let tail =
+            unsafe { std::slice::from_raw_parts(password.0.as_ptr().add(password.0.len()), 2) };
+        assert_eq!(tail, &[0, 0]);
+    }
+
+    #[test]
+    fn test_password_from_file_strips_one_line_ending() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("password.txt");
+        let cases: [(&[u8], &str); 7] = [
+            (b"hunter2\n", "hunter2"),
+            (b"hunter2\r\n", "hunter2"),
+            (b"hunter2", "hunter2"),
+            (b"hunter2\n\n", "hunter2\n"),
+            (b"hunter2\r", "hunter2\r"),
+            (b"hunter2 \n", "hunter2 "),
+            (b"\n", ""),
+        ];
+        for (contents, expected) in cases {
+            std::fs::write(&path, contents).unwrap();
+            assert_eq!(
+                Password::from_file(&path).unwrap().as_bytes(),
+                expected.as_bytes()
+            );
+        }
+
+        std::fs::write(&path, [0xff, b'\n']).unwrap();
+        assert!(Password::from_file(&path).is_err());
+        assert!(Password::from_file(temp_dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_password_read_confirmed() {
+        let mut input = std::io::Cursor::new(b"same\nsame\n".to_vec());
+        assert_eq!(
+            Password::read_confirmed(&mut input).unwrap().as_str(),
+            "same"
+        );
+
+        let mut input = std::io::Cursor::new(b"first\nsecond\n".to_vec());
+        assert!(matches!(
+            Password::read_confirmed(&mut input),
+            Err(CryptoError::FormatError(_))
+        ));
+    }
+
+    #[test]
+    fn test_password_from_secure_bytes() {
+        use crate::crypto::kdf::{derive_key_with_params, KdfParams};
+
+        let password =
+            Password::from_secure_bytes(SecureBytes::new(b"from bytes".to_vec())).unwrap();
+        assert_eq!(password.as_str(), "from bytes");
+
+        let params = KdfParams::default();
+        let salt = [7u8; 16];
+        let from_bytes = derive_key_with_params(&password, &salt, &params).unwrap();
+        let from_string =
+            derive_key_with_params(&Password::new("from bytes".to_string()), &salt, &params)
+                .unwrap();
+        assert_eq!(from_bytes.as_slice(), from_string.as_slice());
+
+        assert!(matches!(
+            Password::from_secure_bytes(SecureBytes::new(vec![0xff, 0xfe])),
+            Err(CryptoError::FormatError(_))
+        ));
+        let mut input = std::io::Cursor::new(vec![0xff, b'\n']);
+        let err = Password::read_line(&mut input).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
     #[test]
     fn test_empty_password() {
         let password = Password::new(String::new());
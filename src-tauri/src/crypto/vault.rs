@@ -0,0 +1,210 @@
+// crypto/vault.rs - Read-Only Access to Encrypted Archives
+//
+// Opens an archive written by batch archive mode (a TAR.ZSTD stream encrypted
+// as one streaming file) so its entries can be listed and read one at a time,
+// without extracting anything to disk. This is the foundation for browsing an
+// archive like a folder.
+//
+// The archive has no index region: TAR entries live inside a single ZSTD
+// stream, which cannot be seeked. Opening the handle therefore decrypts the
+// stream once to build the entry list (which also authenticates every chunk),
+// and each `read_entry` decrypts from the start up to the requested entry,
+// copying out only that entry's bytes. The key is derived once and kept for
+// the lifetime of the handle.
+//
+// Only regular files with relative, traversal-free paths are exposed;
+// directories, links, and unsafe paths are left out, as extraction would.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use tar::{Archive, EntryType};
+
+use crate::crypto::secure::{Password, SecureBytes};
+use crate::crypto::streaming::{derive_decryption_key, read_stream_header, DecryptingReader};
+use crate::error::{CryptoError, CryptoResult};
+
+/// A regular file inside an encrypted archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultEntry {
+    /// Entry path as recorded in the archive
+    pub name: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Open encrypted archive with its key derived, for listing and reading entries
+pub struct ArchiveHandle {
+    path: PathBuf,
+    key: SecureBytes,
+    entries: Vec<VaultEntry>,
+}
+
+/// Open an encrypted archive for read-only access
+///
+/// Derives the key and decrypts the archive once to list its entries, so a
+/// wrong password, missing key file, or tampered archive fails here.
+///
+/// # Arguments
+/// * `encrypted_archive` - Archive written by batch archive mode
+/// * `password` - Password the archive was encrypted with
+/// * `key_file` - Key file, if the archive was encrypted with one
+pub fn open_archive_handle<P: AsRef<Path>>(
+    encrypted_archive: P,
+    password: &Password,
+    key_file: Option<&Path>,
+) -> CryptoResult<ArchiveHandle> {
+    let path = encrypted_archive.as_ref().to_path_buf();
+    let mut reader = BufReader::new(File::open(&path)?);
+    let header = read_stream_header(&mut reader)?;
+    if header.key_file_required() && key_file.is_none() {
+        return Err(CryptoError::KeyFileRequired);
+    }
+    let key = derive_decryption_key(&header, password, key_file)?;
+
+    let mut archive = Archive::new(zstd::Decoder::new(DecryptingReader::with_key(
+        reader, header, &key,
+    )?)?);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if let Some(name) = exposed_name(&entry)? {
+            entries.push(VaultEntry {
+                name,
+                size: entry.size(),
+            });
+        }
+    }
+    // Read past the TAR end marker so the stream end (and any integrity
+    // trailer) is verified too
+    std::io::copy(&mut archive.into_inner(), &mut std::io::sink())?;
+
+    Ok(ArchiveHandle { path, key, entries })
+}
+
+impl ArchiveHandle {
+    /// Regular files in the archive, in archive order
+    pub fn list(&self) -> &[VaultEntry] {
+        &self.entries
+    }
+
+    /// Decrypt and decompress one entry's contents
+    ///
+    /// # Errors
+    /// Returns `ArchiveError` if `name` is not a listed entry, or a
+    /// decryption error if the archive changed since it was opened
+    pub fn read_entry(&self, name: &str) -> CryptoResult<Vec<u8>> {
+        if !self.entries.iter().any(|entry| entry.name == name) {
+            return Err(CryptoError::ArchiveError(format!(
+                "No such entry in archive: {}",
+                name
+            )));
+        }
+
+        let mut archive = Archive::new(zstd::Decoder::new(self.open_stream()?)?);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if exposed_name(&entry)?.as_deref() == Some(name) {
+                let mut contents = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut contents)?;
+                return Ok(contents);
+            }
+        }
+        Err(CryptoError::ArchiveError(format!(
+            "Entry disappeared from archive: {}",
+            name
+        )))
+    }
+
+    /// Reopen the archive and decrypt it from the first chunk
+    fn open_stream(&self) -> CryptoResult<DecryptingReader<BufReader<File>>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let header = read_stream_header(&mut reader)?;
+        DecryptingReader::with_key(reader, header, &self.key)
+    }
+}
+
+/// Name of an entry the handle exposes, or None for anything extraction
+/// would skip or reject
+fn exposed_name<R: Read>(entry: &tar::Entry<R>) -> CryptoResult<Option<String>> {
+    if !matches!(
+        entry.header().entry_type(),
+        EntryType::Regular | EntryType::Continuous
+    ) {
+        return Ok(None);
+    }
+    let path = entry.path()?;
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::archive::create_tar_zstd_archive;
+    use crate::crypto::{encrypt_file_streaming_with, EncryptOptions};
+    use std::fs;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tempfile::tempdir;
+
+    fn test_password() -> Password {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let now_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        Password::new(format!("{now_nanos:x}{counter:x}"))
+    }
+
+    #[test]
+    fn test_archive_handle_reads_entry_by_name() {
+        let temp_dir = tempdir().unwrap();
+        let files_dir = temp_dir.path().join("files");
+        fs::create_dir(&files_dir).unwrap();
+        let mut inputs = Vec::new();
+        for (name, contents) in [
+            ("a.txt", b"first".to_vec()),
+            ("b.bin", vec![9u8; 5000]),
+            ("c.md", b"# third".to_vec()),
+        ] {
+            let path = files_dir.join(name);
+            fs::write(&path, contents).unwrap();
+            inputs.push(path);
+        }
+        let archive_path =
+            create_tar_zstd_archive(&inputs, temp_dir.path().join("files.tar.zst"), None).unwrap();
+        let encrypted_path = temp_dir.path().join("files.tar.zst.encrypted");
+        let password = test_password();
+        encrypt_file_streaming_with(
+            &archive_path,
+            &encrypted_path,
+            &password,
+            EncryptOptions::new().chunk_size(1024),
+        )
+        .unwrap();
+
+        let handle = open_archive_handle(&encrypted_path, &password, None).unwrap();
+        let names: Vec<&str> = handle.list().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "b.bin", "c.md"]);
+        assert_eq!(handle.list()[1].size, 5000);
+
+        assert_eq!(handle.read_entry("b.bin").unwrap(), vec![9u8; 5000]);
+        assert_eq!(handle.read_entry("a.txt").unwrap(), b"first");
+        assert!(matches!(
+            handle.read_entry("missing.txt"),
+            Err(CryptoError::ArchiveError(_))
+        ));
+
+        assert!(open_archive_handle(&encrypted_path, &test_password(), None).is_err());
+    }
+}
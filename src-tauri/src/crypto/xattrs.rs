@@ -0,0 +1,233 @@
+// crypto/xattrs.rs - Extended Attribute Preservation
+//
+// With the `xattrs` feature, metadata preservation also records the input's
+// extended attributes (e.g. macOS Finder tags or `user.*` attributes on Linux)
+// in the Version 9 header and restores them on decryption.
+//
+// Blob layout (stored in the header as [XATTRS_LEN:4][XATTRS:N]):
+// [COUNT:2] then COUNT times [NAME_LEN:2][NAME:UTF-8][VALUE_LEN:4][VALUE]
+//
+// Like the recorded file mode, the blob is stored in the clear and
+// authenticated as AAD, so attribute names and values are visible to anyone
+// holding the encrypted file. Capture and restore are best effort: platforms
+// or filesystems without extended attributes simply record nothing.
+
+use std::path::Path;
+
+use crate::error::{CryptoError, CryptoResult};
+
+/// Maximum size of the encoded attribute blob; larger attributes are skipped
+pub const MAX_XATTRS_SIZE: usize = 64 * 1024;
+
+/// Attribute count (2)
+const COUNT_SIZE: usize = 2;
+
+/// Name length (2) + value length (4)
+const ENTRY_FIELDS_SIZE: usize = 2 + 4;
+
+/// One extended attribute of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedAttribute {
+    /// Attribute name, including its namespace (e.g. `user.comment`)
+    pub name: String,
+    /// Raw attribute value
+    pub value: Vec<u8>,
+}
+
+impl ExtendedAttribute {
+    fn encoded_len(&self) -> usize {
+        ENTRY_FIELDS_SIZE + self.name.len() + self.value.len()
+    }
+}
+
+/// Encode attributes into the header blob
+///
+/// # Errors
+/// Returns `FormatError` if the blob would exceed `MAX_XATTRS_SIZE`
+pub fn encode(attrs: &[ExtendedAttribute]) -> CryptoResult<Vec<u8>> {
+    let len = COUNT_SIZE
+        + attrs
+            .iter()
+            .map(ExtendedAttribute::encoded_len)
+            .sum::<usize>();
+    if len > MAX_XATTRS_SIZE {
+        return Err(CryptoError::FormatError(format!(
+            "Extended attributes too large: {} bytes (max {})",
+            len, MAX_XATTRS_SIZE
+        )));
+    }
+
+    let mut blob = Vec::with_capacity(len);
+    blob.extend_from_slice(&(attrs.len() as u16).to_le_bytes());
+    for attr in attrs {
+        blob.extend_from_slice(&(attr.name.len() as u16).to_le_bytes());
+        blob.extend_from_slice(attr.name.as_bytes());
+        blob.extend_from_slice(&(attr.value.len() as u32).to_le_bytes());
+        blob.extend_from_slice(&attr.value);
+    }
+    Ok(blob)
+}
+
+/// Decode a header blob written by [`encode`]
+///
+/// # Errors
+/// Returns `FormatError` for oversized, truncated, or trailing data, empty or
+/// non-UTF-8 names
+pub fn decode(blob: &[u8]) -> CryptoResult<Vec<ExtendedAttribute>> {
+    let malformed = || CryptoError::FormatError("Malformed extended attributes".to_string());
+    if blob.len() > MAX_XATTRS_SIZE {
+        return Err(CryptoError::FormatError(format!(
+            "Extended attributes too large: {} bytes (max {})",
+            blob.len(),
+            MAX_XATTRS_SIZE
+        )));
+    }
+
+    let mut rest = blob;
+    let count = u16::from_le_bytes(take_array(&mut rest).ok_or_else(malformed)?);
+    let mut attrs = Vec::new();
+    for _ in 0..count {
+        let name_len = u16::from_le_bytes(take_array(&mut rest).ok_or_else(malformed)?);
+        let name = take(&mut rest, name_len as usize).ok_or_else(malformed)?;
+        let name = std::str::from_utf8(name).map_err(|_| malformed())?;
+        if name.is_empty() {
+            return Err(malformed());
+        }
+        let name = name.to_string();
+        let value_len = u32::from_le_bytes(take_array(&mut rest).ok_or_else(malformed)?);
+        let value = take(&mut rest, value_len as usize)
+            .ok_or_else(malformed)?
+            .to_vec();
+        attrs.push(ExtendedAttribute { name, value });
+    }
+    if !rest.is_empty() {
+        return Err(malformed());
+    }
+    Ok(attrs)
+}
+
+/// Split `n` bytes off the front of `rest`, or None if it is too short
+fn take<'a>(rest: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if rest.len() < n {
+        return None;
+    }
+    let (head, tail) = rest.split_at(n);
+    *rest = tail;
+    Some(head)
+}
+
+/// [`take`] for a fixed-size little-endian field
+fn take_array<const N: usize>(rest: &mut &[u8]) -> Option<[u8; N]> {
+    take(rest, N)?.try_into().ok()
+}
+
+/// Read the extended attributes of `path`
+///
+/// Names that are not UTF-8 and attributes that would push the blob past
+/// `MAX_XATTRS_SIZE` are skipped with a warning. Returns an empty list when
+/// the platform or filesystem has no extended attributes.
+#[cfg(all(unix, feature = "xattrs"))]
+pub fn capture(path: &Path) -> CryptoResult<Vec<ExtendedAttribute>> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(Vec::new());
+    }
+    let names = match xattr::list(path) {
+        Ok(names) => names,
+        Err(err) if err.kind() == std::io::ErrorKind::Unsupported => return Ok(Vec::new()),
+        Err(err) => return Err(CryptoError::Io(err)),
+    };
+
+    let mut attrs = Vec::new();
+    let mut len = COUNT_SIZE;
+    for name in names {
+        let Some(name) = name.to_str().map(str::to_string) else {
+            log::warn!("Skipping extended attribute with non-UTF-8 name");
+            continue;
+        };
+        // The attribute may vanish between list and get
+        let Some(value) = xattr::get(path, &name)? else {
+            continue;
+        };
+        let attr = ExtendedAttribute { name, value };
+        if len + attr.encoded_len() > MAX_XATTRS_SIZE {
+            log::warn!("Skipping extended attribute {}: too large", attr.name);
+            continue;
+        }
+        len += attr.encoded_len();
+        attrs.push(attr);
+    }
+    Ok(attrs)
+}
+
+/// Extended attributes are not recorded without the `xattrs` feature on Unix
+#[cfg(not(all(unix, feature = "xattrs")))]
+pub fn capture(_path: &Path) -> CryptoResult<Vec<ExtendedAttribute>> {
+    Ok(Vec::new())
+}
+
+/// Set recorded extended attributes on `path`
+///
+/// Best effort: attributes the filesystem or current user cannot set (e.g.
+/// `security.*` without privileges) are skipped with a warning, so a restore
+/// never fails the decryption that preceded it.
+#[cfg(all(unix, feature = "xattrs"))]
+pub fn restore(path: &Path, attrs: &[ExtendedAttribute]) {
+    if !xattr::SUPPORTED_PLATFORM {
+        return;
+    }
+    for attr in attrs {
+        if let Err(err) = xattr::set(path, &attr.name, &attr.value) {
+            log::warn!(
+                "Failed to restore extended attribute {}: {}",
+                attr.name,
+                err
+            );
+        }
+    }
+}
+
+/// Extended attributes cannot be restored without the `xattrs` feature on Unix
+#[cfg(not(all(unix, feature = "xattrs")))]
+pub fn restore(_path: &Path, _attrs: &[ExtendedAttribute]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attr(name: &str, value: &[u8]) -> ExtendedAttribute {
+        ExtendedAttribute {
+            name: name.to_string(),
+            value: value.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_xattrs_encode_decode_roundtrip() {
+        let attrs = vec![attr("user.comment", b"hello"), attr("user.empty", b"")];
+        let blob = encode(&attrs).unwrap();
+        assert_eq!(decode(&blob).unwrap(), attrs);
+
+        assert_eq!(decode(&encode(&[]).unwrap()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_xattrs_decode_rejects_malformed() {
+        let blob = encode(&[attr("user.comment", b"hello")]).unwrap();
+
+        assert!(decode(&blob[..blob.len() - 1]).is_err());
+        let mut trailing = blob.clone();
+        trailing.push(0);
+        assert!(decode(&trailing).is_err());
+        assert!(decode(&[]).is_err());
+
+        // One attribute with an empty name
+        assert!(decode(&[1, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_xattrs_size_limit() {
+        let big = attr("user.big", &vec![0u8; MAX_XATTRS_SIZE]);
+        assert!(encode(&[big]).is_err());
+        assert!(decode(&vec![0u8; MAX_XATTRS_SIZE + 1]).is_err());
+    }
+}
@@ -4,6 +4,8 @@
 // Using thiserror makes error definitions clean and implements std::error::Error automatically.
 // All errors are serializable so they can be sent to the frontend via Tauri IPC.
 
+use std::path::Path;
+
 use thiserror::Error;
 
 /// Main error type for all cryptographic operations
@@ -52,11 +54,69 @@ pub enum CryptoError {
     /// Key file operation error
     #[error("Key file error: {0}")]
     KeyFileError(String),
+
+    /// Encrypted file is shorter than the length recorded in its trailer
+    #[error("TruncatedFile: {0}")]
+    TruncatedFile(String),
+
+    /// Output file exists but is marked read-only, so it cannot be overwritten
+    #[error("ReadOnlyTarget: Target file is read-only: {0}")]
+    ReadOnlyTarget(String),
+
+    /// OS keychain operation error
+    #[error("KeychainError: {0}")]
+    KeychainError(String),
+
+    /// Another operation is currently writing the same output path
+    #[error("OutputLocked: Output is being written by another operation: {0}")]
+    OutputLocked(String),
+
+    /// Operation was cancelled by the user
+    #[error("Cancelled: Operation cancelled")]
+    Cancelled,
+
+    /// Input has no FileCrypter header (e.g. a plaintext file picked for decryption)
+    #[error("NotEncrypted: This file does not appear to be encrypted by FileCrypter.")]
+    NotEncrypted,
+
+    /// Output filesystem does not have room for the result
+    #[error("OutOfSpace: Not enough free disk space: {0}")]
+    OutOfSpace(String),
+
+    /// Key file differs from the one recorded when the file was encrypted
+    #[error("KeyFileMismatch: This is not the key file used to encrypt the file.")]
+    KeyFileMismatch,
+
+    /// I/O error on a known file, e.g. "Permission denied (os error 13): /path/to/file"
+    #[error("{source}: {path}")]
+    IoPath {
+        path: String,
+        source: std::io::Error,
+    },
 }
 
 /// Result type alias for crypto operations
 pub type CryptoResult<T> = Result<T, CryptoError>;
 
+/// Attach the offending path to an I/O error
+///
+/// A bare `io::Error` only says what went wrong ("Permission denied"), not
+/// which file it happened to. Use this instead of `map_err(CryptoError::Io)`
+/// wherever the path is known.
+pub trait IoPathExt<T> {
+    /// Convert the error to `CryptoError::IoPath` naming `path`
+    fn with_path(self, path: impl AsRef<Path>) -> CryptoResult<T>;
+}
+
+impl<T> IoPathExt<T> for std::io::Result<T> {
+    fn with_path(self, path: impl AsRef<Path>) -> CryptoResult<T> {
+        self.map_err(|source| CryptoError::IoPath {
+            path: path.as_ref().display().to_string(),
+            source,
+        })
+    }
+}
+
 #[cfg(windows)]
 impl From<crate::security::DaclError> for CryptoError {
     fn from(err: crate::security::DaclError) -> Self {
@@ -67,6 +127,74 @@ impl From<crate::security::DaclError> for CryptoError {
     }
 }
 
+impl CryptoError {
+    /// Stable numeric code for each variant, keyed by variant name.
+    ///
+    /// Numbers are part of the IPC contract: never renumber or reuse an entry,
+    /// only append new ones.
+    pub const CODES: &'static [(&'static str, u16)] = &[
+        ("InvalidPassword", 1),
+        ("FormatError", 2),
+        ("EncryptionFailed", 3),
+        ("DecryptionFailed", 4),
+        ("Io", 5),
+        ("TooManyFiles", 6),
+        ("InvalidPath", 7),
+        ("ArchiveError", 8),
+        ("PathTraversal", 9),
+        ("KeyFileRequired", 10),
+        ("KeyFileError", 11),
+        ("TruncatedFile", 12),
+        ("ReadOnlyTarget", 13),
+        ("KeychainError", 14),
+        ("OutputLocked", 15),
+        ("Cancelled", 16),
+        ("NotEncrypted", 17),
+        ("OutOfSpace", 18),
+        ("KeyFileMismatch", 19),
+        ("IoPath", 20),
+    ];
+
+    /// Variant name and stable numeric code, from one exhaustive match
+    ///
+    /// A new variant does not compile until it is given both; the numbers
+    /// must match [`CryptoError::CODES`].
+    fn code_entry(&self) -> (&'static str, u16) {
+        match self {
+            CryptoError::InvalidPassword => ("InvalidPassword", 1),
+            CryptoError::FormatError(_) => ("FormatError", 2),
+            CryptoError::EncryptionFailed => ("EncryptionFailed", 3),
+            CryptoError::DecryptionFailed => ("DecryptionFailed", 4),
+            CryptoError::Io(_) => ("Io", 5),
+            CryptoError::TooManyFiles(_) => ("TooManyFiles", 6),
+            CryptoError::InvalidPath(_) => ("InvalidPath", 7),
+            CryptoError::ArchiveError(_) => ("ArchiveError", 8),
+            CryptoError::PathTraversal(_) => ("PathTraversal", 9),
+            CryptoError::KeyFileRequired => ("KeyFileRequired", 10),
+            CryptoError::KeyFileError(_) => ("KeyFileError", 11),
+            CryptoError::TruncatedFile(_) => ("TruncatedFile", 12),
+            CryptoError::ReadOnlyTarget(_) => ("ReadOnlyTarget", 13),
+            CryptoError::KeychainError(_) => ("KeychainError", 14),
+            CryptoError::OutputLocked(_) => ("OutputLocked", 15),
+            CryptoError::Cancelled => ("Cancelled", 16),
+            CryptoError::NotEncrypted => ("NotEncrypted", 17),
+            CryptoError::OutOfSpace(_) => ("OutOfSpace", 18),
+            CryptoError::KeyFileMismatch => ("KeyFileMismatch", 19),
+            CryptoError::IoPath { .. } => ("IoPath", 20),
+        }
+    }
+
+    /// Variant name, used as the string error code sent to the frontend
+    pub fn code(&self) -> &'static str {
+        self.code_entry().0
+    }
+
+    /// Stable numeric code, as listed in [`CryptoError::CODES`]
+    pub fn code_num(&self) -> u16 {
+        self.code_entry().1
+    }
+}
+
 // Implement Serialize for CryptoError so it can be sent to the frontend
 // Tauri requires all command return types to be serializable
 impl serde::Serialize for CryptoError {
@@ -74,9 +202,15 @@ impl serde::Serialize for CryptoError {
     where
         S: serde::Serializer,
     {
-        // Serialize the error as a string message
-        // This ensures users see friendly error messages in the UI
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        // Serialize as { code, code_num, message } so the frontend can match on
+        // stable codes while still showing the friendly message
+        let mut state = serializer.serialize_struct("CryptoError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("code_num", &self.code_num())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
     }
 }
 
@@ -89,6 +223,174 @@ mod tests {
         let error = CryptoError::InvalidPassword;
         let json = serde_json::to_string(&error).unwrap();
         assert!(json.contains("Invalid password"));
+        assert!(json.contains("\"code\":\"InvalidPassword\""));
+        assert!(json.contains("\"code_num\":1"));
+    }
+
+    #[test]
+    fn test_code_nums_unique_and_stable() {
+        let variants = [
+            CryptoError::InvalidPassword,
+            CryptoError::FormatError(String::new()),
+            CryptoError::EncryptionFailed,
+            CryptoError::DecryptionFailed,
+            CryptoError::Io(std::io::Error::other("x")),
+            CryptoError::TooManyFiles(String::new()),
+            CryptoError::InvalidPath(String::new()),
+            CryptoError::ArchiveError(String::new()),
+            CryptoError::PathTraversal(String::new()),
+            CryptoError::KeyFileRequired,
+            CryptoError::KeyFileError(String::new()),
+            CryptoError::TruncatedFile(String::new()),
+            CryptoError::ReadOnlyTarget(String::new()),
+            CryptoError::KeychainError(String::new()),
+            CryptoError::OutputLocked(String::new()),
+            CryptoError::Cancelled,
+            CryptoError::NotEncrypted,
+            CryptoError::OutOfSpace(String::new()),
+            CryptoError::KeyFileMismatch,
+            CryptoError::IoPath {
+                path: String::new(),
+                source: std::io::Error::other("x"),
+            },
+        ];
+        assert_eq!(variants.len(), CryptoError::CODES.len());
+
+        let mut seen = std::collections::HashSet::new();
+        for variant in &variants {
+            assert!(seen.insert(variant.code_num()), "duplicate code_num");
+            assert!(CryptoError::CODES.contains(&(variant.code(), variant.code_num())));
+        }
+
+        // Pinned values: changing these breaks frontend compatibility
+        assert_eq!(CryptoError::InvalidPassword.code_num(), 1);
+        assert_eq!(CryptoError::KeyFileRequired.code_num(), 10);
+        assert_eq!(CryptoError::TruncatedFile(String::new()).code_num(), 12);
+    }
+
+    /// Position of each variant in the golden table below.
+    ///
+    /// The match is exhaustive on purpose: a new variant does not compile
+    /// until it is given a row, so IPC changes are always deliberate.
+    fn golden_row(error: &CryptoError) -> usize {
+        match error {
+            CryptoError::InvalidPassword => 0,
+            CryptoError::FormatError(_) => 1,
+            CryptoError::EncryptionFailed => 2,
+            CryptoError::DecryptionFailed => 3,
+            CryptoError::Io(_) => 4,
+            CryptoError::TooManyFiles(_) => 5,
+            CryptoError::InvalidPath(_) => 6,
+            CryptoError::ArchiveError(_) => 7,
+            CryptoError::PathTraversal(_) => 8,
+            CryptoError::KeyFileRequired => 9,
+            CryptoError::KeyFileError(_) => 10,
+            CryptoError::TruncatedFile(_) => 11,
+            CryptoError::ReadOnlyTarget(_) => 12,
+            CryptoError::KeychainError(_) => 13,
+            CryptoError::OutputLocked(_) => 14,
+            CryptoError::Cancelled => 15,
+            CryptoError::NotEncrypted => 16,
+            CryptoError::OutOfSpace(_) => 17,
+            CryptoError::KeyFileMismatch => 18,
+            CryptoError::IoPath { .. } => 19,
+        }
+    }
+
+    #[test]
+    fn test_serialized_errors_match_golden_table() {
+        // Frontend contract: code, code_num, and message for every variant
+        let golden = [
+            (
+                CryptoError::InvalidPassword,
+                r#"{"code":"InvalidPassword","code_num":1,"message":"Invalid password or corrupted file"}"#,
+            ),
+            (
+                CryptoError::FormatError("x".to_string()),
+                r#"{"code":"FormatError","code_num":2,"message":"Invalid file format: x"}"#,
+            ),
+            (
+                CryptoError::EncryptionFailed,
+                r#"{"code":"EncryptionFailed","code_num":3,"message":"Encryption failed"}"#,
+            ),
+            (
+                CryptoError::DecryptionFailed,
+                r#"{"code":"DecryptionFailed","code_num":4,"message":"Decryption failed"}"#,
+            ),
+            (
+                CryptoError::Io(std::io::Error::other("x")),
+                r#"{"code":"Io","code_num":5,"message":"File error: x"}"#,
+            ),
+            (
+                CryptoError::TooManyFiles("x".to_string()),
+                r#"{"code":"TooManyFiles","code_num":6,"message":"TooManyFiles: x"}"#,
+            ),
+            (
+                CryptoError::InvalidPath("x".to_string()),
+                r#"{"code":"InvalidPath","code_num":7,"message":"InvalidPath: x"}"#,
+            ),
+            (
+                CryptoError::ArchiveError("x".to_string()),
+                r#"{"code":"ArchiveError","code_num":8,"message":"ArchiveError: x"}"#,
+            ),
+            (
+                CryptoError::PathTraversal("x".to_string()),
+                r#"{"code":"PathTraversal","code_num":9,"message":"PathTraversal: x"}"#,
+            ),
+            (
+                CryptoError::KeyFileRequired,
+                r#"{"code":"KeyFileRequired","code_num":10,"message":"This file was encrypted with a key file. Please provide the key file to decrypt."}"#,
+            ),
+            (
+                CryptoError::KeyFileError("x".to_string()),
+                r#"{"code":"KeyFileError","code_num":11,"message":"Key file error: x"}"#,
+            ),
+            (
+                CryptoError::TruncatedFile("x".to_string()),
+                r#"{"code":"TruncatedFile","code_num":12,"message":"TruncatedFile: x"}"#,
+            ),
+            (
+                CryptoError::ReadOnlyTarget("x".to_string()),
+                r#"{"code":"ReadOnlyTarget","code_num":13,"message":"ReadOnlyTarget: Target file is read-only: x"}"#,
+            ),
+            (
+                CryptoError::KeychainError("x".to_string()),
+                r#"{"code":"KeychainError","code_num":14,"message":"KeychainError: x"}"#,
+            ),
+            (
+                CryptoError::OutputLocked("x".to_string()),
+                r#"{"code":"OutputLocked","code_num":15,"message":"OutputLocked: Output is being written by another operation: x"}"#,
+            ),
+            (
+                CryptoError::Cancelled,
+                r#"{"code":"Cancelled","code_num":16,"message":"Cancelled: Operation cancelled"}"#,
+            ),
+            (
+                CryptoError::NotEncrypted,
+                r#"{"code":"NotEncrypted","code_num":17,"message":"NotEncrypted: This file does not appear to be encrypted by FileCrypter."}"#,
+            ),
+            (
+                CryptoError::OutOfSpace("x".to_string()),
+                r#"{"code":"OutOfSpace","code_num":18,"message":"OutOfSpace: Not enough free disk space: x"}"#,
+            ),
+            (
+                CryptoError::KeyFileMismatch,
+                r#"{"code":"KeyFileMismatch","code_num":19,"message":"KeyFileMismatch: This is not the key file used to encrypt the file."}"#,
+            ),
+            (
+                CryptoError::IoPath {
+                    path: "/p".to_string(),
+                    source: std::io::Error::other("x"),
+                },
+                r#"{"code":"IoPath","code_num":20,"message":"x: /p"}"#,
+            ),
+        ];
+        assert_eq!(golden.len(), CryptoError::CODES.len());
+
+        for (row, (error, expected)) in golden.iter().enumerate() {
+            assert_eq!(golden_row(error), row, "row out of order: {}", error.code());
+            assert_eq!(serde_json::to_string(error).unwrap(), *expected);
+        }
     }
 
     #[test]
@@ -96,4 +398,16 @@ mod tests {
         let error = CryptoError::FormatError("test".to_string());
         assert_eq!(error.to_string(), "Invalid file format: test");
     }
+
+    #[test]
+    fn test_io_error_with_path_names_the_file() {
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let error = Err::<(), _>(denied).with_path("/path/to/file").unwrap_err();
+
+        assert!(matches!(error, CryptoError::IoPath { .. }));
+        let message = error.to_string();
+        assert!(message.ends_with(": /path/to/file"), "{}", message);
+        assert!(message.to_lowercase().contains("permission denied"));
+        assert!(std::error::Error::source(&error).is_some());
+    }
 }
@@ -68,10 +68,13 @@ impl ProgressEvent {
     }
 
     /// Create "deriving key" progress event
+    ///
+    /// The percentage marks the start of the KDF stage; use
+    /// [`ProgressModel`] to place the following crypto stage on the bar.
     pub fn deriving_key() -> Self {
         Self::new(
             "deriving_key",
-            20,
+            READING_PERCENT,
             "Deriving encryption key (this may take a moment)...",
         )
     }
@@ -89,3 +92,97 @@ impl ProgressEvent {
 
 /// Event name constant for crypto progress events
 pub const CRYPTO_PROGRESS_EVENT: &str = "crypto-progress";
+
+/// Share of the bar reserved for validating paths and opening the file
+const READING_PERCENT: u32 = 2;
+
+/// Highest percentage reported before the completion event
+const MAX_IN_PROGRESS_PERCENT: u32 = 99;
+
+/// Calibrated wall-clock time of one Argon2id derivation with default params
+const ESTIMATED_KDF_MILLIS: f64 = 250.0;
+
+/// Estimated streaming throughput (AES-256-GCM plus disk I/O), ~150 MB/s
+const ESTIMATED_BYTES_PER_MILLI: f64 = 150_000.0;
+
+/// Weighted progress model for a single-file operation
+///
+/// Argon2id takes roughly constant time regardless of file size, while the
+/// encrypt/decrypt stage scales with the number of bytes. A fixed KDF share
+/// makes the bar stall on small files and jump on large ones, so this model
+/// sizes the KDF span by its estimated share of the total runtime:
+///
+/// ```text
+/// 0 ── reading ── 2 ── deriving_key ── crypto_start ── encrypting ── 99 → 100
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgressModel {
+    /// Percentage at which the encrypting/decrypting stage begins
+    crypto_start: u32,
+}
+
+impl ProgressModel {
+    /// Build a model for a file of `file_size` bytes
+    pub fn for_file_size(file_size: u64) -> Self {
+        let crypto_millis = file_size as f64 / ESTIMATED_BYTES_PER_MILLI;
+        let kdf_fraction = ESTIMATED_KDF_MILLIS / (ESTIMATED_KDF_MILLIS + crypto_millis);
+        let span = (MAX_IN_PROGRESS_PERCENT - READING_PERCENT) as f64;
+        let kdf_span = (kdf_fraction * span).round() as u32;
+
+        Self {
+            crypto_start: READING_PERCENT + kdf_span,
+        }
+    }
+
+    /// Percentage of the bar allocated to key derivation
+    pub fn kdf_share(&self) -> u32 {
+        self.crypto_start - READING_PERCENT
+    }
+
+    /// Percentage at which the encrypting/decrypting stage begins
+    pub fn crypto_start(&self) -> u32 {
+        self.crypto_start
+    }
+
+    /// Map streaming progress onto the crypto stage's span of the bar
+    ///
+    /// The result is capped at 99 to leave room for the completion event.
+    pub fn crypto_percent(&self, bytes_processed: u64, total_bytes: u64) -> u32 {
+        let fraction = if total_bytes > 0 {
+            (bytes_processed as f64 / total_bytes as f64).min(1.0)
+        } else {
+            0.0
+        };
+        let span = (MAX_IN_PROGRESS_PERCENT - self.crypto_start) as f64;
+
+        (self.crypto_start + (fraction * span) as u32).min(MAX_IN_PROGRESS_PERCENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_files_allocate_more_to_kdf() {
+        let small = ProgressModel::for_file_size(4 * 1024);
+        let large = ProgressModel::for_file_size(4 * 1024 * 1024 * 1024);
+
+        assert!(small.kdf_share() > large.kdf_share());
+        // A tiny file is dominated by key derivation
+        assert!(small.kdf_share() > 90);
+        // A multi-gigabyte file barely registers the KDF
+        assert!(large.kdf_share() < 5);
+    }
+
+    #[test]
+    fn test_crypto_percent_spans_remaining_bar() {
+        let model = ProgressModel::for_file_size(64 * 1024 * 1024);
+
+        assert_eq!(model.crypto_percent(0, 100), model.crypto_start());
+        assert_eq!(model.crypto_percent(100, 100), 99);
+        assert_eq!(model.crypto_percent(200, 100), 99);
+        assert_eq!(model.crypto_percent(0, 0), model.crypto_start());
+        assert!(model.crypto_percent(50, 100) > model.crypto_start());
+    }
+}
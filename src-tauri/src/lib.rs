@@ -12,23 +12,37 @@ pub mod security;
 
 // Import commands for registration
 use commands::{
-    batch_decrypt, batch_decrypt_archive, batch_encrypt, batch_encrypt_archive, decrypt_file,
-    encrypt_file, generate_key_file,
+    audit_vault, batch_decrypt, batch_decrypt_archive, batch_encrypt, batch_encrypt_archive,
+    benchmark, cancel_operation, check_key_file, check_truncation, cleanup_preview_files,
+    clear_recent_operations, decrypt_file, decrypt_to_temp_and_open, describe_file, encrypt_file,
+    encrypt_tree, estimate_duration, export_recovery_blob, generate_key_file,
+    generate_key_file_base64, generate_key_file_from_seed, get_recent_operations,
+    import_recovery_blob, list_archive_contents, set_compression, validate_encrypted_archive,
+    verify_batch, wipe_secrets, RecentOperations,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
-/// Build and run the Tauri application.
+/// Build and run the Tauri application, panicking if it fails to start.
 ///
-/// This function registers plugins and IPC commands, then starts the Tauri runtime.
 /// The desktop binary (`src-tauri/src/main.rs`) delegates to this so the setup lives
-/// in one place.
+/// in one place. Use [`try_run`] to handle startup failures instead.
 pub fn run() {
-    tauri::Builder::default()
+    try_run().expect("error while running tauri application");
+}
+
+/// Build and run the Tauri application.
+///
+/// This function registers plugins and IPC commands, then starts the Tauri runtime.
+/// Returns the error if the application cannot be built (e.g. a plugin fails to
+/// initialize), for embedders that want to report it rather than panic.
+pub fn try_run() -> Result<(), tauri::Error> {
+    let app = tauri::Builder::default()
         // Register plugins
         .plugin(tauri_plugin_dialog::init()) // File dialogs (open/save)
         .plugin(tauri_plugin_store::Builder::default().build()) // Persistent settings storage
         .plugin(tauri_plugin_os::init()) // OS/platform detection
         .plugin(tauri_plugin_opener::init()) // Open URLs in default browser
+        .manage(RecentOperations::default()) // Recently processed files
         .setup(|app| {
             // Setup logging in debug mode
             if cfg!(debug_assertions) {
@@ -51,14 +65,41 @@ pub fn run() {
         })
         // Register Tauri commands that can be called from the frontend
         .invoke_handler(tauri::generate_handler![
-            encrypt_file,          // Streaming encryption (all files)
-            decrypt_file,          // Streaming decryption (all files)
-            batch_encrypt,         // Batch encrypt multiple files
-            batch_decrypt,         // Batch decrypt multiple files
-            batch_encrypt_archive, // Batch encrypt as single archive
-            batch_decrypt_archive, // Batch decrypt archive
-            generate_key_file,     // Generate a key file for two-factor encryption
+            encrypt_file,                // Streaming encryption (all files)
+            decrypt_file,                // Streaming decryption (all files)
+            batch_encrypt,               // Batch encrypt multiple files
+            batch_decrypt,               // Batch decrypt multiple files
+            verify_batch,                // Check encrypted files decrypt, writing nothing
+            encrypt_tree,                // Encrypt a directory tree into a mirrored output tree
+            describe_file,               // Header report for a "Details" panel (no password)
+            check_truncation,            // Password-free check that all chunks are present
+            audit_vault,                 // Which files open with a password; weak KDF params
+            batch_encrypt_archive,       // Batch encrypt as single archive
+            batch_decrypt_archive,       // Batch decrypt archive
+            generate_key_file,           // Generate a key file for two-factor encryption
+            generate_key_file_base64,    // Random key file contents as base64 (no disk write)
+            generate_key_file_from_seed, // Derive a recoverable key file from a seed phrase
+            check_key_file,              // Advisory low-entropy check for a selected key file
+            export_recovery_blob,        // Encode a small secret for a QR code / paper backup
+            import_recovery_blob,        // Decode a recovery blob back to bytes
+            decrypt_to_temp_and_open,    // Decrypt to a private temp file and open it
+            benchmark,                   // Measure encrypt/decrypt throughput in memory
+            estimate_duration,           // Predict encryption time for a file (ms)
+            validate_encrypted_archive,  // Dry-run safety report for an encrypted archive
+            list_archive_contents,       // Entries of an encrypted archive, grouped by extension
+            set_compression,             // Recompress an encrypted file in place
+            cancel_operation,            // Cancel running encrypt/decrypt operations
+            wipe_secrets,                // Panic button: cancel everything, shred previews
+            get_recent_operations,       // Recently encrypted/decrypted files (no passwords)
+            clear_recent_operations,     // Forget the recent files list
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())?;
+
+    app.run(|_app, event| {
+        // Shred any decrypted previews that are still on disk
+        if let tauri::RunEvent::Exit = event {
+            cleanup_preview_files();
+        }
+    });
+    Ok(())
 }
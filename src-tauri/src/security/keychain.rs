@@ -0,0 +1,90 @@
+// security/keychain.rs - OS Keychain Integration
+//
+// Stores and retrieves passwords through the platform credential store
+// (macOS Keychain, Windows Credential Manager, Linux kernel keyring) via the
+// `keyring` crate. Only compiled with the `keychain` feature.
+//
+// Passwords are wrapped in `Password` as soon as they are read so they are
+// zeroized on drop, and they are never logged.
+
+use keyring::Entry;
+
+use crate::crypto::Password;
+use crate::error::{CryptoError, CryptoResult};
+
+/// Service name under which all FileCrypter entries are stored
+const KEYCHAIN_SERVICE: &str = "com.novafluxx.filecrypter";
+
+/// Open the keychain entry for `label`
+fn entry(label: &str) -> CryptoResult<Entry> {
+    if label.trim().is_empty() {
+        return Err(CryptoError::KeychainError(
+            "Keychain label cannot be empty".to_string(),
+        ));
+    }
+    Entry::new(KEYCHAIN_SERVICE, label).map_err(keychain_error)
+}
+
+fn keychain_error(err: keyring::Error) -> CryptoError {
+    match err {
+        keyring::Error::NoEntry => {
+            CryptoError::KeychainError("No password stored under this label".to_string())
+        }
+        other => CryptoError::KeychainError(other.to_string()),
+    }
+}
+
+/// Store `password` in the OS keychain under `label`, replacing any existing entry.
+pub fn store_password_in_keychain(label: &str, password: &Password) -> CryptoResult<()> {
+    if password.is_empty() {
+        return Err(CryptoError::FormatError(
+            "Password cannot be empty".to_string(),
+        ));
+    }
+    entry(label)?
+        .set_password(password.as_str())
+        .map_err(keychain_error)
+}
+
+/// Retrieve the password stored under `label`.
+pub fn get_password_from_keychain(label: &str) -> CryptoResult<Password> {
+    let password = entry(label)?.get_password().map_err(keychain_error)?;
+    Ok(Password::new(password))
+}
+
+/// Remove the password stored under `label`.
+pub fn delete_password_from_keychain(label: &str) -> CryptoResult<()> {
+    entry(label)?.delete_credential().map_err(keychain_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a reachable OS keychain; run with --ignored"]
+    fn test_keychain_roundtrip() {
+        let label = format!("filecrypter-test-{}", std::process::id());
+        let password = Password::new("keychain test password".to_string());
+
+        store_password_in_keychain(&label, &password).unwrap();
+
+        let retrieved = get_password_from_keychain(&label).unwrap();
+        assert_eq!(retrieved.as_str(), password.as_str());
+
+        delete_password_from_keychain(&label).unwrap();
+        assert!(matches!(
+            get_password_from_keychain(&label),
+            Err(CryptoError::KeychainError(_))
+        ));
+    }
+
+    #[test]
+    fn test_keychain_rejects_empty_label() {
+        let password = Password::new("pw".to_string());
+        assert!(matches!(
+            store_password_in_keychain("  ", &password),
+            Err(CryptoError::KeychainError(_))
+        ));
+    }
+}
@@ -10,14 +10,21 @@
 // without sprinkling `#[cfg(windows)]` throughout the codebase, so we provide:
 // - Real implementations on Windows (`windows_acl`).
 // - Small, safe stubs on non-Windows targets.
+//
+// With the `keychain` feature, `keychain` stores passwords in the OS credential store.
 
-use std::fs;
+use std::fs::{self, File};
+use std::io::{Seek, SeekFrom, Write};
 use std::path::Path;
 
-use tempfile::NamedTempFile;
+use rand::{rngs::SysRng, TryRng};
+use tempfile::{NamedTempFile, PersistError};
 
 use crate::error::{CryptoError, CryptoResult};
 
+#[cfg(feature = "keychain")]
+pub mod keychain;
+
 #[cfg(windows)]
 pub mod windows_acl;
 
@@ -53,6 +60,100 @@ pub fn create_secure_file<P: AsRef<std::path::Path>>(
         .open(path)
 }
 
+/// Buffer size used when overwriting files in `secure_delete` (64 KB)
+const SHRED_BUFFER_SIZE: usize = 64 * 1024;
+
+/// What each overwrite pass of [`secure_delete`] writes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShredPattern {
+    /// Every pass writes zeros
+    Zeros,
+    /// Every pass writes fresh random bytes
+    #[default]
+    Random,
+    /// Passes cycle through zeros, ones (0xFF), then random bytes, as in the
+    /// old DoD 5220.22-M guideline (use 3 passes for the full sequence)
+    DoD,
+}
+
+/// Fill written by a single overwrite pass
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PassFill {
+    Byte(u8),
+    Random,
+}
+
+impl ShredPattern {
+    fn fill_for_pass(self, pass: u32) -> PassFill {
+        match self {
+            ShredPattern::Zeros => PassFill::Byte(0x00),
+            ShredPattern::Random => PassFill::Random,
+            ShredPattern::DoD => match pass % 3 {
+                0 => PassFill::Byte(0x00),
+                1 => PassFill::Byte(0xFF),
+                _ => PassFill::Random,
+            },
+        }
+    }
+}
+
+/// Overwrite a file's contents with zeros, sync to disk, then delete it.
+///
+/// Equivalent to `secure_delete(path, 1, ShredPattern::Zeros)`.
+pub fn shred_file(path: &Path) -> Result<(), std::io::Error> {
+    secure_delete(path, 1, ShredPattern::Zeros)
+}
+
+/// Overwrite a file `passes` times (at least once), then delete it.
+///
+/// Each pass is synced to disk before the next starts. A single random pass
+/// is enough for modern drives; extra passes only matter for old magnetic
+/// media. On SSDs, journaling and copy-on-write filesystems, wear leveling and
+/// remapping may keep old blocks around no matter how many passes are made,
+/// so this is best-effort. Symlinks and non-regular files are removed without
+/// being followed.
+pub fn secure_delete(
+    path: &Path,
+    passes: u32,
+    pattern: ShredPattern,
+) -> Result<(), std::io::Error> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.file_type().is_file() {
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        for pass in 0..passes.max(1) {
+            overwrite_pass(&mut file, metadata.len(), pattern.fill_for_pass(pass))?;
+        }
+    }
+
+    fs::remove_file(path)
+}
+
+/// Overwrite the first `len` bytes of an open file with zeros and sync
+fn overwrite_with_zeros(file: &mut File, len: u64) -> Result<(), std::io::Error> {
+    overwrite_pass(file, len, PassFill::Byte(0x00))
+}
+
+/// Overwrite the first `len` bytes of an open file with `fill` and sync
+fn overwrite_pass(file: &mut File, len: u64, fill: PassFill) -> Result<(), std::io::Error> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut buffer = vec![0u8; SHRED_BUFFER_SIZE];
+    if let PassFill::Byte(byte) = fill {
+        buffer.fill(byte);
+    }
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(SHRED_BUFFER_SIZE as u64) as usize;
+        if fill == PassFill::Random {
+            SysRng
+                .try_fill_bytes(&mut buffer[..chunk])
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+        }
+        file.write_all(&buffer[..chunk])?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()
+}
+
 /// Create a temporary file with restrictive permissions (owner read/write only).
 ///
 /// The file is created in the specified parent directory. On Unix, permissions are set to 0o600.
@@ -82,3 +183,243 @@ pub fn create_secure_tempfile(parent: &Path) -> CryptoResult<NamedTempFile> {
 
     Ok(temp_file)
 }
+
+/// Flush the directory entry for `path` to disk after it was renamed into place.
+///
+/// A rename is only durable once its parent directory is synced; without
+/// this a crash shortly after can lose the new name even though the file
+/// data itself reached the disk. Directories cannot be synced this way on
+/// Windows, where this is a no-op.
+pub fn sync_parent_dir(path: &Path) -> Result<(), std::io::Error> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Total rename attempts in `persist_tempfile` when the target is briefly locked
+const PERSIST_ATTEMPTS: u32 = 5;
+
+/// Pause between rename attempts in `persist_tempfile`
+const PERSIST_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Move a temporary file into place, retrying transient failures on Windows.
+///
+/// Antivirus scanners and the search indexer can hold a freshly written target
+/// open for a moment, which makes the rename fail with a sharing violation.
+/// Those errors are retried a few times before giving up; every other error
+/// (and every error on other platforms) is returned immediately. On failure
+/// the temp file is handed back in the `PersistError` for cleanup.
+pub fn persist_tempfile(temp_file: NamedTempFile, path: &Path) -> Result<File, PersistError> {
+    let mut temp_file = temp_file;
+    let mut attempt = 1;
+    loop {
+        match temp_file.persist(path) {
+            Ok(file) => return Ok(file),
+            Err(err) if attempt < PERSIST_ATTEMPTS && is_transient_rename_error(&err.error) => {
+                log::debug!(
+                    "Rename to {} blocked, retrying: {}",
+                    path.display(),
+                    err.error
+                );
+                attempt += 1;
+                std::thread::sleep(PERSIST_RETRY_DELAY);
+                temp_file = err.file;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Whether a rename failure is likely caused by another process briefly
+/// holding the target (Windows sharing/lock violations, access denied)
+#[cfg(windows)]
+fn is_transient_rename_error(err: &std::io::Error) -> bool {
+    /// ERROR_SHARING_VIOLATION
+    const SHARING_VIOLATION: i32 = 32;
+    /// ERROR_LOCK_VIOLATION
+    const LOCK_VIOLATION: i32 = 33;
+
+    err.kind() == std::io::ErrorKind::PermissionDenied
+        || matches!(err.raw_os_error(), Some(SHARING_VIOLATION | LOCK_VIOLATION))
+}
+
+#[cfg(not(windows))]
+fn is_transient_rename_error(_err: &std::io::Error) -> bool {
+    false
+}
+
+/// Secure temporary file whose contents are zeroed before it is removed.
+///
+/// Used for decrypted output: if the operation fails (or the file is dropped
+/// for any other reason before `persist`), the partial plaintext is
+/// overwritten rather than just unlinked. Same best-effort caveats as
+/// [`shred_file`].
+pub struct ShreddingTempFile {
+    inner: Option<NamedTempFile>,
+}
+
+impl ShreddingTempFile {
+    /// The underlying file, for writing
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.named().as_file_mut()
+    }
+
+    /// Current path of the temporary file
+    pub fn path(&self) -> &Path {
+        self.inner
+            .as_ref()
+            .expect("temp file present until persisted")
+            .path()
+    }
+
+    /// Atomically move the file to `path`; on failure it is shredded
+    pub fn persist<P: AsRef<Path>>(mut self, path: P) -> CryptoResult<()> {
+        let file = self
+            .inner
+            .take()
+            .expect("temp file present until persisted");
+        match persist_tempfile(file, path.as_ref()) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                // Dropping `self` shreds the file we hand back
+                self.inner = Some(err.file);
+                Err(CryptoError::Io(err.error))
+            }
+        }
+    }
+
+    fn named(&mut self) -> &mut NamedTempFile {
+        self.inner
+            .as_mut()
+            .expect("temp file present until persisted")
+    }
+}
+
+impl Drop for ShreddingTempFile {
+    fn drop(&mut self) {
+        if let Some(mut file) = self.inner.take() {
+            let file = file.as_file_mut();
+            if let Ok(metadata) = file.metadata() {
+                if let Err(err) = overwrite_with_zeros(file, metadata.len()) {
+                    log::warn!("Failed to overwrite temporary file: {}", err);
+                }
+            }
+            // NamedTempFile removes the file when dropped here
+        }
+    }
+}
+
+/// Create a [`ShreddingTempFile`] with restrictive permissions in `parent`.
+pub fn create_shredding_tempfile(parent: &Path) -> CryptoResult<ShreddingTempFile> {
+    Ok(ShreddingTempFile {
+        inner: Some(create_secure_tempfile(parent)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_shredding_tempfile_zeroes_contents_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut temp = create_shredding_tempfile(dir.path()).unwrap();
+        temp.as_file_mut().write_all(b"secret plaintext").unwrap();
+
+        // A second link keeps the inode readable after the temp file is removed
+        let witness = dir.path().join("witness");
+        fs::hard_link(temp.path(), &witness).unwrap();
+        drop(temp);
+
+        assert_eq!(fs::read(&witness).unwrap(), vec![0u8; 16]);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_secure_delete_dod_passes_change_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.txt");
+        let original = b"secret plaintext".repeat(100);
+        fs::write(&path, &original).unwrap();
+
+        // A second link keeps the inode readable between passes
+        let witness = dir.path().join("witness");
+        fs::hard_link(&path, &witness).unwrap();
+
+        let len = original.len() as u64;
+        let mut file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let mut previous = original.clone();
+        for pass in 0..3 {
+            overwrite_pass(&mut file, len, ShredPattern::DoD.fill_for_pass(pass)).unwrap();
+            let current = fs::read(&witness).unwrap();
+            assert_eq!(current.len(), original.len());
+            assert_ne!(current, original);
+            assert_ne!(current, previous);
+            previous = current;
+        }
+        drop(file);
+
+        secure_delete(&path, 3, ShredPattern::DoD).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_secure_delete_removes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.txt");
+        fs::write(&path, b"secret plaintext").unwrap();
+
+        secure_delete(&path, 0, ShredPattern::default()).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_shredding_tempfile_persist_keeps_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out.txt");
+        let mut temp = create_shredding_tempfile(dir.path()).unwrap();
+        temp.as_file_mut().write_all(b"kept").unwrap();
+        temp.persist(&target).unwrap();
+
+        assert_eq!(fs::read(&target).unwrap(), b"kept");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_persist_tempfile_retries_transient_lock() {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("locked.txt");
+        fs::write(&target, b"old").unwrap();
+
+        // Hold the target without FILE_SHARE_DELETE, like a scanner would,
+        // and release it shortly after the first rename attempt fails
+        let holder = fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0)
+            .open(&target)
+            .unwrap();
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            drop(holder);
+        });
+
+        let mut temp = create_secure_tempfile(dir.path()).unwrap();
+        temp.write_all(b"new").unwrap();
+        persist_tempfile(temp, &target).unwrap();
+        release.join().unwrap();
+
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+    }
+}
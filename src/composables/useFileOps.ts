@@ -95,14 +95,16 @@ export function useFileOps() {
    * Requirements:
    * - Input file must be selected
    * - Output path must be set
-   * - Password must be at least MIN_PASSWORD_LENGTH characters (recommended minimum)
+   * - Password must be at least MIN_PASSWORD_LENGTH characters (recommended minimum),
+   *   or empty when a key file is selected (key-file-only mode)
    * - Not currently processing
    */
   const isEncryptFormValid = computed(() => {
+    const keyFileOnly = password.value.length === 0 && keyFilePath.value.length > 0;
     return (
       inputPath.value.length > 0 &&
       outputPath.value.length > 0 &&
-      password.value.length >= MIN_PASSWORD_LENGTH &&
+      (password.value.length >= MIN_PASSWORD_LENGTH || keyFileOnly) &&
       !isProcessing.value
     );
   });
@@ -120,7 +122,8 @@ export function useFileOps() {
   /**
    * Check if the decrypt form is valid and ready to submit
    *
-   * For decryption, we only require a non-empty password because:
+   * For decryption, we only require a non-empty password (or a key file, for
+   * key-file-only files) because:
    * - The cryptographic layer validates the password (wrong password = auth failure)
    * - Files may have been encrypted with different password policies
    * - Better UX: crypto error "wrong password" vs frontend validation error
@@ -129,7 +132,7 @@ export function useFileOps() {
     return (
       inputPath.value.length > 0 &&
       outputPath.value.length > 0 &&
-      password.value.length > 0 &&
+      (password.value.length > 0 || keyFilePath.value.length > 0) &&
       !isProcessing.value
     );
   });
@@ -251,8 +254,16 @@ export function useFileOps() {
         keyFilePath.value || undefined
       );
 
-      // Success!
-      showStatus(result.message, 'success');
+      // Success! Still point out a file that was already encrypted
+      if (result.already_encrypted) {
+        showStatus(
+          `${result.message}. Note: the input was already an encrypted file.`,
+          'info',
+          0
+        );
+      } else {
+        showStatus(result.message, 'success');
+      }
       outputPath.value = result.output_path;
       lastSuccessfulOutputPath.value = result.output_path;
       lastSuccessfulUsedKeyFile.value = keyFilePath.value.length > 0;
@@ -315,7 +326,7 @@ export function useFileOps() {
       return true;
     } catch (error) {
       // Handle errors (most commonly: wrong password) with sanitized messages
-      const errorMessage = sanitizeErrorMessage(error);
+      const errorMessage = sanitizeErrorMessage(error, keyFilePath.value.length > 0);
       showStatus(errorMessage, 'error', 0);
       lastSuccessfulOutputPath.value = '';
       lastSuccessfulUsedKeyFile.value = false;
@@ -11,7 +11,21 @@
 
 import { invoke } from '@tauri-apps/api/core';
 import { open, save } from '@tauri-apps/plugin-dialog';
-import type { CryptoResponse, BatchResult, ArchiveResult } from '../types/crypto';
+import type {
+  CryptoResponse,
+  BatchResult,
+  ArchiveResult,
+  BenchmarkResult,
+  ArchiveListing,
+  ArchiveValidation,
+  CipherAlgorithm,
+  KdfParams,
+  ProgressInterval,
+  RecentOperation,
+  TruncationReport,
+  VaultAudit,
+} from '../types/crypto';
+import { errorToString } from '../utils/errorSanitizer';
 
 /**
  * Composable for Tauri-specific operations
@@ -29,7 +43,7 @@ export function useTauri() {
    * 1. Reads the input file
    * 2. Optionally compresses the data using ZSTD
    * 3. Derives encryption key from password using Argon2id
-   * 4. Encrypts with AES-256-GCM
+   * 4. Encrypts with AES-256-GCM (or ChaCha20-Poly1305)
    * 5. Writes encrypted file
    *
    * @param inputPath - Path to the file to encrypt
@@ -38,6 +52,20 @@ export function useTauri() {
    * @param allowOverwrite - Allow overwriting existing files (default: false)
    * @param compressionEnabled - Enable ZSTD compression (default: false)
    * @param compressionLevel - ZSTD compression level 1-22 (default: 3)
+   * @param keyFilePath - Optional key file for two-factor encryption
+   * @param saltLength - Optional salt length in bytes, 16-64 (default: 16)
+   * @param trimPassword - Ignore trailing whitespace in the password (default: false)
+   * @param skipSpaceCheck - Skip the free disk space preflight (default: false)
+   * @param kdfParams - Optional Argon2id parameters (default: recommended settings)
+   * @param preserveMetadata - Record the owner permission bits, e.g. executable, and extended
+   *   attributes when built with the `xattrs` feature (default: false)
+   * @param durable - Sync the output to disk before returning; slower (default: false)
+   * @param keyFileCommitment - Record a key file commitment so a wrong key file is reported
+   *   separately from a wrong password; only for generated key files (default: false)
+   * @param normalizePassword - Convert the password to Unicode NFC so composed and decomposed
+   *   accents match (default: false)
+   * @param cipher - Chunk cipher; ChaCha20-Poly1305 is faster without AES hardware
+   *   (default: 'aes-256-gcm')
    * @returns Promise resolving to message + resolved output path
    * @throws Error if encryption fails (wrong path, permission denied, etc.)
    */
@@ -48,7 +76,16 @@ export function useTauri() {
     allowOverwrite = false,
     compressionEnabled = false,
     compressionLevel = 3,
-    keyFilePath?: string
+    keyFilePath?: string,
+    saltLength?: number,
+    trimPassword = false,
+    skipSpaceCheck = false,
+    kdfParams?: KdfParams,
+    preserveMetadata = false,
+    durable = false,
+    keyFileCommitment = false,
+    normalizePassword = false,
+    cipher: CipherAlgorithm = 'aes-256-gcm'
   ): Promise<CryptoResponse> {
     try {
       // invoke() is Tauri's IPC mechanism - it calls the Rust function
@@ -61,11 +98,20 @@ export function useTauri() {
         compressionEnabled,
         compressionLevel,
         keyFilePath: keyFilePath || null,
+        saltLength: saltLength ?? null,
+        trimPassword,
+        normalizePassword,
+        keyFileCommitment,
+        skipSpaceCheck,
+        kdfParams: kdfParams ?? null,
+        preserveMetadata,
+        durable,
+        cipher,
       });
       return result;
     } catch (error) {
       // Tauri errors are serialized from Rust CryptoError enum
-      throw new Error(`Encryption failed: ${error}`, { cause: error });
+      throw new Error(`Encryption failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -80,9 +126,12 @@ export function useTauri() {
    * 5. Writes decrypted file
    *
    * @param inputPath - Path to the encrypted file
-   * @param outputPath - Path where decrypted file will be saved
+   * @param outputPath - Path where decrypted file will be saved, or an existing directory
    * @param password - User's password (must match encryption password)
    * @param allowOverwrite - Allow overwriting existing files (default: false)
+   * @param keyFilePath - Optional key file used during encryption
+   * @param preserveMetadata - Restore owner permission bits recorded at encryption (default: false)
+   * @param durable - Sync the output to disk before returning; slower (default: false)
    * @returns Promise resolving to message + resolved output path
    * @throws Error if decryption fails (wrong password, corrupted file, etc.)
    */
@@ -91,7 +140,9 @@ export function useTauri() {
     outputPath: string,
     password: string,
     allowOverwrite = false,
-    keyFilePath?: string
+    keyFilePath?: string,
+    preserveMetadata = false,
+    durable = false
   ): Promise<CryptoResponse> {
     try {
       const result = await invoke<CryptoResponse>('decrypt_file', {
@@ -100,11 +151,13 @@ export function useTauri() {
         password,
         allowOverwrite,
         keyFilePath: keyFilePath || null,
+        preserveMetadata,
+        durable,
       });
       return result;
     } catch (error) {
       // Error will include descriptive message from Rust
-      throw new Error(`Decryption failed: ${error}`, { cause: error });
+      throw new Error(`Decryption failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -224,6 +277,11 @@ export function useTauri() {
    * @param outputDir - Directory where encrypted files will be saved
    * @param password - Password for encryption
    * @param allowOverwrite - Allow overwriting existing files (default: false)
+   * @param keyFilePath - Optional key file for two-factor encryption
+   * @param excludePatterns - Optional glob patterns for files to skip (e.g. '*.tmp')
+   * @param maxConcurrentFiles - Files processed at once (default: 1 = sequential)
+   * @param progressInterval - Optional throttling of per-file progress events
+   * @param transactional - All-or-nothing: roll back every output if any file fails
    * @returns Promise resolving to BatchResult
    */
   async function batchEncrypt(
@@ -231,7 +289,11 @@ export function useTauri() {
     outputDir: string,
     password: string,
     allowOverwrite = false,
-    keyFilePath?: string
+    keyFilePath?: string,
+    excludePatterns?: string[],
+    maxConcurrentFiles?: number,
+    progressInterval?: ProgressInterval,
+    transactional = false
   ): Promise<BatchResult> {
     try {
       const result = await invoke<BatchResult>('batch_encrypt', {
@@ -240,10 +302,14 @@ export function useTauri() {
         password,
         allowOverwrite,
         keyFilePath: keyFilePath || null,
+        excludePatterns: excludePatterns ?? null,
+        maxConcurrentFiles: maxConcurrentFiles ?? null,
+        progressInterval: progressInterval ?? null,
+        transactional,
       });
       return result;
     } catch (error) {
-      throw new Error(`Batch encryption failed: ${error}`, { cause: error });
+      throw new Error(`Batch encryption failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -254,6 +320,10 @@ export function useTauri() {
    * @param outputDir - Directory where decrypted files will be saved
    * @param password - Password for decryption
    * @param allowOverwrite - Allow overwriting existing files (default: false)
+   * @param keyFilePath - Optional key file used during encryption
+   * @param maxConcurrentFiles - Files processed at once (default: 1 = sequential)
+   * @param progressInterval - Optional throttling of per-file progress events
+   * @param transactional - All-or-nothing: roll back every output if any file fails
    * @returns Promise resolving to BatchResult
    */
   async function batchDecrypt(
@@ -261,7 +331,10 @@ export function useTauri() {
     outputDir: string,
     password: string,
     allowOverwrite = false,
-    keyFilePath?: string
+    keyFilePath?: string,
+    maxConcurrentFiles?: number,
+    progressInterval?: ProgressInterval,
+    transactional = false
   ): Promise<BatchResult> {
     try {
       const result = await invoke<BatchResult>('batch_decrypt', {
@@ -270,10 +343,84 @@ export function useTauri() {
         password,
         allowOverwrite,
         keyFilePath: keyFilePath || null,
+        maxConcurrentFiles: maxConcurrentFiles ?? null,
+        progressInterval: progressInterval ?? null,
+        transactional,
+      });
+      return result;
+    } catch (error) {
+      throw new Error(`Batch decryption failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Verify multiple encrypted files decrypt correctly without writing output
+   *
+   * @param inputPaths - Array of encrypted file paths
+   * @param password - Password for decryption
+   * @param keyFilePath - Optional key file used during encryption
+   * @param quick - Only check the first chunk of each file (default: false)
+   * @param maxConcurrentFiles - Files processed at once (default: 1 = sequential)
+   * @param progressInterval - Optional throttling of per-file progress events
+   * @returns Promise resolving to BatchResult (output paths are always null)
+   */
+  async function verifyBatch(
+    inputPaths: string[],
+    password: string,
+    keyFilePath?: string,
+    quick = false,
+    maxConcurrentFiles?: number,
+    progressInterval?: ProgressInterval
+  ): Promise<BatchResult> {
+    try {
+      const result = await invoke<BatchResult>('verify_batch', {
+        inputPaths,
+        password,
+        keyFilePath: keyFilePath || null,
+        quick,
+        maxConcurrentFiles: maxConcurrentFiles ?? null,
+        progressInterval: progressInterval ?? null,
       });
       return result;
     } catch (error) {
-      throw new Error(`Batch decryption failed: ${error}`, { cause: error });
+      throw new Error(`Batch verification failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Encrypt each file in a directory tree into an output tree mirroring its structure
+   *
+   * @param inputDir - Directory whose files will be encrypted
+   * @param outputDir - Existing directory to create the mirrored tree in
+   * @param password - Password for encryption (used for all files)
+   * @param keyFilePath - Optional key file for two-factor encryption
+   * @param recurse - Descend into subdirectories (default: true)
+   * @param maxConcurrentFiles - Files processed at once (default: 1 = sequential)
+   * @param progressInterval - Optional throttling of per-file progress events
+   * @returns Promise resolving to BatchResult
+   */
+  async function encryptTree(
+    inputDir: string,
+    outputDir: string,
+    password: string,
+    keyFilePath?: string,
+    recurse = true,
+    maxConcurrentFiles?: number,
+    progressInterval?: ProgressInterval
+  ): Promise<BatchResult> {
+    try {
+      const result = await invoke<BatchResult>('encrypt_tree', {
+        inputDir,
+        outputDir,
+        password,
+        keyFilePath: keyFilePath || null,
+        recurse,
+        maxConcurrentFiles: maxConcurrentFiles ?? null,
+        progressInterval: progressInterval ?? null,
+      });
+      return result;
+    } catch (error) {
+      throw new Error(`Tree encryption failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -287,6 +434,8 @@ export function useTauri() {
    * @param password - Password for encryption
    * @param archiveName - Optional custom name for the archive (without extension)
    * @param allowOverwrite - Allow overwriting existing files (default: false)
+   * @param keyFilePath - Optional key file for two-factor encryption
+   * @param excludePatterns - Optional glob patterns for files to leave out (e.g. '.DS_Store')
    * @returns Promise resolving to ArchiveResult
    */
   async function batchEncryptArchive(
@@ -295,7 +444,8 @@ export function useTauri() {
     password: string,
     archiveName?: string,
     allowOverwrite = false,
-    keyFilePath?: string
+    keyFilePath?: string,
+    excludePatterns?: string[]
   ): Promise<ArchiveResult> {
     try {
       const result = await invoke<ArchiveResult>('batch_encrypt_archive', {
@@ -305,10 +455,11 @@ export function useTauri() {
         archiveName: archiveName || null,
         allowOverwrite,
         keyFilePath: keyFilePath || null,
+        excludePatterns: excludePatterns ?? null,
       });
       return result;
     } catch (error) {
-      throw new Error(`Archive encryption failed: ${error}`, { cause: error });
+      throw new Error(`Archive encryption failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -321,6 +472,8 @@ export function useTauri() {
    * @param outputDir - Directory where extracted files will be saved
    * @param password - Password for decryption
    * @param allowOverwrite - Allow overwriting existing files (default: false)
+   * @param keyFilePath - Optional key file used during encryption
+   * @param maxExtractedSize - Optional absolute cap on extracted bytes (default: 10 GB)
    * @returns Promise resolving to ArchiveResult
    */
   async function batchDecryptArchive(
@@ -328,7 +481,8 @@ export function useTauri() {
     outputDir: string,
     password: string,
     allowOverwrite = false,
-    keyFilePath?: string
+    keyFilePath?: string,
+    maxExtractedSize?: number
   ): Promise<ArchiveResult> {
     try {
       const result = await invoke<ArchiveResult>('batch_decrypt_archive', {
@@ -337,10 +491,11 @@ export function useTauri() {
         password,
         allowOverwrite,
         keyFilePath: keyFilePath || null,
+        maxExtractedSize: maxExtractedSize ?? null,
       });
       return result;
     } catch (error) {
-      throw new Error(`Archive decryption failed: ${error}`, { cause: error });
+      throw new Error(`Archive decryption failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -357,7 +512,359 @@ export function useTauri() {
       });
       return result;
     } catch (error) {
-      throw new Error(`Key file generation failed: ${error}`, { cause: error });
+      throw new Error(`Key file generation failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Generate key file contents without writing them to disk
+   *
+   * Useful for displaying the key as a QR code or storing it in a secret manager.
+   *
+   * @returns Promise resolving to 32 random bytes, base64-encoded
+   */
+  async function generateKeyFileBase64(): Promise<string> {
+    try {
+      return await invoke<string>('generate_key_file_base64');
+    } catch (error) {
+      throw new Error(`Key file generation failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Derive a key file deterministically from a seed phrase
+   *
+   * The same seed always produces the same key file, so it can be recreated if lost.
+   * The seed should be treated like a second password.
+   *
+   * @param outputPath - Path where the key file will be saved
+   * @param seedPhrase - Memorized seed phrase
+   * @returns Promise resolving to success message
+   */
+  async function generateKeyFileFromSeed(
+    outputPath: string,
+    seedPhrase: string
+  ): Promise<CryptoResponse> {
+    try {
+      return await invoke<CryptoResponse>('generate_key_file_from_seed', {
+        outputPath,
+        seedPhrase,
+      });
+    } catch (error) {
+      throw new Error(`Key file generation failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Check a key file for suspiciously low entropy (all zeros, repeated bytes)
+   *
+   * This is advisory only; the key file can still be used.
+   *
+   * @param keyFilePath - Path to the selected key file
+   * @returns Promise resolving to a warning message, or null if the key file looks fine
+   */
+  async function checkKeyFile(keyFilePath: string): Promise<string | null> {
+    try {
+      return await invoke<string | null>('check_key_file', { keyFilePath });
+    } catch (error) {
+      throw new Error(`Key file check failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Encode a small secret (e.g. key file bytes) as a recovery blob
+   *
+   * The blob is base64 text sized to fit a QR code for a paper backup.
+   *
+   * @param data - Bytes to encode (at most 1 KB)
+   * @returns Promise resolving to the recovery blob
+   */
+  async function exportRecoveryBlob(data: Uint8Array): Promise<string> {
+    try {
+      return await invoke<string>('export_recovery_blob', { data: Array.from(data) });
+    } catch (error) {
+      throw new Error(`Recovery blob export failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Decode a recovery blob back to the original bytes
+   *
+   * @param blob - Recovery blob text; whitespace and line breaks are ignored
+   * @returns Promise resolving to the decoded bytes
+   */
+  async function importRecoveryBlob(blob: string): Promise<Uint8Array> {
+    try {
+      const data = await invoke<number[]>('import_recovery_blob', { blob });
+      return new Uint8Array(data);
+    } catch (error) {
+      throw new Error(`Recovery blob import failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Decrypt a file to a private temporary copy and open it with the OS default app
+   *
+   * The temporary copy is securely deleted after a timeout or when the app exits.
+   *
+   * @param inputPath - Path to the encrypted file
+   * @param password - User's password
+   * @param keyFilePath - Optional key file used during encryption
+   * @returns Promise resolving to message + temporary file path
+   */
+  async function decryptToTempAndOpen(
+    inputPath: string,
+    password: string,
+    keyFilePath?: string
+  ): Promise<CryptoResponse> {
+    try {
+      return await invoke<CryptoResponse>('decrypt_to_temp_and_open', {
+        inputPath,
+        password,
+        keyFilePath: keyFilePath || null,
+      });
+    } catch (error) {
+      throw new Error(`Decrypt and open failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Measure encryption/decryption throughput on random in-memory data
+   *
+   * Key derivation is timed separately (kdf_ms) and excluded from MB/s.
+   *
+   * @param sizeMb - Benchmark input size in MB (1-256)
+   * @param compressionEnabled - Whether to enable ZSTD compression
+   * @param chunkSize - Optional chunk size in bytes
+   * @returns Promise resolving to the benchmark result
+   */
+  async function benchmark(
+    sizeMb: number,
+    compressionEnabled = false,
+    chunkSize?: number
+  ): Promise<BenchmarkResult> {
+    try {
+      return await invoke<BenchmarkResult>('benchmark', {
+        sizeMb,
+        chunkSize: chunkSize ?? null,
+        compressionEnabled,
+      });
+    } catch (error) {
+      throw new Error(`Benchmark failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Describe an encrypted file for a details panel
+   *
+   * Only the header is read, so no password is needed.
+   *
+   * @param inputPath - Path to the encrypted file
+   * @returns Promise resolving to a multi-line `Field: value` report
+   */
+  async function describeFile(inputPath: string): Promise<string> {
+    try {
+      return await invoke<string>('describe_file', { inputPath });
+    } catch (error) {
+      throw new Error(`Describe failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Check that an encrypted file has all its chunks and nothing appended
+   *
+   * Reads only chunk lengths, so no password is needed; contents are not
+   * authenticated.
+   *
+   * @param inputPath - Path to the encrypted file
+   * @returns Promise resolving to the truncation report
+   */
+  async function checkTruncation(inputPath: string): Promise<TruncationReport> {
+    try {
+      return await invoke<TruncationReport>('check_truncation', { inputPath });
+    } catch (error) {
+      throw new Error(`Truncation check failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Check which encrypted files open with a password and which use weak KDF settings
+   *
+   * @param paths - Encrypted files to audit
+   * @param password - Password expected to open every file
+   * @param keyFilePath - Optional key file expected alongside the password
+   * @returns Promise resolving to per-file results and summary counts
+   */
+  async function auditVault(
+    paths: string[],
+    password: string,
+    keyFilePath?: string
+  ): Promise<VaultAudit> {
+    try {
+      return await invoke<VaultAudit>('audit_vault', {
+        paths,
+        password,
+        keyFilePath: keyFilePath || null,
+      });
+    } catch (error) {
+      throw new Error(`Vault audit failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Estimate how long encrypting a file will take
+   *
+   * Times one key derivation plus a small in-memory throughput probe and
+   * extrapolates to the file's size.
+   *
+   * @param inputPath - File that will be encrypted
+   * @param compressionEnabled - Whether ZSTD compression will be enabled
+   * @returns Promise resolving to the estimate in milliseconds
+   */
+  async function estimateDuration(
+    inputPath: string,
+    compressionEnabled = false
+  ): Promise<number> {
+    try {
+      return await invoke<number>('estimate_duration', {
+        inputPath,
+        compressionEnabled,
+      });
+    } catch (error) {
+      throw new Error(`Estimate failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Validate an encrypted archive without extracting it
+   *
+   * @param inputPath - Path to the encrypted archive
+   * @param password - Password for decryption
+   * @param keyFilePath - Optional key file for two-factor decryption
+   * @returns Promise resolving to the archive safety report
+   */
+  async function validateEncryptedArchive(
+    inputPath: string,
+    password: string,
+    keyFilePath?: string
+  ): Promise<ArchiveValidation> {
+    try {
+      return await invoke<ArchiveValidation>('validate_encrypted_archive', {
+        inputPath,
+        password,
+        keyFilePath: keyFilePath || null,
+      });
+    } catch (error) {
+      throw new Error(`Archive validation failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * List the contents of an encrypted archive without extracting it
+   *
+   * @param inputPath - Path to the encrypted archive
+   * @param password - Password for decryption
+   * @param keyFilePath - Optional key file for two-factor decryption
+   * @returns Promise resolving to the entries and per-extension summary
+   */
+  async function listArchiveContents(
+    inputPath: string,
+    password: string,
+    keyFilePath?: string
+  ): Promise<ArchiveListing> {
+    try {
+      return await invoke<ArchiveListing>('list_archive_contents', {
+        inputPath,
+        password,
+        keyFilePath: keyFilePath || null,
+      });
+    } catch (error) {
+      throw new Error(`Archive listing failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Recompress an encrypted file in place at a new ZSTD level
+   *
+   * @param inputPath - Encrypted file to rewrite
+   * @param password - Password the file was encrypted with
+   * @param newLevel - ZSTD compression level 1-22
+   * @param keyFilePath - Optional key file used during encryption
+   * @returns Promise resolving to message + file path
+   */
+  async function setCompression(
+    inputPath: string,
+    password: string,
+    newLevel: number,
+    keyFilePath?: string
+  ): Promise<CryptoResponse> {
+    try {
+      return await invoke<CryptoResponse>('set_compression', {
+        inputPath,
+        password,
+        keyFilePath: keyFilePath || null,
+        newLevel,
+      });
+    } catch (error) {
+      throw new Error(`Recompression failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Cancel running encrypt/decrypt operations
+   *
+   * The operations stop promptly (even during key derivation) and reject with
+   * a Cancelled error; no output file is written.
+   *
+   * @returns Promise resolving to the number of operations signalled
+   */
+  async function cancelOperation(): Promise<number> {
+    try {
+      return await invoke<number>('cancel_operation');
+    } catch (error) {
+      throw new Error(`Cancel failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Lock everything now: cancel running operations and shred decrypted previews
+   *
+   * The backend keeps no session password or key between commands, so this
+   * stops every operation that holds one and removes plaintext preview copies.
+   *
+   * @returns Promise resolving to the number of operations and previews wiped
+   */
+  async function wipeSecrets(): Promise<number> {
+    try {
+      return await invoke<number>('wipe_secrets');
+    } catch (error) {
+      throw new Error(`Wipe failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * List recently encrypted/decrypted files, newest first
+   *
+   * Kept in memory by the backend for the current session (at most 50 entries).
+   *
+   * @returns Promise resolving to the recent operations
+   */
+  async function getRecentOperations(): Promise<RecentOperation[]> {
+    try {
+      return await invoke<RecentOperation[]>('get_recent_operations');
+    } catch (error) {
+      throw new Error(`Loading recent files failed: ${errorToString(error)}`, { cause: error });
+    }
+  }
+
+  /**
+   * Forget the recent files list
+   */
+  async function clearRecentOperations(): Promise<void> {
+    try {
+      await invoke('clear_recent_operations');
+    } catch (error) {
+      throw new Error(`Clearing recent files failed: ${errorToString(error)}`, { cause: error });
     }
   }
 
@@ -371,8 +878,28 @@ export function useTauri() {
     selectDirectory,
     batchEncrypt,
     batchDecrypt,
+    verifyBatch,
+    encryptTree,
     batchEncryptArchive,
     batchDecryptArchive,
     generateKeyFile,
+    generateKeyFileBase64,
+    generateKeyFileFromSeed,
+    checkKeyFile,
+    exportRecoveryBlob,
+    importRecoveryBlob,
+    decryptToTempAndOpen,
+    benchmark,
+    describeFile,
+    checkTruncation,
+    auditVault,
+    estimateDuration,
+    validateEncryptedArchive,
+    listArchiveContents,
+    setCompression,
+    cancelOperation,
+    wipeSecrets,
+    getRecentOperations,
+    clearRecentOperations,
   };
 }
@@ -7,10 +7,30 @@
  * Response from encryption/decryption operations
  *
  * The Rust backend returns a message plus the resolved output path.
+ * `already_encrypted` is set by encryption when the input was itself a
+ * FileCrypter file (likely encrypted twice by mistake). `ciphertext_hash` is
+ * the hex BLAKE3 hash of the encrypted output (encryption only), for audit logs.
+ * `overhead` reports how much encryption grew (or compression shrank) the file.
  */
 export interface CryptoResponse {
   message: string;
   output_path: string;
+  already_encrypted: boolean;
+  ciphertext_hash: string | null;
+  overhead: SizeOverhead | null;
+}
+
+/**
+ * Size change from encrypting one file
+ *
+ * `overhead_bytes` and `overhead_percent` are negative when compression made
+ * the output smaller; `overhead_percent` is null for an empty input.
+ */
+export interface SizeOverhead {
+  input_size: number;
+  output_size: number;
+  overhead_bytes: number;
+  overhead_percent: number | null;
 }
 
 /**
@@ -39,6 +59,7 @@ export interface FileResult {
   output_path: string | null;
   success: boolean;
   error: string | null;
+  ciphertext_hash: string | null;
 }
 
 /**
@@ -48,12 +69,13 @@ export interface BatchResult {
   files: FileResult[];
   success_count: number;
   failed_count: number;
+  duplicates_skipped: number;
 }
 
 /**
  * Batch operation stage
  */
-export type BatchStage = 'encrypting' | 'decrypting' | 'complete';
+export type BatchStage = 'encrypting' | 'decrypting' | 'verifying' | 'complete';
 
 /**
  * Progress event for batch operations
@@ -66,6 +88,19 @@ export interface BatchProgress {
   percent: number;
 }
 
+/**
+ * Limits on how often per-file batch progress events are emitted.
+ * The final 'complete' event is always emitted.
+ */
+export interface ProgressInterval {
+  /** Emit at most once every this many files (0 = every file) */
+  files?: number;
+  /** Emit at most once every this many milliseconds (0 = no time limit) */
+  ms?: number;
+  /** Suppress per-file events entirely */
+  silent?: boolean;
+}
+
 /**
  * Archive operation phase
  */
@@ -92,7 +127,138 @@ export interface ArchiveResult {
   error: string | null;
 }
 
+/**
+ * Archive entry that would be refused during extraction
+ */
+export interface RejectedEntry {
+  path: string;
+  reason: string;
+}
+
+/**
+ * Physical layout of an encrypted file compared with its header
+ */
+export interface TruncationReport {
+  /** Chunks the header declares (null for streamed files) */
+  declared_chunks: number | null;
+  present_chunks: number;
+  file_size: number;
+  /** Lower bound when whole chunks are missing */
+  missing_bytes: number;
+  extra_bytes: number;
+}
+
+/**
+ * Dry-run safety report for an encrypted archive
+ */
+export interface ArchiveValidation {
+  total_entries: number;
+  total_size: number;
+  max_extracted_size: number;
+  exceeds_limit: boolean;
+  rejected_entries: RejectedEntry[];
+}
+
+/**
+ * How extraction would treat an archive entry
+ */
+export type ArchiveEntryKind = 'regular' | 'skipped' | 'rejected';
+
+/**
+ * One entry in an encrypted archive listing
+ */
+export interface ArchiveEntryInfo {
+  path: string;
+  kind: ArchiveEntryKind;
+  extension: string | null;
+  size: number;
+}
+
+/**
+ * Regular archive entries sharing one extension
+ */
+export interface ExtensionSummary {
+  extension: string | null;
+  count: number;
+  total_size: number;
+}
+
+/**
+ * Contents of an encrypted archive with a per-extension summary
+ */
+export interface ArchiveListing {
+  entries: ArchiveEntryInfo[];
+  by_extension: ExtensionSummary[];
+}
+
+/**
+ * Chunk cipher (mirrors Rust CipherAlgorithm)
+ */
+export type CipherAlgorithm = 'aes-256-gcm' | 'chacha20-poly1305';
+
+/**
+ * Argon2id key derivation parameters (mirrors Rust KdfParams)
+ */
+export interface KdfParams {
+  algorithm: 'argon2id';
+  memory_cost_kib: number;
+  time_cost: number;
+  parallelism: number;
+  key_length: number;
+  salt_length: number;
+}
+
+/**
+ * Audit result for one file (see audit_vault)
+ */
+export interface FileAudit {
+  path: string;
+  /** Whether the file opens with the given password and key file */
+  opens: boolean;
+  error: string | null;
+  /** Header KDF parameters, null if the header could not be read */
+  kdf_params: KdfParams | null;
+  /** Why the KDF parameters are weak, null if they are not */
+  weak_kdf: string | null;
+}
+
+/**
+ * Result of a vault audit
+ */
+export interface VaultAudit {
+  files: FileAudit[];
+  opens_count: number;
+  mismatched_count: number;
+  weak_kdf_count: number;
+}
+
 /**
  * Batch mode type
  */
 export type BatchMode = 'individual' | 'archive';
+
+/**
+ * Result of an in-memory throughput benchmark
+ */
+export interface BenchmarkResult {
+  size_mb: number;
+  chunk_size: number;
+  iterations: number;
+  kdf_ms: number;
+  encrypt_mb_per_sec: number;
+  decrypt_mb_per_sec: number;
+}
+
+/**
+ * A recently encrypted or decrypted file (never includes passwords)
+ *
+ * `timestamp` is in milliseconds since the Unix epoch; `output_path` is null
+ * when the operation failed.
+ */
+export interface RecentOperation {
+  input_path: string;
+  output_path: string | null;
+  operation: 'encrypt' | 'decrypt';
+  timestamp: number;
+  success: boolean;
+}
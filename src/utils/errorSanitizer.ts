@@ -14,15 +14,64 @@
 export const SAFE_ERROR_MESSAGES: Record<string, string> = {
   InvalidPassword: 'Incorrect password or corrupted file',
   FileNotFound: 'File could not be accessed',
+  IoPath: 'File could not be accessed',
   FileTooLarge: 'File is too large for this operation',
   TooManyFiles: 'Too many files selected for batch operation',
   InvalidPath: 'Invalid file path',
+  TruncatedFile: 'File is incomplete or truncated',
+  KeychainError: 'Password could not be read from or saved to the system keychain',
+  ReadOnlyTarget: 'Target file is read-only - choose another location or remove the read-only flag',
+  OutputLocked: 'Another operation is already writing this file - wait for it to finish',
+  Cancelled: 'Operation cancelled',
+  NotEncrypted: 'This file does not appear to be encrypted by FileCrypter',
+  OutOfSpace: 'Not enough free disk space for the output file',
+  KeyFileMismatch: 'This is not the key file used to encrypt the file',
   'encrypted with a key file': 'This file was encrypted with a key file — please provide it to decrypt',
   'Key file error': 'Key file is invalid or could not be read',
   permission: 'Permission denied - unable to access file',
   default: 'Operation failed - please try again',
 };
 
+/**
+ * Structured error payload returned by Rust commands.
+ * `code_num` is stable across releases; `code` is the variant name.
+ */
+export interface CryptoErrorPayload {
+  code: string;
+  code_num: number;
+  message: string;
+}
+
+function isCryptoErrorPayload(error: unknown): error is CryptoErrorPayload {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    'code' in error &&
+    'message' in error
+  );
+}
+
+/**
+ * Convert any thrown value (Error, backend error payload, string) to a string.
+ * Backend payloads are rendered as "<code>: <message>" so keyword matching
+ * works on both the variant name and the message text.
+ *
+ * @param error - The thrown value
+ * @returns A string representation suitable for logging or wrapping
+ */
+export function errorToString(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  if (isCryptoErrorPayload(error)) return `${error.code}: ${error.message}`;
+  return String(error);
+}
+
+/**
+ * Message for a failed decryption that used a key file. Password and key file
+ * are combined before key derivation, so the backend cannot tell which is wrong.
+ */
+export const INVALID_PASSWORD_OR_KEY_FILE_MESSAGE =
+  'Incorrect password or key file (both must match the ones used to encrypt), or corrupted file';
+
 /**
  * Sanitize error messages for user display.
  * Prevents information leakage by mapping backend errors to safe messages.
@@ -31,11 +80,16 @@ export const SAFE_ERROR_MESSAGES: Record<string, string> = {
  * them to strings before checking for known error keywords.
  *
  * @param error - The error to sanitize (can be Error instance, string, or any other type)
+ * @param keyFileUsed - Whether a key file was supplied (adds a hint to wrong-password errors)
  * @returns A safe, user-friendly error message
  */
-export function sanitizeErrorMessage(error: unknown): string {
+export function sanitizeErrorMessage(error: unknown, keyFileUsed = false): string {
   // Convert error to string to handle both Error instances and other types
-  const errStr = error instanceof Error ? error.message : String(error);
+  const errStr = errorToString(error);
+
+  if (keyFileUsed && errStr.includes('InvalidPassword')) {
+    return INVALID_PASSWORD_OR_KEY_FILE_MESSAGE;
+  }
 
   // Check for known error keywords
   for (const [key, safeMsg] of Object.entries(SAFE_ERROR_MESSAGES)) {